@@ -0,0 +1,162 @@
+//! Attribute macros of the [kaos](https://docs.rs/kaos) chaotic testing
+//! harness. They are re-exported by kaos and expand to its macros, use them
+//! through `kaos` rather than depending on this crate.
+
+extern crate proc_macro;
+
+use proc_macro::{Delimiter, Group, Ident, Literal, Punct, Spacing, Span, TokenStream, TokenTree};
+use std::iter::FromIterator;
+
+type Error = (Span, String);
+
+///
+/// Attribute to put a flunk point at the entry of a function
+///
+/// Entering the function evaluates `flunk!` named after the function, so the
+/// point of `fn connect` in module `net` is `net::connect`, and the name stays
+/// in sync when the function is renamed. `#[flunkable(name = "dial")]` names
+/// it otherwise and `#[flunkable(rate = 0.1)]` sets the probability it fails
+/// with, `1.0` by default. Methods of different types sharing a name share a
+/// point unless they are named. The expansion refers to the crate as `kaos`.
+#[proc_macro_attribute]
+pub fn flunkable(args: TokenStream, item: TokenStream) -> TokenStream {
+    match expand(args, item.clone()) {
+        Ok(expanded) => expanded,
+        Err((span, message)) => {
+            let mut expanded = compile_error(span, &message);
+            expanded.extend(item);
+            expanded
+        }
+    }
+}
+
+#[derive(Default)]
+struct Args {
+    name: Option<Literal>,
+    rate: Option<TokenStream>,
+}
+
+// `name = "..."` and `rate = <probability>`, separated by commas.
+fn parse_args(args: TokenStream) -> Result<Args, Error> {
+    let mut tokens = args.into_iter();
+    let mut args = Args::default();
+    while let Some(token) = tokens.next() {
+        let key = match token {
+            TokenTree::Ident(key) => key,
+            other => {
+                return Err((
+                    other.span(),
+                    "expected `name = \"...\"` or `rate = <probability>`".to_owned(),
+                ))
+            }
+        };
+        match tokens.next() {
+            Some(TokenTree::Punct(punct)) if punct.as_char() == '=' => {}
+            _ => return Err((key.span(), format!("expected `=` after `{}`", key))),
+        }
+        let mut value = Vec::new();
+        for token in tokens.by_ref() {
+            match &token {
+                TokenTree::Punct(punct) if punct.as_char() == ',' => break,
+                _ => value.push(token),
+            }
+        }
+        if value.is_empty() {
+            return Err((key.span(), format!("expected a value for `{}`", key)));
+        }
+        match key.to_string().as_str() {
+            "name" => match value.as_slice() {
+                [TokenTree::Literal(name)] if is_string(name) => args.name = Some(name.clone()),
+                _ => return Err((key.span(), "expected a string literal for `name`".to_owned())),
+            },
+            "rate" => args.rate = Some(TokenStream::from_iter(value)),
+            _ => {
+                return Err((
+                    key.span(),
+                    format!("unknown argument `{}`, expected `name` or `rate`", key),
+                ))
+            }
+        }
+    }
+    Ok(args)
+}
+
+fn is_string(literal: &Literal) -> bool {
+    let literal = literal.to_string();
+    literal.starts_with('"') || literal.starts_with("r\"") || literal.starts_with("r#")
+}
+
+fn expand(args: TokenStream, item: TokenStream) -> Result<TokenStream, Error> {
+    let args = parse_args(args)?;
+    let mut tokens = item.into_iter().collect::<Vec<_>>();
+
+    let name = match args.name {
+        Some(name) => name,
+        None => Literal::string(&fn_name(&tokens)?),
+    };
+    let rate = args.rate.map_or_else(|| "1.0".to_owned(), |rate| rate.to_string());
+    let point = format!("::kaos::flunk!({}, {});", name, rate)
+        .parse::<TokenStream>()
+        .map_err(|err| (Span::call_site(), err.to_string()))?;
+
+    let body = match tokens.last() {
+        Some(TokenTree::Group(body)) if body.delimiter() == Delimiter::Brace => body.clone(),
+        _ => {
+            return Err((
+                Span::call_site(),
+                "#[flunkable] only applies to functions with a body".to_owned(),
+            ))
+        }
+    };
+    // Inner attributes of the body have to stay in front.
+    let statements = body.stream().into_iter().collect::<Vec<_>>();
+    let mut inner = 0;
+    while let [TokenTree::Punct(pound), TokenTree::Punct(bang), TokenTree::Group(_), ..] =
+        &statements[inner..]
+    {
+        if pound.as_char() != '#' || bang.as_char() != '!' {
+            break;
+        }
+        inner += 3;
+    }
+    let mut stream = TokenStream::from_iter(statements[..inner].iter().cloned());
+    stream.extend(point);
+    stream.extend(statements[inner..].iter().cloned());
+
+    let mut expanded = Group::new(Delimiter::Brace, stream);
+    expanded.set_span(body.span());
+    *tokens.last_mut().unwrap() = TokenTree::Group(expanded);
+    Ok(TokenStream::from_iter(tokens))
+}
+
+// The identifier after `fn`, without the `r#` of raw identifiers.
+fn fn_name(tokens: &[TokenTree]) -> Result<String, Error> {
+    tokens
+        .windows(2)
+        .find_map(|pair| match pair {
+            [TokenTree::Ident(keyword), TokenTree::Ident(name)] if keyword.to_string() == "fn" => {
+                let name = name.to_string();
+                Some(name.strip_prefix("r#").unwrap_or(&name).to_owned())
+            }
+            _ => None,
+        })
+        .ok_or_else(|| {
+            (
+                Span::call_site(),
+                "#[flunkable] only applies to functions".to_owned(),
+            )
+        })
+}
+
+fn compile_error(span: Span, message: &str) -> TokenStream {
+    let mut message = Literal::string(message);
+    message.set_span(span);
+    let mut arguments = Group::new(Delimiter::Parenthesis, TokenTree::Literal(message).into());
+    arguments.set_span(span);
+    TokenStream::from_iter(vec![
+        TokenTree::Ident(Ident::new("compile_error", span)),
+        TokenTree::Punct(Punct::new('!', Spacing::Alone)),
+        TokenTree::Group(arguments),
+        TokenTree::Punct(Punct::new(';', Spacing::Alone)),
+    ])
+}
@@ -0,0 +1,170 @@
+use core::ptr;
+use core::slice;
+use core::str;
+use core::sync::atomic::{AtomicPtr, AtomicU32, AtomicUsize, Ordering};
+
+// Prefix of the names that activate a group of flunk points.
+const GROUP_PREFIX: &str = "group:";
+// How many flunk point names can be active at once.
+const SLOTS: usize = 32;
+
+// An active name, stored as the parts of a `&'static str`. Only atomic loads
+// and stores are used, so targets without compare-and-swap work too.
+struct Slot {
+    ptr: AtomicPtr<u8>,
+    len: AtomicUsize,
+}
+
+#[allow(clippy::declare_interior_mutable_const)]
+const EMPTY: Slot = Slot {
+    ptr: AtomicPtr::new(ptr::null_mut()),
+    len: AtomicUsize::new(0),
+};
+
+static ACTIVE: [Slot; SLOTS] = [EMPTY; SLOTS];
+// State of the xorshift generator behind probabilistic flunks, never zero.
+static RNG: AtomicU32 = AtomicU32::new(0x9e37_79b9);
+
+impl Slot {
+    fn get(&self) -> Option<&'static str> {
+        let ptr = self.ptr.load(Ordering::Acquire);
+        if ptr.is_null() {
+            return None;
+        }
+        let len = self.len.load(Ordering::Acquire);
+        // Slots only ever hold the parts of a `&'static str`.
+        unsafe { Some(str::from_utf8_unchecked(slice::from_raw_parts(ptr, len))) }
+    }
+
+    fn set(&self, name: &'static str) {
+        self.len.store(name.len(), Ordering::Release);
+        self.ptr.store(name.as_ptr() as *mut u8, Ordering::Release);
+    }
+}
+
+/// Turns on the named flunk points, like the names a kaos test passes to
+/// `kaostest!`: bare names, `module::name` for a single point, or
+/// `group:<name>` for a group. Names past the first 32 are ignored.
+///
+/// Activate points before the code reaching them starts, points activated
+/// concurrently with others may be lost.
+pub fn activate(names: &[&'static str]) {
+    let mut names = names.iter();
+    for slot in ACTIVE.iter().filter(|slot| slot.get().is_none()) {
+        match names.next() {
+            Some(name) => slot.set(name),
+            None => return,
+        }
+    }
+}
+
+/// Turns off every flunk point.
+pub fn reset() {
+    for slot in ACTIVE.iter() {
+        slot.ptr.store(ptr::null_mut(), Ordering::Release);
+    }
+}
+
+/// Seeds the generator deciding whether probabilistic flunks fire.
+pub fn seed(seed: u32) {
+    RNG.store(if seed == 0 { 0x9e37_79b9 } else { seed }, Ordering::Relaxed);
+}
+
+fn is_active(matches: impl Fn(&str) -> bool) -> bool {
+    ACTIVE.iter().filter_map(Slot::get).any(matches)
+}
+
+/// Evaluates the flunk point `name` declared in `module`, active when its bare
+/// or namespaced name is. `f` is passed the bare name, namespacing it would
+/// take an allocation.
+pub fn eval_in<R, F: FnOnce(&str) -> R>(module: &str, name: &str, f: F) -> Option<R> {
+    eval_in_groups(module, name, &[], f)
+}
+
+/// Like [eval_in] for a point that is also active when one of `groups` is.
+pub fn eval_in_groups<R, F: FnOnce(&str) -> R>(
+    module: &str,
+    name: &str,
+    groups: &[&str],
+    f: F,
+) -> Option<R> {
+    let active = is_active(|active| {
+        active == name
+            || active
+                .strip_prefix(module)
+                .and_then(|rest| rest.strip_prefix("::"))
+                == Some(name)
+            || active
+                .strip_prefix(GROUP_PREFIX)
+                .is_some_and(|group| groups.contains(&group))
+    });
+    if active {
+        Some(f(name))
+    } else {
+        None
+    }
+}
+
+/// Like [eval_in] for a point that may let the call through while active.
+/// `fires` is passed the bare name and decides whether the point fires on this
+/// evaluation, the bare name is returned when it does.
+pub fn eval_in_if<'a, F: FnOnce(&str) -> bool>(
+    module: &str,
+    name: &'a str,
+    fires: F,
+) -> Option<&'a str> {
+    eval_in_groups_if(module, name, &[], fires)
+}
+
+/// Like [eval_in_if] for a point that is also active when one of `groups` is.
+pub fn eval_in_groups_if<'a, F: FnOnce(&str) -> bool>(
+    module: &str,
+    name: &'a str,
+    groups: &[&str],
+    fires: F,
+) -> Option<&'a str> {
+    eval_in_groups(module, name, groups, fires)
+        .unwrap_or(false)
+        .then_some(name)
+}
+
+/// Evaluates the flunk point declared under its bare name.
+pub fn eval<R, F: FnOnce(Option<&str>) -> R>(name: &str, f: F) -> Option<R> {
+    if is_active(|active| active == name) {
+        Some(f(None))
+    } else {
+        None
+    }
+}
+
+/// Like [eval] for a point that may let the call through while active, `fires`
+/// deciding whether it fires on this evaluation.
+pub fn eval_if<F: FnOnce() -> bool>(name: &str, fires: F) -> bool {
+    eval(name, |_| fires()).unwrap_or(false)
+}
+
+fn next() -> u32 {
+    let mut x = RNG.load(Ordering::Relaxed);
+    x ^= x << 13;
+    x ^= x >> 17;
+    x ^= x << 5;
+    RNG.store(x, Ordering::Relaxed);
+    x
+}
+
+/// Decides whether a flunk with the given failure probability fires on this
+/// evaluation.
+pub fn roll(probability: f64) -> bool {
+    if !(0.0..=1.0).contains(&probability) {
+        panic!(
+            "KAOS: flunk probability must be within [0.0, 1.0], got {}",
+            probability
+        );
+    }
+    probability == 1.0 || (next() as f64 / 4_294_967_296.0) < probability
+}
+
+/// Picks which of `count` actions a flunk point takes.
+pub fn pick(count: usize) -> usize {
+    next() as usize % count
+}
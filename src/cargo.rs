@@ -1,9 +1,16 @@
 use serde::Deserialize;
-use std::path::PathBuf;
+use std::collections::BTreeMap;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
 use std::process::{Command, Output, Stdio};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
 
+use crate::env;
 use crate::error::{Error, Result};
 use crate::manifest::Name;
+use crate::process;
 use crate::run::Project;
 use crate::rustflags;
 
@@ -11,14 +18,56 @@ use crate::rustflags;
 pub struct Metadata {
     pub target_directory: PathBuf,
     pub workspace_root: PathBuf,
+    #[serde(default)]
+    pub packages: Vec<PackageMetadata>,
 }
 
-fn raw_cargo() -> Command {
-    Command::new(option_env!("CARGO").unwrap_or("cargo"))
+#[derive(Deserialize)]
+pub struct PackageMetadata {
+    pub name: String,
+    pub manifest_path: PathBuf,
+    #[serde(default)]
+    pub targets: Vec<TargetMetadata>,
+}
+
+#[derive(Deserialize)]
+pub struct TargetMetadata {
+    pub name: String,
+    pub kind: Vec<String>,
+}
+
+// The extra arguments come before the subcommand, where `+toolchain` and
+// `-Z` flags go.
+fn raw_cargo(bin: &Path, args: &[String]) -> Command {
+    let mut cmd = Command::new(bin);
+    cmd.args(args);
+    cmd
+}
+
+// The cargo running the tests, unless another was set with `Runs::cargo_bin`.
+// Read at runtime, a cargo recorded at build time may be gone by then.
+pub fn default_bin() -> PathBuf {
+    std::env::var_os("CARGO").map_or_else(|| PathBuf::from("cargo"), PathBuf::from)
+}
+
+// Resolves a binary set with `Runs::cargo_bin`. Bare names are looked up in
+// `PATH` like the shell does, paths are made absolute since cargo runs from
+// the generated project.
+pub fn find_bin(bin: &Path) -> Result<PathBuf> {
+    let found = if bin.components().count() > 1 {
+        bin.canonicalize().ok().filter(|bin| bin.is_file())
+    } else {
+        std::env::var_os("PATH").and_then(|paths| {
+            std::env::split_paths(&paths)
+                .map(|dir| dir.join(bin))
+                .find(|bin| bin.is_file())
+        })
+    };
+    found.ok_or_else(|| Error::CargoBin(bin.to_owned()))
 }
 
 fn cargo(project: &Project) -> Command {
-    let mut cmd = raw_cargo();
+    let mut cmd = raw_cargo(&project.cargo_bin, &project.cargo_args);
     cmd.current_dir(&project.dir);
     cmd.env(
         "CARGO_TARGET_DIR",
@@ -30,21 +79,38 @@ fn cargo(project: &Project) -> Command {
 }
 
 pub fn build_dependencies(project: &Project) -> Result<()> {
-    let status = cargo(project)
+    let mut child = cargo(project)
         .arg(if project.has_run_at_least { "build" } else { "check" })
         .arg("--bin")
         .arg(&project.name)
-        .status()
+        .args(profile(project))
+        .args(target(project))
+        .stderr(Stdio::piped())
+        .spawn()
         .map_err(Error::Cargo)?;
+    // Cargo's output still shows as it builds, a copy is kept for the error.
+    let mut stderr = Vec::new();
+    if let Some(mut stream) = child.stderr.take() {
+        let mut chunk = [0; 4096];
+        while let Ok(n @ 1..) = stream.read(&mut chunk) {
+            let _ = io::stderr().write_all(&chunk[..n]);
+            stderr.extend_from_slice(&chunk[..n]);
+        }
+    }
+    let status = child.wait().map_err(Error::Cargo)?;
 
     if status.success() {
         Ok(())
     } else {
-        Err(Error::CargoFail)
+        Err(Error::CargoFail {
+            stderr: String::from_utf8_lossy(&stderr).into_owned(),
+            status,
+        })
     }
 }
 
-pub fn build_test(project: &Project, name: &Name) -> Result<Output> {
+// Removes the built tests, so that building one reports its warnings again.
+pub fn clean_tests(project: &Project) {
     let _ = cargo(project)
         .arg("clean")
         .arg("--package")
@@ -53,47 +119,214 @@ pub fn build_test(project: &Project, name: &Name) -> Result<Output> {
         .stdout(Stdio::null())
         .stderr(Stdio::null())
         .status();
+}
 
+// Builds one test, keeping the others that are already built.
+pub fn build_bin(project: &Project, name: &Name, test_features: &[String]) -> Result<Output> {
     cargo(project)
         .arg(if project.has_run_at_least { "build" } else { "check" })
         .arg("--bin")
         .arg(name)
-        .args(features(project))
+        .args(features(project, test_features))
+        .args(profile(project))
+        .args(target(project))
         .arg("--quiet")
         .arg("--color=never")
         .output()
         .map_err(Error::Cargo)
 }
 
-pub fn run_test(project: &Project, name: &Name) -> Result<Output> {
-    cargo(project)
-        .arg("run")
+pub fn run_test(
+    project: &Project,
+    name: &Name,
+    test_features: &[String],
+    test_env: &BTreeMap<String, String>,
+    run_dir: Option<&Path>,
+    surge: Option<isize>,
+) -> Result<Output> {
+    let mut cmd = cargo(project);
+    // Crashes come with where they happened, unless backtraces were configured.
+    if std::env::var_os("RUST_BACKTRACE").is_none() && !test_env.contains_key("RUST_BACKTRACE") {
+        cmd.env("RUST_BACKTRACE", "1");
+    }
+    cmd.envs(test_env);
+    if let Some(surge) = surge {
+        cmd.env(env::SURGE, surge.to_string());
+    }
+
+    cmd.arg("run");
+    if let Some(run_dir) = run_dir {
+        cmd.current_dir(run_dir)
+            .arg("--manifest-path")
+            .arg(path!(project.dir / "Cargo.toml"));
+        rustflags::set_encoded_env(&mut cmd);
+    }
+
+    cmd
         .arg("--bin")
         .arg(name)
-        .args(features(project))
+        .args(features(project, test_features))
+        .args(profile(project))
+        .args(target(project))
         .arg("--quiet")
-        .arg("--color=never")
-        .output()
-        .map_err(Error::Cargo)
+        .arg("--color=never");
+
+    output_within(cmd, &running_dir(project), project.timeout)
+}
+
+// Where the process groups of running services are recorded.
+pub fn running_dir(project: &Project) -> PathBuf {
+    path!(project.dir / "running")
+}
+
+// How often to check whether a child with a time limit has exited.
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+// How long to let the output readers drain after killing a child.
+const DRAIN_GRACE: Duration = Duration::from_millis(100);
+
+// Like `Command::output`, but kills the child once `limit` has passed. The
+// output captured until then is returned in `Error::Timeout`. Processes the
+// child leaves behind are killed with it, they would otherwise hold on to the
+// ports and files of the next run, and keep the output pipes open.
+fn output_within(mut cmd: Command, running: &Path, limit: Option<Duration>) -> Result<Output> {
+    process::isolate(&mut cmd);
+    let mut child = cmd
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(Error::Cargo)?;
+    let _tracked = process::Tracked::new(running, child.id());
+    let stdout = Capture::spawn(child.stdout.take());
+    let stderr = Capture::spawn(child.stderr.take());
+
+    let start = Instant::now();
+    loop {
+        if let Some(status) = child.try_wait().map_err(Error::Cargo)? {
+            process::kill_group(child.id());
+            return Ok(Output {
+                status,
+                stdout: stdout.finish(),
+                stderr: stderr.finish(),
+            });
+        }
+
+        if limit.is_some_and(|limit| start.elapsed() >= limit) {
+            process::kill_group(child.id());
+            let _ = child.kill();
+            let status = child.wait().map_err(Error::Cargo)?;
+            // The service may outlive cargo and keep the pipes open, so don't
+            // wait for the readers to hit the end of the streams.
+            thread::sleep(DRAIN_GRACE);
+            let output = Output {
+                status,
+                stdout: stdout.snapshot(),
+                stderr: stderr.snapshot(),
+            };
+            return Err(Error::Timeout(limit.unwrap(), output));
+        }
+
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+// Reads a child's stream on a background thread.
+struct Capture {
+    buffer: Arc<Mutex<Vec<u8>>>,
+    reader: Option<JoinHandle<()>>,
+}
+
+impl Capture {
+    fn spawn<R: Read + Send + 'static>(stream: Option<R>) -> Self {
+        let buffer = Arc::new(Mutex::new(Vec::new()));
+        let reader = stream.map(|mut stream| {
+            let buffer = Arc::clone(&buffer);
+            thread::spawn(move || {
+                let mut chunk = [0; 4096];
+                while let Ok(n @ 1..) = stream.read(&mut chunk) {
+                    buffer
+                        .lock()
+                        .unwrap_or_else(PoisonError::into_inner)
+                        .extend_from_slice(&chunk[..n]);
+                }
+            })
+        });
+        Capture { buffer, reader }
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if let Some(reader) = self.reader.take() {
+            let _ = reader.join();
+        }
+        self.snapshot()
+    }
+
+    fn snapshot(&self) -> Vec<u8> {
+        self.buffer
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
 }
 
-pub fn metadata() -> Result<Metadata> {
-    let output = raw_cargo()
+pub fn metadata(bin: &Path, args: &[String]) -> Result<Metadata> {
+    let output = raw_cargo(bin, args)
         .arg("metadata")
         .arg("--format-version=1")
+        .arg("--no-deps")
         .output()
         .map_err(Error::Cargo)?;
 
+    if !output.status.success()
+        && String::from_utf8_lossy(&output.stderr).contains("could not find `Cargo.toml`")
+    {
+        let dir = std::env::current_dir().map_err(Error::Io)?;
+        return Err(Error::NoCargoProject(dir));
+    }
+
     serde_json::from_slice(&output.stdout).map_err(Error::Metadata)
 }
 
-fn features(project: &Project) -> Vec<String> {
-    match &project.features {
-        Some(features) => vec![
-            "--no-default-features".to_owned(),
-            "--features".to_owned(),
-            features.join(","),
-        ],
-        None => vec![],
+// Cross compiles for the configured target. Cargo runs the binaries through
+// the target's `CARGO_TARGET_<TRIPLE>_RUNNER`, if there is one.
+fn target(project: &Project) -> Vec<&str> {
+    match &project.target {
+        Some(triple) => vec!["--target", triple],
+        None => Vec::new(),
+    }
+}
+
+// Builds with the configured profile, dev unless one was set. Cargo puts what
+// each profile builds in a directory of its own, `target/release` for release.
+fn profile(project: &Project) -> Vec<&str> {
+    match project.profile.as_deref() {
+        None | Some("dev") => Vec::new(),
+        Some("release") => vec!["--release"],
+        Some(name) => vec!["--profile", name],
+    }
+}
+
+// Features the tests were run with, plus the ones a single test asked for.
+fn features(project: &Project, test_features: &[String]) -> Vec<String> {
+    let mut args = Vec::new();
+    let mut features = test_features.to_vec();
+    if let Some(enabled) = &project.features {
+        args.push("--no-default-features".to_owned());
+        features.splice(..0, enabled.iter().cloned());
+    }
+    if !features.is_empty() {
+        args.push("--features".to_owned());
+        args.push(features.join(","));
+    }
+    args
+}
+
+#[cfg(unix)]
+#[test]
+fn test_find_bin() {
+    assert!(find_bin(Path::new("sh")).unwrap().is_absolute());
+    assert!(find_bin(Path::new("/bin/sh")).is_ok());
+    match find_bin(Path::new("kaos-no-such-cargo")) {
+        Err(Error::CargoBin(bin)) => assert_eq!(bin, Path::new("kaos-no-such-cargo")),
+        other => panic!("expected a missing binary, got {:?}", other.map(|_| ())),
     }
 }
@@ -0,0 +1,170 @@
+use std::io::{self, Read, Write};
+
+use crate::flunk;
+
+// Whether the point of an adapter fires on this operation, and how much of a
+// buffer of `len` bytes the operation gets to use if it does.
+fn shortened(name: &str, every: usize, len: usize) -> usize {
+    if len < 2 {
+        return len;
+    }
+    if flunk::eval_if(name, || every <= 1 || flunk::hits(name).is_multiple_of(every)) {
+        len / 2
+    } else {
+        len
+    }
+}
+
+///
+/// Writer that writes short while its flunk point is active
+///
+/// Wraps any [Write] and, whenever the point `name` fires, hands only the
+/// first half of the buffer to the inner writer. Nothing fails, `write`
+/// returns fewer bytes than it was given, which is what code assuming full
+/// writes gets wrong. Buffers of a single byte are written whole, so the
+/// writer never reports a write of zero bytes.
+///
+/// The point is named `name` alone, like the ones of
+/// [flunk_global](crate::flunk_global), and counts its evaluations like them.
+/// It fires on every write by default, [ChaosWriter::every] makes it
+/// intermittent the way [flunk_every](crate::flunk_every) does.
+///
+/// ```
+/// use std::io::Write;
+///
+/// let mut log = kaos::ChaosWriter::new(Vec::new(), "short-log-write").every(3);
+/// // Retries short writes, unlike `log.write(b"entry")`.
+/// log.write_all(b"entry").unwrap();
+/// ```
+#[derive(Debug)]
+pub struct ChaosWriter<W> {
+    inner: W,
+    name: String,
+    every: usize,
+}
+
+impl<W: Write> ChaosWriter<W> {
+    pub fn new<S: Into<String>>(inner: W, name: S) -> Self {
+        ChaosWriter {
+            inner,
+            name: name.into(),
+            every: 1,
+        }
+    }
+
+    /// Only shorten every `interval`-th write while the point is active.
+    /// `interval` must be at least 1.
+    pub fn every(mut self, interval: usize) -> Self {
+        assert!(interval >= 1, "kaos: short write interval must be at least 1");
+        self.every = interval;
+        self
+    }
+
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.inner
+    }
+
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+impl<W: Write> Write for ChaosWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let len = shortened(&self.name, self.every, buf.len());
+        self.inner.write(&buf[..len])
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+///
+/// Reader that reads short while its flunk point is active
+///
+/// Wraps any [Read] and, whenever the point `name` fires, lets the inner
+/// reader fill only the first half of the buffer, for code assuming a read
+/// fills all of it. Reads into a single byte are left alone, so a short read
+/// is never mistaken for the end of the stream.
+///
+/// Points are named and made intermittent like the ones of [ChaosWriter].
+///
+/// ```
+/// use std::io::Read;
+///
+/// let mut frames = kaos::ChaosReader::new(&b"header+body"[..], "short-frame-read");
+/// let mut header = [0; 7];
+/// // Keeps reading until the buffer is full, unlike `frames.read(&mut header)`.
+/// frames.read_exact(&mut header).unwrap();
+/// ```
+#[derive(Debug)]
+pub struct ChaosReader<R> {
+    inner: R,
+    name: String,
+    every: usize,
+}
+
+impl<R: Read> ChaosReader<R> {
+    pub fn new<S: Into<String>>(inner: R, name: S) -> Self {
+        ChaosReader {
+            inner,
+            name: name.into(),
+            every: 1,
+        }
+    }
+
+    /// Only shorten every `interval`-th read while the point is active.
+    /// `interval` must be at least 1.
+    pub fn every(mut self, interval: usize) -> Self {
+        assert!(interval >= 1, "kaos: short read interval must be at least 1");
+        self.every = interval;
+        self
+    }
+
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: Read> Read for ChaosReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = shortened(&self.name, self.every, buf.len());
+        self.inner.read(&mut buf[..len])
+    }
+}
+
+#[test]
+fn test_short_io() {
+    let scenario = flunk::Scenario::setup();
+    fail::cfg("kaos::short-io", "return").unwrap();
+
+    let mut writer = ChaosWriter::new(Vec::new(), "kaos::short-io").every(2);
+    assert_eq!(writer.write(b"abcd").unwrap(), 4);
+    assert_eq!(writer.write(b"abcd").unwrap(), 2);
+    assert_eq!(writer.write(b"a").unwrap(), 1);
+    assert_eq!(writer.into_inner(), b"abcdaba");
+
+    let mut reader = ChaosReader::new(&b"abcdef"[..], "kaos::short-io");
+    let mut buf = [0; 4];
+    assert_eq!(reader.read(&mut buf).unwrap(), 2);
+    let mut rest = Vec::new();
+    reader.read_to_end(&mut rest).unwrap();
+    assert_eq!(rest, b"cdef");
+
+    scenario.teardown();
+    let mut untouched = ChaosWriter::new(Vec::new(), "kaos::short-io");
+    assert_eq!(untouched.write(b"abcd").unwrap(), 4);
+}
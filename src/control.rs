@@ -0,0 +1,104 @@
+use std::fs;
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::Path;
+use std::sync::Once;
+use std::thread;
+
+use crate::env;
+use crate::flunk;
+
+static LISTENER: Once = Once::new();
+
+/// Starts listening on the socket at `KAOS_CONTROL_SOCK`, if set, for commands
+/// that turn flunk points on and off while the service runs. Only the first
+/// call starts the listener, it serves until the process exits.
+pub fn listen() {
+    if let Some(path) = env::control_sock() {
+        LISTENER.call_once(|| serve(&path));
+    }
+}
+
+// A service that can't listen still runs, with the points its test turned on.
+fn serve(path: &Path) {
+    // The socket of an earlier run of the service would fail the bind.
+    let _ = fs::remove_file(path);
+    let listener = match UnixListener::bind(path) {
+        Ok(listener) => listener,
+        Err(err) => {
+            eprintln!("KAOS: failed to listen on {}: {}", path.display(), err);
+            return;
+        }
+    };
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            thread::spawn(move || session(stream));
+        }
+    });
+}
+
+// Answers every command of one connection with `ok` or `error: <reason>`.
+fn session(stream: UnixStream) {
+    let mut replies = match stream.try_clone() {
+        Ok(replies) => replies,
+        Err(_) => return,
+    };
+    for line in BufReader::new(stream).lines() {
+        let line = match line {
+            Ok(line) => line,
+            Err(_) => return,
+        };
+        let reply = match command(&line) {
+            Ok(()) => "ok".to_owned(),
+            Err(err) => format!("error: {}", err),
+        };
+        if writeln!(replies, "{}", reply).is_err() {
+            return;
+        }
+    }
+}
+
+// Applies `enable <name>` or `disable <name>`, where the name is anything a
+// kaos test can activate.
+fn command(line: &str) -> Result<(), String> {
+    let words = line.split_whitespace().collect::<Vec<_>>();
+    match words.as_slice() {
+        ["enable", name] => flunk::enable(name),
+        ["disable", name] => {
+            flunk::disable(name);
+            Ok(())
+        }
+        _ => Err(format!(
+            "unrecognized command {:?}, expected `enable <name>` or `disable <name>`",
+            line.trim(),
+        )),
+    }
+}
+
+#[test]
+fn test_control_socket() {
+    let path = std::env::temp_dir().join(format!("kaos-control-{}.sock", std::process::id()));
+    let scenario = flunk::Scenario::setup();
+    serve(&path);
+
+    let stream = UnixStream::connect(&path).unwrap();
+    let mut replies = BufReader::new(stream.try_clone().unwrap()).lines();
+    let mut send = |command: &str| {
+        writeln!(&stream, "{}", command).unwrap();
+        replies.next().unwrap().unwrap()
+    };
+    let fires = || flunk::eval_in("kaos::control", "drop-writes", |_| ()).is_some();
+
+    assert!(!fires());
+    assert_eq!(send("enable kaos::control::drop-writes"), "ok");
+    assert!(fires());
+    assert_eq!(send("disable kaos::control::drop-writes"), "ok");
+    assert!(!fires());
+    assert_eq!(
+        send("toggle drop-writes"),
+        "error: unrecognized command \"toggle drop-writes\", expected `enable <name>` or `disable <name>`"
+    );
+
+    scenario.teardown();
+    let _ = fs::remove_file(&path);
+}
@@ -1,18 +1,37 @@
 use crate::error::{Error, Result};
 use std::env;
+use std::ffi::OsString;
+use std::path::PathBuf;
+use termcolor::ColorChoice;
 
-#[derive(PartialEq, Debug)]
+pub const SURGE: &str = "KAOS_SURGE";
+pub const RESTART: &str = "KAOS_RESTART";
+pub const TMPDIR: &str = "KAOS_TMPDIR";
+pub const SEED: &str = "KAOS_SEED";
+pub const JUNIT_PATH: &str = "KAOS_JUNIT_PATH";
+pub const JOBS: &str = "KAOS_JOBS";
+pub const FAIL_FAST: &str = "KAOS_FAIL_FAST";
+pub const DRY_RUN: &str = "KAOS_DRY_RUN";
+pub const CARGO_TARGET_DIR: &str = "CARGO_TARGET_DIR";
+pub const FLUNKS: &str = "KAOS_FLUNKS";
+pub const COLOR: &str = "KAOS_COLOR";
+pub const NO_COLOR: &str = "NO_COLOR";
+pub const QUIET: &str = "KAOS_QUIET";
+pub const VERBOSE: &str = "KAOS_VERBOSE";
+pub const BLESS: &str = "KAOS_BLESS";
+pub const EVENTS_PATH: &str = "KAOS_EVENTS_PATH";
+pub const EXIT_CODES: &str = "KAOS_EXIT_CODES";
+pub const WATCH: &str = "KAOS_WATCH";
+pub const DUMP_PROJECT: &str = "KAOS_DUMP_PROJECT";
+pub const CONTROL_SOCK: &str = "KAOS_CONTROL_SOCK";
+
+#[derive(PartialEq, Debug, Default)]
 pub enum Update {
+    #[default]
     Wip,
     Overwrite,
 }
 
-impl Default for Update {
-    fn default() -> Self {
-        Update::Wip
-    }
-}
-
 impl Update {
     pub fn env() -> Result<Self> {
         let var = match env::var_os("KAOS") {
@@ -27,3 +46,175 @@ impl Update {
         }
     }
 }
+
+// Surge of the chaotic run the current process was spawned for, or of the
+// single run to replay when set for the launcher.
+pub fn surge() -> Result<Option<u64>> {
+    let var = match env::var_os(SURGE) {
+        Some(var) => var,
+        None => return Ok(None),
+    };
+
+    match var.to_str().and_then(|s| s.parse().ok()) {
+        Some(surge) => Ok(Some(surge)),
+        None => Err(Error::SurgeVar(var)),
+    }
+}
+
+// How many times a supervised service was restarted before this incarnation.
+pub fn restart() -> Option<u64> {
+    env::var(RESTART).ok()?.parse().ok()
+}
+
+// Seed pinned from the environment, taking precedence over `Runs::with_seed`.
+pub fn seed() -> Result<Option<u64>> {
+    let var = match env::var_os(SEED) {
+        Some(var) => var,
+        None => return Ok(None),
+    };
+
+    match var.to_str().and_then(|s| s.parse().ok()) {
+        Some(seed) => Ok(Some(seed)),
+        None => Err(Error::SeedVar(var)),
+    }
+}
+
+// Where to write a JUnit XML report, if anywhere.
+pub fn junit_path() -> Option<PathBuf> {
+    env::var_os(JUNIT_PATH)
+        .filter(|path| !path.is_empty())
+        .map(PathBuf::from)
+}
+
+// Where a service listens for commands turning flunk points on and off, if anywhere.
+pub fn control_sock() -> Option<PathBuf> {
+    env::var_os(CONTROL_SOCK)
+        .filter(|path| !path.is_empty())
+        .map(PathBuf::from)
+}
+
+// Where to stream events as JSON lines, if anywhere.
+pub fn events_path() -> Option<PathBuf> {
+    env::var_os(EVENTS_PATH)
+        .filter(|path| !path.is_empty())
+        .map(PathBuf::from)
+}
+
+// Number of tests to run at once, taking precedence over `Runs::parallelism`.
+pub fn jobs() -> Result<Option<usize>> {
+    let var = match env::var_os(JOBS) {
+        Some(var) => var,
+        None => return Ok(None),
+    };
+
+    match var.to_str().and_then(|s| s.parse().ok()) {
+        Some(jobs) => Ok(Some(jobs)),
+        None => Err(Error::JobsVar(var)),
+    }
+}
+
+// Whether to stop at the first failing test, taking precedence over `Runs::fail_fast`.
+pub fn fail_fast() -> Result<Option<bool>> {
+    flag(FAIL_FAST, Error::FailFastVar)
+}
+
+// Whether to exit with a code telling failures apart, taking precedence over
+// `Runs::exit_codes`.
+pub fn exit_codes() -> Result<Option<bool>> {
+    flag(EXIT_CODES, Error::ExitCodesVar)
+}
+
+// Whether to only list the tests that would run.
+pub fn dry_run() -> Result<bool> {
+    Ok(flag(DRY_RUN, Error::DryRunVar)?.unwrap_or(false))
+}
+
+// Whether to print where the generated project is, and keep it.
+pub fn dump_project() -> Result<bool> {
+    Ok(flag(DUMP_PROJECT, Error::DumpProjectVar)?.unwrap_or(false))
+}
+
+// Whether to keep running the tests affected by each change, until interrupted.
+pub fn watch() -> Result<bool> {
+    Ok(flag(WATCH, Error::WatchVar)?.unwrap_or(false))
+}
+
+// Whether to hide the progress of chaotic sweeps.
+pub fn quiet() -> Result<bool> {
+    Ok(flag(QUIET, Error::QuietVar)?.unwrap_or(false))
+}
+
+// How much to print, from 0 for the summary only to 3 for the output of
+// every run.
+pub fn verbose() -> Result<Option<u8>> {
+    let var = match env::var_os(VERBOSE) {
+        Some(var) => var,
+        None => return Ok(None),
+    };
+
+    match var.to_str().and_then(|s| s.parse().ok()) {
+        Some(level) if level <= 3 => Ok(Some(level)),
+        _ => Err(Error::VerboseVar(var)),
+    }
+}
+
+// Whether to overwrite expected output files with the actual output.
+pub fn bless() -> Result<bool> {
+    Ok(flag(BLESS, Error::BlessVar)?.unwrap_or(false))
+}
+
+// Comma separated flunk names to activate instead of the one named by
+// `kaostest!`. Set but empty activates none.
+pub fn flunks() -> Option<Vec<String>> {
+    let var = env::var(FLUNKS).ok()?;
+    Some(
+        var.split(',')
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .map(str::to_owned)
+            .collect(),
+    )
+}
+
+// Whether to color the output. `KAOS_COLOR` wins over the `NO_COLOR`
+// convention, which turns colors off when set to anything.
+pub fn color() -> Result<ColorChoice> {
+    if let Some(var) = env::var_os(COLOR) {
+        return match var.to_str() {
+            Some("auto") => Ok(ColorChoice::Auto),
+            Some("always") => Ok(ColorChoice::Always),
+            Some("never") => Ok(ColorChoice::Never),
+            _ => Err(Error::ColorVar(var)),
+        };
+    }
+
+    match env::var_os(NO_COLOR) {
+        Some(var) if !var.is_empty() => Ok(ColorChoice::Never),
+        _ => Ok(ColorChoice::Auto),
+    }
+}
+
+// Target directory overridden for the whole build, as cargo itself resolves
+// it: relative paths are taken from the current directory.
+pub fn cargo_target_dir() -> Option<PathBuf> {
+    let dir = env::var_os(CARGO_TARGET_DIR).filter(|dir| !dir.is_empty())?;
+    let dir = PathBuf::from(dir);
+    if dir.is_absolute() {
+        Some(dir)
+    } else {
+        Some(env::current_dir().ok()?.join(dir))
+    }
+}
+
+fn flag(name: &str, error: fn(OsString) -> Error) -> Result<Option<bool>> {
+    let var = match env::var_os(name) {
+        Some(var) => var,
+        None => return Ok(None),
+    };
+
+    match var.to_str() {
+        Some("1") | Some("true") => Ok(Some(true)),
+        Some("0") | Some("false") => Ok(Some(false)),
+        _ => Err(error(var)),
+    }
+}
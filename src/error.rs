@@ -3,28 +3,68 @@ use std::env;
 use std::ffi::OsString;
 use std::fmt::{self, Display};
 use std::io;
+use std::ops::Range;
 use std::path::PathBuf;
+use std::process::{ExitStatus, Output};
+use std::time::Duration;
+
+use crate::normalize;
 
 #[derive(Debug)]
 pub enum Error {
+    BlessVar(OsString),
     Cargo(io::Error),
-    CargoFail,
+    CargoBin(PathBuf),
+    CargoFail { stderr: String, status: ExitStatus },
+    ColorVar(OsString),
+    Crashed(Option<String>),
+    DiagnosticsRejected(String),
+    Downtime(Duration, Duration),
+    DryRunVar(OsString),
+    DumpProjectVar(OsString),
+    Edition(String),
+    ExtraCrate(String, PathBuf),
+    ExitCodesVar(OsString),
+    FailFastVar(OsString),
+    FixtureFailed(&'static str, PathBuf),
     Glob(GlobError),
     Io(io::Error),
+    JobsVar(OsString),
     Metadata(serde_json::Error),
     Mismatch,
+    MissingOutput(PathBuf),
+    NotBuilt,
+    NotRecovered(usize),
+    OutOfMemory,
+    NoCargoProject(PathBuf),
     Open(PathBuf, io::Error),
+    Partition(String),
     Pattern(PatternError),
     PkgName(env::VarError),
     ProjectDir,
-    ReadStderr(io::Error),
-    RunFailed,
+    QuietVar(OsString),
+    RunFailed { stderr: String, status: ExitStatus },
+    SeedVar(OsString),
+    Settings(PathBuf, toml::de::Error),
+    SloMissed(f64, Duration, Duration),
+    SurgeOutOfRange(u64, Range<isize>),
+    SurgeTooLarge(usize),
+    SurgeVar(OsString),
     ChaosTestFailed(String),
-    ShouldNotHaveCompiled,
+    TargetCrate(String),
+    Timeout(Duration, Output),
+    TmpDir(PathBuf, io::Error),
+    TooSlow(Duration, Duration),
     TomlDe(toml::de::Error),
     TomlSer(toml::ser::Error),
+    UnreachedFlunks(Vec<String>),
     UpdateVar(OsString),
-    WriteStderr(io::Error),
+    VerboseVar(OsString),
+    WatchVar(OsString),
+    WriteEvents(PathBuf, io::Error),
+    WriteHistory(PathBuf, io::Error),
+    WriteLog(PathBuf, io::Error),
+    WriteReport(PathBuf, io::Error),
     Shrink(proptest::test_runner::TestError<isize>)
 }
 
@@ -35,43 +75,272 @@ impl Display for Error {
         use self::Error::*;
 
         match self {
+            BlessVar(var) => write!(
+                f,
+                "unrecognized value of KAOS_BLESS: {:?}, expected 1, 0, true or false",
+                var.to_string_lossy(),
+            ),
             Cargo(e) => write!(f, "failed to execute cargo: {}", e),
-            CargoFail => write!(f, "cargo reported an error"),
+            CargoBin(bin) => write!(f, "cargo binary not found: {}", bin.display()),
+            CargoFail { stderr, status } => {
+                write!(f, "cargo reported an error ({})", status)?;
+                if let Some(cause) = first_error(stderr) {
+                    write!(f, ": {}", cause)?;
+                }
+                Ok(())
+            }
+            ColorVar(var) => write!(
+                f,
+                "unrecognized value of KAOS_COLOR: {:?}, expected auto, always or never",
+                var.to_string_lossy(),
+            ),
+            Crashed(backtrace) => {
+                write!(f, "service crashed and did not recover")?;
+                if let Some(backtrace) = backtrace {
+                    write!(f, ", backtrace:\n{}", backtrace.trim_end())?;
+                }
+                Ok(())
+            }
+            DiagnosticsRejected(reason) => write!(f, "diagnostics check failed: {}", reason),
+            Downtime(budget, downtime) => write!(
+                f,
+                "service was down for too long. Expected at most: {}, Found: {}",
+                humantime::format_duration(*budget),
+                humantime::format_duration(*downtime),
+            ),
+            DryRunVar(var) => write!(
+                f,
+                "unrecognized value of KAOS_DRY_RUN: {:?}, expected 1, 0, true or false",
+                var.to_string_lossy(),
+            ),
+            DumpProjectVar(var) => write!(
+                f,
+                "unrecognized value of KAOS_DUMP_PROJECT: {:?}, expected 1, 0, true or false",
+                var.to_string_lossy(),
+            ),
+            Edition(edition) => write!(
+                f,
+                "unknown edition {:?}, expected 2015, 2018, 2021 or 2024",
+                edition,
+            ),
+            ExitCodesVar(var) => write!(
+                f,
+                "unrecognized value of KAOS_EXIT_CODES: {:?}, expected 1, 0, true or false",
+                var.to_string_lossy(),
+            ),
+            FailFastVar(var) => write!(
+                f,
+                "unrecognized value of KAOS_FAIL_FAST: {:?}, expected 1, 0, true or false",
+                var.to_string_lossy(),
+            ),
+            FixtureFailed(kind, path) => write!(f, "{} binary {} failed", kind, path.display()),
             Glob(e) => write!(f, "{}", e),
             Io(e) => write!(f, "{}", e),
+            JobsVar(var) => write!(
+                f,
+                "unrecognized value of KAOS_JOBS: {:?}, expected an unsigned integer",
+                var.to_string_lossy(),
+            ),
             Metadata(e) => write!(f, "failed to read cargo metadata: {}", e),
-            Mismatch => write!(f, "compiler error does not match expected error"),
+            Mismatch => write!(f, "output does not match the expected output"),
+            MissingOutput(path) => write!(
+                f,
+                "expected output file {} does not exist, rerun with KAOS_BLESS=1 to create it",
+                path.display(),
+            ),
+            NotBuilt => write!(f, "test failed to build, see its errors above"),
+            NotRecovered(restarts) => write!(
+                f,
+                "service did not recover after {} restart{}",
+                restarts,
+                if *restarts == 1 { "" } else { "s" },
+            ),
+            OutOfMemory => write!(f, "service was killed, most likely out of memory"),
+            NoCargoProject(dir) => write!(
+                f,
+                "no cargo project found in {} or any parent directory",
+                dir.display(),
+            ),
             Open(path, e) => write!(f, "{}: {}", path.display(), e),
+            Partition(reason) => write!(f, "failed to partition the network: {}", reason),
             Pattern(e) => write!(f, "{}", e),
             PkgName(e) => write!(f, "failed to detect CARGO_PKG_NAME: {}", e),
             ProjectDir => write!(f, "failed to determine name of project dir"),
-            ReadStderr(e) => write!(f, "failed to read stderr file: {}", e),
-            RunFailed => write!(f, "execution of the test case was unsuccessful"),
-            ChaosTestFailed(e) => write!(f, "chaos test failed: {}", e),
-            ShouldNotHaveCompiled => {
-                write!(f, "expected test case to fail to compile, but it succeeded")
+            QuietVar(var) => write!(
+                f,
+                "unrecognized value of KAOS_QUIET: {:?}, expected 1, 0, true or false",
+                var.to_string_lossy(),
+            ),
+            RunFailed { stderr, status } => {
+                write!(f, "execution of the test case was unsuccessful ({})", status)?;
+                if let Some(words) = last_words(stderr) {
+                    write!(f, ": {}", words)?;
+                }
+                Ok(())
             }
+            SeedVar(var) => write!(
+                f,
+                "unrecognized value of KAOS_SEED: {:?}, expected an unsigned integer",
+                var.to_string_lossy(),
+            ),
+            ChaosTestFailed(e) => write!(f, "chaos test failed: {}", e),
+            TargetCrate(name) => write!(
+                f,
+                "target crate `{}` is not a member of the workspace",
+                name,
+            ),
+            ExtraCrate(name, path) => write!(
+                f,
+                "extra crate `{}` has no Cargo.toml at {}",
+                name,
+                path.display(),
+            ),
+            Timeout(limit, _) => write!(
+                f,
+                "service did not exit within {} and was killed",
+                humantime::format_duration(*limit),
+            ),
+            TmpDir(path, e) => write!(
+                f,
+                "failed to create temporary directory {}: {}",
+                path.display(),
+                e,
+            ),
+            Settings(path, e) => write!(f, "failed to parse {}: {}", path.display(), e),
+            SloMissed(percentile, min_duration, uptime) => write!(
+                f,
+                "availability objective missed. Expected {}% of runs to stay up at least: {}, Found: {}",
+                percentile,
+                humantime::format_duration(*min_duration),
+                humantime::format_duration(*uptime),
+            ),
+            SurgeTooLarge(max_surge) => write!(
+                f,
+                "max_surge of {}ms is too large, surges can be at most {}ms",
+                max_surge,
+                isize::MAX,
+            ),
+            SurgeVar(var) => write!(
+                f,
+                "unrecognized value of KAOS_SURGE: {:?}, expected a number of milliseconds",
+                var.to_string_lossy(),
+            ),
+            SurgeOutOfRange(surge, range) => write!(
+                f,
+                "KAOS_SURGE of {}ms is outside the surges of the test, {}..{}ms",
+                surge, range.start, range.end,
+            ),
+            TooSlow(max, elapsed) => write!(
+                f,
+                "service is too slow. Expected at most: {}, Found: {}",
+                humantime::format_duration(*max),
+                humantime::format_duration(*elapsed),
+            ),
             TomlDe(e) => write!(f, "{}", e),
             TomlSer(e) => write!(f, "{}", e),
+            UnreachedFlunks(names) => write!(
+                f,
+                "flunk points were never reached: {}",
+                names
+                    .iter()
+                    .map(|name| format!("{:?}", name))
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            ),
             UpdateVar(var) => write!(
                 f,
                 "unrecognized value of KAOS: {:?}",
                 var.to_string_lossy(),
             ),
-            WriteStderr(e) => write!(f, "failed to write stderr file: {}", e),
+            VerboseVar(var) => write!(
+                f,
+                "unrecognized value of KAOS_VERBOSE: {:?}, expected 0, 1, 2 or 3",
+                var.to_string_lossy(),
+            ),
+            WatchVar(var) => write!(
+                f,
+                "unrecognized value of KAOS_WATCH: {:?}, expected 1, 0, true or false",
+                var.to_string_lossy(),
+            ),
+            WriteEvents(path, e) => {
+                write!(f, "failed to open events stream {}: {}", path.display(), e)
+            }
+            WriteHistory(path, e) => {
+                write!(f, "failed to write history to {}: {}", path.display(), e)
+            }
+            WriteLog(path, e) => {
+                write!(f, "failed to write service output to {}: {}", path.display(), e)
+            }
+            WriteReport(path, e) => {
+                write!(f, "failed to write report to {}: {}", path.display(), e)
+            }
             Shrink(e) => write!(f, "test failed with a randomization: {}", e),
         }
     }
 }
 
+// The line of cargo's output saying what went wrong, the rest is the context
+// of it.
+fn first_error(stderr: &str) -> Option<&str> {
+    stderr
+        .lines()
+        .map(str::trim)
+        .find(|line| line.starts_with("error"))
+}
+
+// What a service said last before failing, leaving out its backtrace, the
+// notes of the panic machinery and cargo's report of the exit status.
+fn last_words(stderr: &str) -> Option<String> {
+    let (rest, _) = normalize::backtrace(stderr);
+    rest.lines()
+        .map(str::trim)
+        .rev()
+        .find(|line| {
+            !line.is_empty()
+                && !line.starts_with("note: ")
+                && !line.starts_with("error: process didn't exit successfully")
+        })
+        .map(str::to_owned)
+}
+
 impl Error {
     pub fn already_printed(&self) -> bool {
         use self::Error::*;
 
-        match self {
-            CargoFail | Mismatch | RunFailed | ShouldNotHaveCompiled => true,
-            _ => false,
-        }
+        matches!(
+            self,
+            CargoFail { .. }
+                | Crashed(_)
+                | Mismatch
+                | OutOfMemory
+                | RunFailed { .. }
+        )
+    }
+}
+
+impl Error {
+    // Whether the harness, rather than the service, is at fault: the test
+    // didn't build, cargo couldn't run, or the setup is wrong.
+    pub fn is_infra(&self) -> bool {
+        use self::Error::*;
+
+        !matches!(
+            self,
+            ChaosTestFailed(_)
+                | Crashed(_)
+                | DiagnosticsRejected(_)
+                | Downtime(..)
+                | Mismatch
+                | MissingOutput(_)
+                | NotRecovered(_)
+                | OutOfMemory
+                | RunFailed { .. }
+                | Shrink(_)
+                | SloMissed(..)
+                | Timeout(..)
+                | TooSlow(..)
+                | UnreachedFlunks(_)
+        )
     }
 }
 
@@ -111,3 +380,47 @@ impl From<proptest::test_runner::TestError<isize>> for Error {
         Error::Shrink(err)
     }
 }
+
+#[cfg(unix)]
+#[test]
+fn test_cargo_fail() {
+    use std::os::unix::process::ExitStatusExt;
+
+    let err = Error::CargoFail {
+        stderr: "    Updating crates.io index\n\
+                 error: failed to select a version for the requirement `left-pad = \"^9\"`\n\
+                 candidate versions found which didn't match: 1.0.1\n"
+            .to_owned(),
+        status: ExitStatus::from_raw(101 << 8),
+    };
+    assert_eq!(
+        err.to_string(),
+        "cargo reported an error (exit status: 101): \
+         error: failed to select a version for the requirement `left-pad = \"^9\"`",
+    );
+    let err = Error::CargoFail {
+        stderr: String::new(),
+        status: ExitStatus::from_raw(101 << 8),
+    };
+    assert_eq!(err.to_string(), "cargo reported an error (exit status: 101)");
+}
+
+#[cfg(unix)]
+#[test]
+fn test_run_failed() {
+    use std::os::unix::process::ExitStatusExt;
+
+    let err = Error::RunFailed {
+        stderr: "thread 'main' panicked at src/main.rs:3:5:\n\
+                 replica lost its lease\n\
+                 stack backtrace:\n   0: replica::main\n\
+                 note: Some details are omitted, run with `RUST_BACKTRACE=full` for a verbose backtrace.\n\
+                 error: process didn't exit successfully: `target/debug/kaos001` (exit status: 101)\n"
+            .to_owned(),
+        status: ExitStatus::from_raw(101 << 8),
+    };
+    assert_eq!(
+        err.to_string(),
+        "execution of the test case was unsuccessful (exit status: 101): replica lost its lease",
+    );
+}
@@ -0,0 +1,100 @@
+use lazy_static::lazy_static;
+use serde::Serialize;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+use std::sync::{Mutex, PoisonError};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::error::{Error, Result};
+
+lazy_static! {
+    // Where events go, if anywhere. Shared by the worker threads of parallel runs.
+    static ref SINK: Mutex<Option<File>> = Mutex::new(None);
+}
+
+/// Something that happened during the run, streamed as one line of JSON.
+#[derive(Serialize, Debug)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event<'a> {
+    TestStarted {
+        test: &'a str,
+        path: &'a Path,
+    },
+    RunCompleted {
+        test: &'a str,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        surge: Option<isize>,
+        elapsed_ms: u64,
+        passed: bool,
+    },
+    TestPassed {
+        test: &'a str,
+    },
+    TestFailed {
+        test: &'a str,
+        error: &'a str,
+    },
+    Finished {
+        passed: usize,
+        failed: usize,
+        skipped: usize,
+    },
+}
+
+#[derive(Serialize)]
+struct Record<'a> {
+    timestamp_ms: u128,
+    #[serde(flatten)]
+    event: &'a Event<'a>,
+}
+
+/// Starts streaming events to `path`. Appends, so that named pipes and
+/// `/dev/fd/N` work as well as regular files.
+pub fn open(path: &Path) -> Result<()> {
+    let file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .map_err(|err| Error::WriteEvents(path.to_owned(), err))?;
+    *SINK.lock().unwrap_or_else(PoisonError::into_inner) = Some(file);
+    Ok(())
+}
+
+/// Writes the event if events are streamed. A consumer that went away doesn't
+/// interrupt the run.
+pub fn emit(event: Event) {
+    let mut sink = SINK.lock().unwrap_or_else(PoisonError::into_inner);
+    let file = match sink.as_mut() {
+        Some(file) => file,
+        None => return,
+    };
+    let record = Record {
+        timestamp_ms: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |since| since.as_millis()),
+        event: &event,
+    };
+    if let Ok(mut line) = serde_json::to_vec(&record) {
+        line.push(b'\n');
+        let _ = file.write_all(&line);
+    }
+}
+
+#[test]
+fn test_event_json() {
+    let event = Event::RunCompleted {
+        test: "kaos000",
+        surge: Some(420),
+        elapsed_ms: 500,
+        passed: true,
+    };
+    let record = Record {
+        timestamp_ms: 7,
+        event: &event,
+    };
+    assert_eq!(
+        serde_json::to_string(&record).unwrap(),
+        r#"{"timestamp_ms":7,"event":"run_completed","test":"kaos000","surge":420,"elapsed_ms":500,"passed":true}"#
+    );
+}
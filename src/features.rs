@@ -91,7 +91,7 @@ fn try_find() -> Result<Vec<String>, Ignored> {
 }
 
 fn is_lower_hex_digit(byte: u8) -> bool {
-    byte >= b'0' && byte <= b'9' || byte >= b'a' && byte <= b'f'
+    byte.is_ascii_digit() || (b'a'..=b'f').contains(&byte)
 }
 
 fn from_json<'de, T, D>(deserializer: D) -> Result<T, D::Error>
@@ -0,0 +1,451 @@
+use fail::FailScenario;
+use lazy_static::lazy_static;
+use proptest::prelude::RngCore;
+use proptest::test_runner::{RngAlgorithm, TestRng};
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
+use std::panic;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, Once, PoisonError, RwLock};
+use std::task::{Context, Poll};
+use std::thread;
+use std::time::Duration;
+
+#[cfg(unix)]
+use crate::control;
+use crate::env;
+
+// Prefix of the panic message raised by `flunk!`.
+pub(crate) const FLUNK_MARKER: &str = "KAOS: Flunking at ";
+// Prefix of the names that activate a group of flunk points.
+pub(crate) const GROUP_PREFIX: &str = "group:";
+// Prefix of the line written for each flunk point a kaos test turns on.
+pub(crate) const ACTIVATED_MARKER: &str = "KAOS: Activated ";
+// Prefix of the line `flunk_delay!` writes to stderr, read back by the harness.
+pub(crate) const DELAY_MARKER: &str = "KAOS: Delaying at ";
+// Prefix of the line written the first time a scenario reaches an inactive point.
+pub(crate) const INACTIVE_MARKER: &str = "KAOS: Reached inactive ";
+// Prefix of the line written at teardown for active points that were never reached.
+pub(crate) const UNREACHED_MARKER: &str = "KAOS: Never reached ";
+
+lazy_static! {
+    // Seeded from the surge of the current run so that a given proptest case
+    // makes the same flunk decisions every time it is replayed.
+    static ref RNG: Mutex<TestRng> = Mutex::new(seeded_rng());
+    // How many times each active flunk point has been evaluated, by name. Points
+    // are entered with a zero count when activated.
+    static ref HITS: Mutex<HashMap<String, Arc<AtomicUsize>>> = Mutex::new(HashMap::new());
+    // Inactive points reached during the current scenario, `None` outside of
+    // one. Each is reported to the harness once.
+    static ref INACTIVE: Mutex<Option<HashSet<String>>> = Mutex::new(None);
+    // Groups activated by `group:<name>`. Points declare the groups they are
+    // in, so members are only known once they are reached.
+    static ref GROUPS: Mutex<HashSet<String>> = Mutex::new(HashSet::new());
+    // Called with every evaluation of a point, see `on_flunk`.
+    static ref OBSERVER: RwLock<Option<Observer>> = RwLock::new(None);
+}
+
+type Observer = Box<dyn Fn(&str, bool) + Send + Sync>;
+
+// Whether flunk panics skip the panic hook, set for the length of a scenario.
+static QUIET_PANICS: AtomicBool = AtomicBool::new(false);
+static PANIC_HOOK: Once = Once::new();
+// Whether an observer is registered, so points don't take the lock without one.
+static OBSERVED: AtomicBool = AtomicBool::new(false);
+
+/// Fail point scenario that also resets the evaluation counts of flunk points
+/// when it is set up and torn down. Teardown reports active points that were
+/// never reached to the harness.
+///
+/// While the scenario is set up, panics raised by flunk points are written as
+/// their bare message instead of going through the panic hook, so expected
+/// chaos doesn't bury genuine panics under backtrace notes. Teardown hands
+/// them back to the previous hook.
+#[derive(Debug)]
+pub struct Scenario<'a> {
+    _fail: FailScenario<'a>,
+}
+
+impl<'a> Scenario<'a> {
+    pub fn setup() -> Self {
+        let scenario = Scenario {
+            _fail: FailScenario::setup(),
+        };
+        PANIC_HOOK.call_once(quiet_flunk_panics);
+        QUIET_PANICS.store(true, Ordering::SeqCst);
+        reset_hits();
+        reset_groups();
+        *INACTIVE.lock().unwrap_or_else(PoisonError::into_inner) = Some(HashSet::new());
+        #[cfg(unix)]
+        control::listen();
+        scenario
+    }
+
+    pub fn teardown(self) {
+        for name in unreached() {
+            eprintln!("{}\"{}\"", UNREACHED_MARKER, name);
+        }
+        drop(self)
+    }
+}
+
+impl<'a> Drop for Scenario<'a> {
+    fn drop(&mut self) {
+        reset_hits();
+        reset_groups();
+        *INACTIVE.lock().unwrap_or_else(PoisonError::into_inner) = None;
+        QUIET_PANICS.store(false, Ordering::SeqCst);
+    }
+}
+
+// Wraps the panic hook so that, during a scenario, flunk panics are written as
+// a single line, the marker the harness looks for. Every other panic, and all
+// of them outside of a scenario, go to the hook it wraps. Hooks can't be
+// swapped back while unwinding out of a scenario, so this stays installed.
+fn quiet_flunk_panics() {
+    let previous = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        let payload = info.payload();
+        let message = payload
+            .downcast_ref::<String>()
+            .map(String::as_str)
+            .or_else(|| payload.downcast_ref::<&str>().copied());
+        match message {
+            Some(message)
+                if message.starts_with(FLUNK_MARKER) && QUIET_PANICS.load(Ordering::SeqCst) =>
+            {
+                eprintln!("{}", message)
+            }
+            _ => previous(info),
+        }
+    }));
+}
+
+/// Turns on the flunk points of a kaos test: the ones listed in `KAOS_FLUNKS`
+/// if it is set, otherwise the ones named by the test. Each activated point is
+/// reported to the harness. A name like `group:storage` turns on every point
+/// in the `storage` group.
+pub fn activate<S: AsRef<str>>(names: &[S]) {
+    let names = env::flunks().unwrap_or_else(|| {
+        names.iter().map(|name| name.as_ref().to_owned()).collect()
+    });
+    for name in names {
+        enable(&name).unwrap();
+    }
+}
+
+/// Turns on one flunk point, or a group of them, and reports it to the harness.
+pub(crate) fn enable(name: &str) -> Result<(), String> {
+    match name.strip_prefix(GROUP_PREFIX) {
+        Some(group) => {
+            GROUPS
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .insert(group.to_owned());
+        }
+        None => fail::cfg(name, "return")?,
+    }
+    eprintln!("{}\"{}\"", ACTIVATED_MARKER, name);
+    HITS.lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .entry(name.to_owned())
+        .or_default();
+    Ok(())
+}
+
+/// Turns off a flunk point, or a group of them, that [enable] turned on. Its
+/// evaluations so far still count.
+pub(crate) fn disable(name: &str) {
+    match name.strip_prefix(GROUP_PREFIX) {
+        Some(group) => {
+            GROUPS
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .remove(group);
+        }
+        None => fail::remove(name),
+    }
+}
+
+/// Evaluates the flunk point `name` declared in `module`. The point is active
+/// when its namespaced name `module::name` is configured, or when the bare
+/// `name` is, which turns on the points of that name in every module. `f` is
+/// passed the namespaced name.
+///
+/// Evaluations are counted under the namespaced name as well as the configured
+/// one.
+pub fn eval_in<R, F: FnOnce(&str) -> R>(module: &str, name: &str, f: F) -> Option<R> {
+    eval_in_groups(module, name, &[], f)
+}
+
+/// Like [eval_in] for a point that is also active when one of `groups` is.
+pub fn eval_in_groups<R, F: FnOnce(&str) -> R>(
+    module: &str,
+    name: &str,
+    groups: &[&str],
+    f: F,
+) -> Option<R> {
+    fire_in_groups(module, name, groups, |_| true, f)
+}
+
+/// Like [eval_in] for a point that may let the call through while active.
+/// `fires` is passed the namespaced name and decides whether the point fires
+/// on this evaluation, the namespaced name is returned when it does.
+pub fn eval_in_if<F: FnOnce(&str) -> bool>(module: &str, name: &str, fires: F) -> Option<String> {
+    eval_in_groups_if(module, name, &[], fires)
+}
+
+/// Like [eval_in_if] for a point that is also active when one of `groups` is.
+pub fn eval_in_groups_if<F: FnOnce(&str) -> bool>(
+    module: &str,
+    name: &str,
+    groups: &[&str],
+    fires: F,
+) -> Option<String> {
+    fire_in_groups(module, name, groups, fires, str::to_owned)
+}
+
+// Evaluates a point, running `f` when the point is active and `fires` says it
+// fires on this evaluation. The observer hears the outcome before `f` runs,
+// since `f` may panic.
+fn fire_in_groups<R>(
+    module: &str,
+    name: &str,
+    groups: &[&str],
+    fires: impl FnOnce(&str) -> bool,
+    f: impl FnOnce(&str) -> R,
+) -> Option<R> {
+    let namespaced = format!("{}::{}", module, name);
+    let mut actions = Some((fires, f));
+    let mut fire = |configured: &str| {
+        hit(configured);
+        if configured != namespaced {
+            hit(&namespaced);
+        }
+        let (fires, f) = actions.take()?;
+        let fired = fires(&namespaced);
+        observe(&namespaced, fired);
+        fired.then(|| f(&namespaced))
+    };
+
+    let result = fail::eval(&namespaced, |_| fire(&namespaced))
+        .or_else(|| fail::eval(name, |_| fire(name)))
+        .or_else(|| {
+            let group = active_group(groups)?;
+            Some(fire(&format!("{}{}", GROUP_PREFIX, group)))
+        });
+    match result {
+        Some(result) => result,
+        None => {
+            observe(&namespaced, false);
+            reached_inactive(&namespaced);
+            None
+        }
+    }
+}
+
+/// Evaluates the named fail point like `fail::eval`, counting the evaluation
+/// when the point is active.
+pub fn eval<R, F: FnOnce(Option<String>) -> R>(name: &str, f: F) -> Option<R> {
+    let result = fail::eval(name, |arg| {
+        hit(name);
+        observe(name, true);
+        f(arg)
+    });
+    if result.is_none() {
+        observe(name, false);
+        reached_inactive(name);
+    }
+    result
+}
+
+/// Like [eval] for a point that may let the call through while active, `fires`
+/// deciding whether it fires on this evaluation.
+pub fn eval_if<F: FnOnce() -> bool>(name: &str, fires: F) -> bool {
+    let fired = fail::eval(name, |_| {
+        hit(name);
+        let fired = fires();
+        observe(name, fired);
+        fired
+    });
+    if fired.is_none() {
+        observe(name, false);
+        reached_inactive(name);
+    }
+    fired == Some(true)
+}
+
+/// Registers `observer` to be called with every evaluation of a flunk point,
+/// with the point's name and whether it fired, replacing the one registered
+/// before. A point fires when it is active and, for one failing with a
+/// probability or on a count, decides to fail on this evaluation. The observer
+/// is called before the point's action runs. Without an observer evaluations
+/// cost an atomic load.
+///
+/// ```
+/// use std::sync::atomic::{AtomicUsize, Ordering};
+///
+/// static FIRED: AtomicUsize = AtomicUsize::new(0);
+///
+/// kaos::on_flunk(|name, fired| {
+///     if fired {
+///         FIRED.fetch_add(1, Ordering::Relaxed);
+///         eprintln!("injected a fault at {}", name);
+///     }
+/// });
+/// ```
+pub fn on_flunk<F: Fn(&str, bool) + Send + Sync + 'static>(observer: F) {
+    *OBSERVER.write().unwrap_or_else(PoisonError::into_inner) = Some(Box::new(observer));
+    OBSERVED.store(true, Ordering::SeqCst);
+}
+
+fn observe(name: &str, fired: bool) {
+    if !OBSERVED.load(Ordering::Relaxed) {
+        return;
+    }
+    if let Some(observer) = &*OBSERVER.read().unwrap_or_else(PoisonError::into_inner) {
+        observer(name, fired);
+    }
+}
+
+// Active points nobody evaluated, sorted by name.
+fn unreached() -> Vec<String> {
+    let mut names = HITS
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .iter()
+        .filter(|(_, hits)| hits.load(Ordering::SeqCst) == 0)
+        .map(|(name, _)| name.clone())
+        .collect::<Vec<_>>();
+    names.sort();
+    names
+}
+
+// Reports an inactive point reached by a kaos test, usually a misspelled name,
+// unless `KAOS_FLUNKS` deliberately picked the active points.
+fn reached_inactive(name: &str) {
+    let mut inactive = INACTIVE.lock().unwrap_or_else(PoisonError::into_inner);
+    if let Some(inactive) = inactive.as_mut() {
+        if env::flunks().is_none() && inactive.insert(name.to_owned()) {
+            eprintln!("{}\"{}\"", INACTIVE_MARKER, name);
+        }
+    }
+}
+
+// The first of `groups` that is active.
+fn active_group<'a>(groups: &[&'a str]) -> Option<&'a str> {
+    let active = GROUPS.lock().unwrap_or_else(PoisonError::into_inner);
+    groups.iter().copied().find(|group| active.contains(*group))
+}
+
+fn reset_groups() {
+    GROUPS.lock().unwrap_or_else(PoisonError::into_inner).clear();
+}
+
+fn reset_hits() {
+    HITS.lock().unwrap_or_else(PoisonError::into_inner).clear();
+}
+
+fn hit(name: &str) {
+    let counter = HITS
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .entry(name.to_owned())
+        .or_default()
+        .clone();
+    counter.fetch_add(1, Ordering::SeqCst);
+}
+
+/// How many times the named flunk point has been evaluated while active in
+/// the current scenario, including the evaluation in progress. Points sharing
+/// a name share the count.
+pub fn hits(name: &str) -> usize {
+    HITS.lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .get(name)
+        .map_or(0, |hits| hits.load(Ordering::SeqCst))
+}
+
+// Incarnations of a supervised service draw differently after each restart.
+fn seeded_rng() -> TestRng {
+    let mut bytes = [0; 32];
+    // Kaos sets the surge itself, it is always well-formed here.
+    let surge = env::surge().ok().flatten().unwrap_or(0);
+    bytes[..8].copy_from_slice(&surge.to_le_bytes());
+    bytes[8..16].copy_from_slice(&env::restart().unwrap_or(0).to_le_bytes());
+    TestRng::from_seed(RngAlgorithm::ChaCha, &bytes)
+}
+
+pub(crate) fn rng_from_seed(seed: u64) -> TestRng {
+    let mut bytes = [0; 32];
+    bytes[..8].copy_from_slice(&seed.to_le_bytes());
+    TestRng::from_seed(RngAlgorithm::ChaCha, &bytes)
+}
+
+/// Decides whether a flunk with the given failure probability fires on this
+/// evaluation.
+pub fn roll(probability: f64) -> bool {
+    if !(0.0..=1.0).contains(&probability) {
+        panic!(
+            "KAOS: flunk probability must be within [0.0, 1.0], got {}",
+            probability
+        );
+    }
+
+    if probability == 0.0 {
+        return false;
+    }
+    if probability == 1.0 {
+        return true;
+    }
+
+    let mut rng = RNG.lock().unwrap_or_else(PoisonError::into_inner);
+    let sample = (rng.next_u64() >> 11) as f64 / (1u64 << 53) as f64;
+    sample < probability
+}
+
+/// Picks which of `count` actions a flunk point takes, from the same seeded
+/// generator as [roll].
+pub fn pick(count: usize) -> usize {
+    let mut rng = RNG.lock().unwrap_or_else(PoisonError::into_inner);
+    (rng.next_u64() % count as u64) as usize
+}
+
+/// Blocks the calling thread for the injected latency and reports it on
+/// stderr so the harness can account for it.
+pub fn sleep<D: Into<Duration>>(name: &str, duration: D) {
+    let duration = duration.into();
+    eprintln!(
+        "{}\"{}\" for {}ms",
+        DELAY_MARKER,
+        name,
+        duration.as_millis()
+    );
+    thread::sleep(duration);
+}
+
+/// Future that hands control back to the executor once before completing, so
+/// an async flunk fails at an await point like real async faults do.
+#[derive(Debug, Default)]
+pub struct Yield {
+    yielded: bool,
+}
+
+pub fn yield_now() -> Yield {
+    Yield::default()
+}
+
+impl Future for Yield {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if self.yielded {
+            Poll::Ready(())
+        } else {
+            self.yielded = true;
+            cx.waker().wake_by_ref();
+            Poll::Pending
+        }
+    }
+}
@@ -0,0 +1,111 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap as Map;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::error::{Error, Result};
+use crate::report::TestResult;
+
+// Older observations are dropped so the file doesn't grow without bound.
+const MAX_OBSERVATIONS: usize = 1024;
+
+/// Surges tried by chaotic runs of previous invocations, keyed by test path.
+#[derive(Serialize, Deserialize, Default, Debug)]
+pub struct History {
+    #[serde(default)]
+    tests: Map<String, Vec<Observation>>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Observation {
+    pub surge: isize,
+    pub failed: bool,
+}
+
+impl History {
+    pub fn load(path: &Path) -> History {
+        fs::read(path)
+            .ok()
+            .and_then(|json| serde_json::from_slice(&json).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let write = || -> io::Result<()> {
+            let json = serde_json::to_vec(self)?;
+            fs::write(path, json)
+        };
+        write().map_err(|err| Error::WriteHistory(path.to_owned(), err))
+    }
+
+    pub fn record(&mut self, results: &[TestResult]) {
+        for result in results {
+            let mut runs = result
+                .runs
+                .iter()
+                .filter_map(|run| {
+                    Some(Observation {
+                        surge: run.surge?,
+                        failed: !run.passed,
+                    })
+                })
+                .peekable();
+            if runs.peek().is_none() {
+                continue;
+            }
+
+            let observations = self
+                .tests
+                .entry(result.path.to_string_lossy().into_owned())
+                .or_default();
+            observations.extend(runs);
+            let excess = observations.len().saturating_sub(MAX_OBSERVATIONS);
+            observations.drain(..excess);
+        }
+    }
+
+    /// Smallest surge at which the test has been seen failing, i.e. where the
+    /// service starts to fall short of the availability asked from it.
+    pub fn boundary(&self, path: &Path) -> Option<isize> {
+        self.tests
+            .get(path.to_string_lossy().as_ref())?
+            .iter()
+            .filter(|observation| observation.failed)
+            .map(|observation| observation.surge)
+            .min()
+    }
+}
+
+#[test]
+fn test_boundary() {
+    use crate::report::RunResult;
+    use std::path::PathBuf;
+    use std::time::Duration;
+
+    let run = |surge, passed| RunResult {
+        surge: Some(surge),
+        elapsed: Duration::from_millis(0),
+        passed,
+    };
+    let result = TestResult {
+        name: "kaos000".to_owned(),
+        path: PathBuf::from("kaos-tests/chaos.rs"),
+        expected: crate::Expected::Chaotic,
+        passed: false,
+        failure: None,
+        elapsed: Duration::from_millis(0),
+        elapsed_stats: None,
+        attempts: 1,
+        runs: vec![run(900, false), run(100, true), run(420, false), run(300, true)],
+        infra_failure: false,
+        description: None,
+    };
+
+    let mut history = History::default();
+    assert_eq!(history.boundary(&result.path), None);
+
+    history.record(&[result]);
+    assert_eq!(history.boundary(Path::new("kaos-tests/chaos.rs")), Some(420));
+    assert_eq!(history.boundary(Path::new("kaos-tests/other.rs")), None);
+}
@@ -0,0 +1,151 @@
+use glob::{MatchOptions, Pattern};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::error::{Error, Result};
+
+/// Directory holding the test launchers, relative to the crate being tested.
+pub const TESTS_DIR: &str = "kaos-tests";
+pub const FILE_NAME: &str = ".kaosignore";
+
+const OPTIONS: MatchOptions = MatchOptions {
+    case_sensitive: true,
+    require_literal_separator: true,
+    require_literal_leading_dot: false,
+};
+
+/// Gitignore-style patterns, relative to the kaos-tests directory, for paths
+/// that should never be turned into test binaries.
+#[derive(Debug, Default)]
+pub struct Ignore {
+    dir: PathBuf,
+    rules: Vec<Rule>,
+}
+
+#[derive(Debug)]
+struct Rule {
+    pattern: Pattern,
+    negated: bool,
+    // Pattern contained a slash so it matches from the kaos-tests directory,
+    // otherwise it matches a file or directory name at any depth.
+    anchored: bool,
+    dir_only: bool,
+}
+
+impl Ignore {
+    /// Reads `.kaosignore` from `dir`. A missing file ignores nothing.
+    pub fn load(dir: &Path) -> Result<Ignore> {
+        let path = dir.join(FILE_NAME);
+        match fs::read_to_string(&path) {
+            Ok(contents) => Ignore::parse(dir, &contents),
+            Err(ref err) if err.kind() == io::ErrorKind::NotFound => Ok(Ignore::default()),
+            Err(err) => Err(Error::Open(path, err)),
+        }
+    }
+
+    pub fn parse(dir: &Path, contents: &str) -> Result<Ignore> {
+        let mut rules = Vec::new();
+
+        for line in contents.lines() {
+            let line = line.trim_end();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (negated, line) = match line.strip_prefix('!') {
+                Some(rest) => (true, rest),
+                None => (false, line),
+            };
+            let (dir_only, line) = match line.strip_suffix('/') {
+                Some(rest) => (true, rest),
+                None => (false, line),
+            };
+            let anchored = line.contains('/');
+            let line = line.trim_start_matches('/');
+
+            rules.push(Rule {
+                pattern: Pattern::new(line)?,
+                negated,
+                anchored,
+                dir_only,
+            });
+        }
+
+        Ok(Ignore {
+            dir: dir.to_owned(),
+            rules,
+        })
+    }
+
+    /// Whether `path` is excluded. Paths outside the kaos-tests directory are
+    /// never ignored. Later patterns override earlier ones, as in gitignore.
+    pub fn is_ignored(&self, path: &Path) -> bool {
+        let relative = match path.strip_prefix(&self.dir) {
+            Ok(relative) => relative,
+            Err(_) => return false,
+        };
+        let components = relative
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>();
+
+        let mut ignored = false;
+        for rule in &self.rules {
+            if rule.negated == ignored && rule.matches(&components) {
+                ignored = !rule.negated;
+            }
+        }
+        ignored
+    }
+}
+
+impl Rule {
+    // A directory match excludes everything beneath it, so every leading
+    // sequence of components is a candidate.
+    fn matches<S: AsRef<str>>(&self, components: &[S]) -> bool {
+        (1..=components.len()).any(|len| {
+            if self.dir_only && len == components.len() {
+                return false;
+            }
+            if self.anchored {
+                let prefix = components[..len]
+                    .iter()
+                    .map(AsRef::as_ref)
+                    .collect::<Vec<_>>()
+                    .join("/");
+                self.pattern.matches_with(&prefix, OPTIONS)
+            } else {
+                self.pattern.matches_with(components[len - 1].as_ref(), OPTIONS)
+            }
+        })
+    }
+}
+
+#[test]
+fn test_kaosignore() {
+    let dir = Path::new(TESTS_DIR);
+    let ignore = Ignore::parse(
+        dir,
+        "\
+# helpers
+common.rs
+fixtures/
+/nested/**/*.rs
+!nested/**/keep.rs
+*_helper.rs
+",
+    )
+    .unwrap();
+
+    let ignored = |path: &str| ignore.is_ignored(&dir.join(path));
+    assert!(ignored("common.rs"));
+    assert!(ignored("deep/common.rs"));
+    assert!(ignored("fixtures/data.rs"));
+    assert!(!ignored("fixtures.rs"));
+    assert!(ignored("nested/a/b.rs"));
+    assert!(!ignored("nested/a/keep.rs"));
+    assert!(ignored("db_helper.rs"));
+    assert!(!ignored("chaos.rs"));
+    assert!(!ignore.is_ignored(Path::new("elsewhere/common.rs")));
+}
@@ -16,7 +16,7 @@
 //!
 //! A minimal launcher for kaos setup looks like this:
 //!
-//! ```
+//! ```no_run
 //! #[test]
 //! fn chaos_tests() {
 //!     let k = kaos::Runs::new();
@@ -39,6 +39,39 @@
 //! path = "kaos-tests/launcher.rs"
 //! ```
 //!
+//! Files in `kaos-tests` that aren't tests, like the launcher itself or shared fixtures, can be
+//! listed in a `kaos-tests/.kaosignore` file. It takes gitignore-style patterns relative to the
+//! `kaos-tests` directory, with support for `*`, `**` and `!` negation:
+//!
+//! ```text
+//! launcher.rs
+//! fixtures/
+//! !fixtures/keep_me.rs
+//! ```
+//!
+//! A subset of the tests can be selected by passing filters to the launcher:
+//! `cargo test -- chaos_tests kaos=network` runs the tests whose path contains `network`, while
+//! `kaosfile=network.rs` runs only the tests whose file name is exactly `network.rs`.
+//!
+//! Running with `KAOS_DRY_RUN=1` lists the tests kaos would run, with their modes and surge
+//! ranges, without building anything.
+//!
+//! Kaos builds the tests in a crate it generates under `target/tests`. Running with
+//! `KAOS_DUMP_PROJECT=1` prints where, to look into the manifest and config it was given.
+//!
+//! With `KAOS_WATCH=1` kaos keeps running after the tests, watching `kaos-tests`, `src`,
+//! `Cargo.toml` and the directories of the tests for changes. A changed test file reruns its tests,
+//! any other changed file, a source file or a helper module next to the tests, reruns all of them.
+//! Stop it with Ctrl-C.
+//!
+//! How much kaos prints is set by `KAOS_VERBOSE`: `0` for the summary only, `1`, the default, for
+//! the outcome of each test, `2` for the progress of chaotic tests too, like `run 12/256 (surge
+//! 340ms)` before every run, and `3` for the output of the services even when they pass. Set
+//! `KAOS_QUIET=1` to hide the progress at any level.
+//!
+//! Output is colored by severity and ends with a summary of passed and failed tests. Colors are
+//! turned off by the `NO_COLOR` convention, and `KAOS_COLOR=auto|always|never` overrides both.
+//!
 //! Mind that there two types of tests, first one is: availability test, the latter one is chaotic test which seeks the minimum timing, failure, MTBF combination.
 //! The setup shows availability tests as an example. When availability tests run you will see:
 //!
@@ -46,6 +79,10 @@
 //! <img src="https://raw.githubusercontent.com/vertexclique/kaos/master/img/availability_run.png" width="700">
 //! </p>
 //!
+//! Services that are expected to crash and come back, the way an orchestrator restarts them, can
+//! be tested with [Runs::supervised]. It restarts a crashed service up to a number of times and
+//! fails the test when the time spent down exceeds the budget.
+//!
 //!
 //! ## Definining flunks
 //! In kaos there is a concept of [flunk]. Every flunk is a point of failure with panic. This can be redefinable.
@@ -62,6 +99,177 @@
 //! ```
 //! This flunk point will be used later by kaos.
 //!
+//! A flunk can also fail sporadically. Pass the probability of failing on each evaluation
+//! as the second argument:
+//! ```rust
+//! use kaos::flunk;
+//! fn vec_check(v: &Vec<usize>) {
+//!   if v.len() >= 3 {
+//!     // Fails one out of four times the point is reached.
+//!     flunk!("sometimes-fail-when-three-elems", 0.25);
+//!   }
+//! }
+//! ```
+//! Randomness is seeded from the surge of the run, so a chaotic run replays the same decisions.
+//!
+//! Functions can get a point at their entry with the [flunkable] attribute instead, named after the
+//! function so the name follows renames:
+//! ```rust
+//! use kaos::flunkable;
+//! // The point `connect`, namespaced by the module.
+//! #[flunkable]
+//! fn connect() {}
+//! // Overrides of the name and the probability.
+//! #[flunkable(name = "flush-disk", rate = 0.1)]
+//! fn flush() {}
+//! ```
+//!
+//! Not every outage is a crash. [flunk_delay] injects latency by blocking the calling thread
+//! instead of panicking:
+//! ```rust
+//! use kaos::flunk_delay;
+//! use std::time::Duration;
+//! fn fetch() {
+//!   flunk_delay!("slow-fetch", Duration::from_millis(500));
+//! }
+//! ```
+//! Injected latency is reported next to the measured availability when a run falls short.
+//!
+//! Faults can be aimed at one state of the service with [flunk_if], which only engages the point
+//! while its condition holds:
+//! ```rust
+//! use kaos::flunk_if;
+//! fn append(log: &mut Vec<u8>, entry: &[u8]) {
+//!   // Only fails writes that grow the log past a segment.
+//!   flunk_if!("segment-full", log.len() + entry.len() > 4096);
+//!   log.extend_from_slice(entry);
+//! }
+//! ```
+//!
+//! Faults that corrupt state instead of crashing are injected with [flunk_with], which calls the
+//! given closure when the point fires:
+//! ```rust
+//! use kaos::flunk_with;
+//! fn write(buffer: &mut Vec<u8>) {
+//!   // Loses the tail of the write.
+//!   flunk_with!("torn-write", || buffer.truncate(buffer.len() / 2));
+//! }
+//! ```
+//!
+//! Fallible code can exercise its error path without unwinding with [flunk_err], which makes the
+//! enclosing function return the given error:
+//! ```rust
+//! use kaos::flunk_err;
+//! # #[derive(Debug)]
+//! # enum DbError { Timeout }
+//! fn query() -> Result<Vec<u8>, DbError> {
+//!   flunk_err!("db-timeout", DbError::Timeout);
+//!   Ok(vec![])
+//! }
+//! ```
+//!
+//! I/O code returning `io::Result` gets ready-made failures from [flunk_io], with an
+//! `io::ErrorKind` to pick the failure:
+//! ```rust
+//! use kaos::flunk_io;
+//! use std::io::{self, ErrorKind};
+//! fn send(payload: &[u8]) -> io::Result<usize> {
+//!   flunk_io!("peer-reset", ErrorKind::ConnectionReset);
+//!   Ok(payload.len())
+//! }
+//! ```
+//!
+//! A single point can exercise several failure modes with [flunk_any], which picks one of its
+//! actions each time it fires:
+//! ```rust
+//! use kaos::flunk_any;
+//! use std::io::{self, ErrorKind};
+//! use std::time::Duration;
+//! fn recv(buffer: &mut [u8]) -> io::Result<usize> {
+//!   flunk_any!("recv", [panic, delay(Duration::from_millis(500)), err(ErrorKind::TimedOut)]);
+//!   Ok(buffer.len())
+//! }
+//! ```
+//!
+//! Async services use [flunk_async], which yields to the executor before failing. It needs a
+//! running runtime, like any other `.await`:
+//! ```rust
+//! use kaos::flunk_async;
+//! # #[derive(Debug)]
+//! # enum RpcError { Unavailable }
+//! async fn call() -> Result<(), RpcError> {
+//!   // Panics after yielding.
+//!   flunk_async!("rpc-panic");
+//!   // Returns the error after yielding.
+//!   flunk_async!("rpc-unavailable", RpcError::Unavailable);
+//!   Ok(())
+//! }
+//! ```
+//!
+//! Failures that only show up after repeated use are modelled by [flunk_after]. It stays silent
+//! for the given number of evaluations and panics on the next one:
+//! ```rust
+//! use kaos::flunk_after;
+//! fn connect() {
+//!   // The fourth connection fails.
+//!   flunk_after!("fourth-connection", 3);
+//! }
+//! ```
+//!
+//! Intermittent outages are modelled by [flunk_every], which fails every n-th evaluation rather
+//! than a random share of them:
+//! ```rust
+//! use kaos::flunk_every;
+//! fn handle_request() {
+//!   // One request in ten fails.
+//!   flunk_every!("request", 10);
+//! }
+//! ```
+//!
+//! Code assuming that reads and writes use the whole buffer is exercised by [ChaosReader] and
+//! [ChaosWriter], which wrap any reader or writer and make its operations short while their point
+//! is active. Like [flunk_every], `.every(n)` only shortens every n-th operation:
+//! ```rust
+//! use std::io::Write;
+//! fn append(file: std::fs::File, entry: &[u8]) -> std::io::Result<()> {
+//!   let mut file = kaos::ChaosWriter::new(file, "short-append").every(4);
+//!   file.write_all(entry)
+//! }
+//! ```
+//!
+//! [flunk_hits] counts the evaluations of an active point in the current [kaostest], to assert
+//! that a service gives up after a bounded number of retries:
+//! ```rust
+//! use kaos::{flunk_err, flunk_hits, kaostest};
+//! fn fetch() -> Result<u32, String> {
+//!   flunk_err!("fetch", "unavailable".to_owned());
+//!   Ok(7)
+//! }
+//! fn fetch_with_retries(attempts: usize) -> Result<u32, String> {
+//!   let mut result = fetch();
+//!   for _ in 1..attempts {
+//!     if result.is_ok() {
+//!       break;
+//!     }
+//!     result = fetch();
+//!   }
+//!   result
+//! }
+//! kaostest!("fetch", {
+//!   assert!(fetch_with_retries(3).is_err());
+//!   assert_eq!(flunk_hits("fetch"), 3);
+//! });
+//! ```
+//!
+//! Flunk points are named after the module declaring them, so `flunk!("timeout")` in `net` is
+//! `my_service::net::timeout`. Activating the bare name `timeout` turns on every point called
+//! `timeout`, activating `my_service::net::timeout` only the one in `net`. [flunk_global] declares a
+//! point by its bare name alone.
+//!
+//! Points of a fault domain can share a group, `flunk!("write", group = "storage")`, and a point can
+//! be in several with `groups = ["storage", "io"]`. Activating `group:storage`, from `kaostest!` or
+//! `KAOS_FLUNKS`, turns on all of its points.
+//!
 //! ## Writing tests
 //! Test harness will execute tests marked by a launcher. An example test for the flunk mentioned above is like this:
 //! ```
@@ -86,13 +294,48 @@
 //!          }
 //! );
 //! ```
+//!
+//! Inside `kaostest!` a flunk panic is written to stderr as its one line message, without the
+//! panic hook's location and backtrace notes. Any other panic goes to the panic hook as usual.
+//!
+//! To explore which flunk breaks a service, set `KAOS_FLUNKS` to a comma separated list of flunk
+//! names. Kaos tests then activate only those points, leaving the rest as no-ops.
+//!
+//! Long-running services can have their points toggled while they run. On Unix, a kaos test
+//! whose service has `KAOS_CONTROL_SOCK` set, for instance with [Runs::env], listens on a socket
+//! at that path for one command per line, `enable storage::write` or `disable group:net`, and
+//! answers each with `ok` or `error: <reason>`:
+//! ```text
+//! $ echo "enable storage::write" | socat - UNIX-CONNECT:/tmp/service.sock
+//! ok
+//! ```
+//! Points enabled this way are turned off with the rest when the test's scenario ends. Tests
+//! running in parallel need a socket path each.
+//!
+//! Flunk activity can be fed to a metrics system with [on_flunk], whose observer is called with the
+//! name of every point evaluated and whether it fired, to line injected faults up with what the
+//! service's metrics show.
+//!
+//! ## `no_std` services
+//! Turning off the default `std` feature leaves only the flunk points, for `no_std` and bare-metal
+//! crates: `kaos = { version = "*", default-features = false }`. [flunk], [flunk_global],
+//! [flunk_if], [flunk_with], [flunk_err] and [flunkable] work as usual, backed by a fixed registry
+//! of at most 32 active names instead of the `fail` crate. Points are turned on by the code itself with
+//! `kaos::flunk_activate(&["write"])`, turned off with `kaos::flunk_reset()`, and probabilistic
+//! points draw from a generator seeded with `kaos::flunk_seed`.
+//!
+//! Without std there is no harness: no `kaostest!`, no `KAOS_FLUNKS` or other environment variable
+//! configuration, no hit counts or [on_flunk], and no reporting of activated, unreached or
+//! recovered points. The other flunk macros need std, and points pass their bare name to the panic
+//! message, without the module namespace.
+//!
 //! # Chaos Tests
 //!
 //! In addition to availability tests mentioned above we can test the software with chaos tests too.
 //! For using chaotic measures and finding bare minimum failure, timing and MTBF combination
 //! you can configure chaos tests in your launcher:
 //!
-//! ```
+//! ```no_run
 //! #[test]
 //! fn chaos_tests() {
 //!     let k = kaos::Runs::new();
@@ -120,8 +363,101 @@
 //! <img src="https://raw.githubusercontent.com/vertexclique/kaos/master/img/chaotic_run.png" width="700">
 //! </p>
 //!
+//! Test paths can also be glob patterns with `{a,b}` alternatives, so
+//! `k.chaotic("kaos-tests/{net,disk}/*.rs", 10, 10 * 1000)` registers every test of both directories.
+//! A file registered several times, by pattern or by name, runs once, with the duration, surges
+//! and kind of the first registration covering it. Later ones skip it. Tests are numbered in the
+//! order of their paths, whatever the order of the registrations.
+//!
+//! A chaotic test fails as soon as one run goes down before its surge. Services with an availability
+//! objective can be held to that instead with [Runs::chaotic_slo]:
+//! `k.chaotic_slo("kaos-tests/replica.rs", 1, Duration::from_secs(2), 95.0)` only fails when fewer
+//! than 95% of the runs stay up for 2 seconds.
+//!
+//! A failing chaotic test prints the command replaying its failing run, `KAOS_SEED=... KAOS_SURGE=...
+//! cargo test --test <launcher> -- <test> kaos=<path>`, with `--lib` for a launcher among the unit
+//! tests. With `KAOS_SURGE` set, chaotic tests run once at that surge.
+//!
+//! Services run with `RUST_BACKTRACE=1` unless it is set otherwise, so a chaotic run that crashes
+//! reports where, with the frames of the standard library and kaos left out of the backtrace.
+//!
+//! How many runs an MTBF estimate needs depends on the service. [Runs::chaotic_until_converged]
+//! keeps sampling until the estimate stops moving, `k.chaotic_until_converged("kaos-tests/replica.rs",
+//! 200, 0.05)` stops once a run changes it by 5% or less.
+//!
+//! Leaks and slow degradation take longer than a handful of runs to show. [Runs::soak] keeps
+//! running a chaotic test for a wall-clock budget instead, `k.soak("kaos-tests/replica.rs",
+//! Duration::from_secs(600), 100..2000)` runs it for ten minutes and reports how many runs it got
+//! through and how many of them failed.
+//!
+//! Runs of a chaotic test follow each other right away. Services that need a moment to release
+//! ports or clean up after themselves can be given one with [Runs::settle], the time spent waiting
+//! is reported separately in the timing breakdown.
+//!
+//! An availability test that passes by a hair is one slow machine away from failing.
+//! `k.warn_margin(0.1)` warns about runs that beat their duration by less than 10% of it.
+//!
+//! Code without flunk points can still face a failing network. `k.partition(&[5432],
+//! Duration::from_secs(2))` drops the traffic on port 5432 for the first two seconds of every run,
+//! through firewall rules that take root to install.
+//!
+//! State shared by the whole suite, like a seeded database, can be created by a binary given to
+//! [Runs::setup_bin], which is built against the crate and runs once before any test. The suite
+//! stops if it fails. [Runs::teardown_bin] runs once after the tests, whether or not they passed.
+//!
+//! After its sweep, every chaotic test prints a histogram of its runs by surge, passing runs as `#`
+//! and failing ones as `x`, which shows at a glance where the service stops coping.
+//!
+//! Paths in diagnostics and expected outputs are shown relative to the crate as `$DIR`. Checkouts
+//! behind symlinks or in virtualized layouts can map their paths onto that with
+//! [Runs::normalize_path], so golden outputs are shared across machines.
+//!
+//! The same normalized compiler output is handed to the checks added with
+//! [Runs::assert_diagnostics], as [Variations], to fail tests whose build says something it
+//! shouldn't.
+//!
+//! Tests needing Linux cgroups, namespaces or other platform specifics are registered with
+//! [Runs::only_on], e.g. `k.only_on(&[Os::Linux]).chaotic("kaos-tests/cgroup-oom.rs", 2, 1000)`, and
+//! reported as skipped on other systems.
+//!
+//! Tests are built and run with the cargo running `cargo test`. Toolchains and wrappers are picked
+//! with [Runs::cargo_bin] and [Runs::cargo_arg], e.g. `k.cargo_arg("+nightly")` or
+//! `k.cargo_bin("cross")`.
+//!
 //! Now you know all the basics, what you have to do is *unleash some chaos* with `cargo test`.
 //!
+//! # Reports
+//!
+//! Besides the terminal output, kaos can write machine-readable results for CI:
+//! * [Runs::report_to] writes a JSON report with every test and the surges it tried.
+//! * Setting `KAOS_JUNIT_PATH`, or [RunsBuilder::junit], writes a JUnit XML report to that path.
+//!
+//! Reports are written even when tests fail.
+//!
+//! Harnesses embedding kaos can get the results as a [RunReport] from [Runs::run_collect]
+//! instead, which runs the tests right away and leaves acting on failures to the caller.
+//!
+//! For live monitoring, set `KAOS_EVENTS_PATH` to a file or named pipe. Kaos appends one JSON
+//! object per line as things happen, `test_started`, `run_completed`, `test_passed`,
+//! `test_failed` and `finished`, each with a `timestamp_ms` and the test binary's name.
+//!
+//! # Configuration file
+//!
+//! Teams can share defaults in a `kaos.toml` at the crate root instead of the launcher:
+//!
+//! ```toml
+//! run_count = 25
+//! max_surge = 2000
+//! seed = 42
+//! jobs = 4
+//! report = "target/kaos-report.json"
+//! junit = "target/kaos-junit.xml"
+//! ```
+//!
+//! `run_count` and `max_surge` are used by [Runs::chaotic_default]. Environment variables take
+//! precedence over options set in code, which take precedence over the file, which takes
+//! precedence over the built-in defaults.
+//!
 //! Kaos is using the same approach that [trybuild](https://docs.rs/trybuild) has.
 //! Instead of being compiler-like test harness, it has diverged to be chaos engineering
 //! oriented harness.
@@ -129,102 +465,1675 @@
 #![doc(
     html_logo_url = "https://raw.githubusercontent.com/vertexclique/kaos/master/img/chaos.png"
 )]
+#![cfg_attr(not(feature = "std"), no_std)]
 
+// Lets the tests expand `#[flunkable]`, which refers to the crate by name.
+#[cfg(test)]
+extern crate self as kaos;
+
+#[cfg(feature = "std")]
 extern crate humantime;
 
+#[cfg(feature = "std")]
 #[macro_use]
 mod term;
 
+#[cfg(feature = "std")]
 #[macro_use]
 mod path;
 
+#[cfg(feature = "std")]
 mod cargo;
+#[cfg(feature = "std")]
+mod chaos_io;
+#[cfg(all(feature = "std", unix))]
+mod control;
+#[cfg(feature = "std")]
 mod dependencies;
+#[cfg(feature = "std")]
 mod diff;
+#[cfg(feature = "std")]
 mod env;
+#[cfg(feature = "std")]
 mod error;
+#[cfg(feature = "std")]
+mod events;
+#[cfg(feature = "std")]
 mod features;
+#[cfg(feature = "std")]
+mod flunk;
+#[cfg(feature = "std")]
+mod history;
+#[cfg(feature = "std")]
+mod ignore;
+#[cfg(feature = "std")]
 mod manifest;
+#[cfg(feature = "std")]
 mod message;
+#[cfg(feature = "std")]
 mod normalize;
+#[cfg(feature = "std")]
+mod partition;
+#[cfg(feature = "std")]
+mod process;
+#[cfg(feature = "std")]
+mod report;
+#[cfg(feature = "std")]
 mod run;
+#[cfg(feature = "std")]
 mod rustflags;
+#[cfg(feature = "std")]
+mod settings;
+#[cfg(feature = "std")]
+mod watch;
+#[cfg(not(feature = "std"))]
+mod bare;
 mod macros;
 
+#[cfg(feature = "std")]
+use serde::Serialize;
+#[cfg(feature = "std")]
+use normalize::PathRule;
+#[cfg(feature = "std")]
+use partition::Partition;
+#[cfg(feature = "std")]
 use std::cell::RefCell;
+#[cfg(feature = "std")]
+use std::collections::BTreeMap;
+#[cfg(feature = "std")]
+use std::fmt::{self, Debug};
+#[cfg(feature = "std")]
+use std::ops::Range;
+#[cfg(feature = "std")]
 use std::path::{Path, PathBuf};
+#[cfg(feature = "std")]
+use std::sync::{Arc, Mutex, PoisonError};
+#[cfg(feature = "std")]
 use std::{time::Duration, thread};
 
+#[cfg(feature = "std")]
+#[doc(hidden)]
+pub use flunk::eval as flunker;
+#[cfg(feature = "std")]
+#[doc(hidden)]
+pub use flunk::eval_in as flunker_in;
+#[cfg(feature = "std")]
 #[doc(hidden)]
-pub use fail::eval as flunker;
+pub use flunk::eval_in_groups as flunker_in_groups;
+#[cfg(feature = "std")]
+#[doc(hidden)]
+pub use flunk::eval_if as flunker_if;
+#[cfg(feature = "std")]
+#[doc(hidden)]
+pub use flunk::eval_in_if as flunker_in_if;
+#[cfg(feature = "std")]
+#[doc(hidden)]
+pub use flunk::eval_in_groups_if as flunker_in_groups_if;
+#[cfg(feature = "std")]
 #[doc(hidden)]
 pub use fail::cfg as flunker_cfg;
+#[cfg(feature = "std")]
+#[doc(hidden)]
+pub use flunk::Scenario as KaosFailScenario;
+#[cfg(feature = "std")]
+#[doc(hidden)]
+pub use flunk::activate as flunk_activate;
+#[cfg(feature = "std")]
+pub use flunk::hits as flunk_hits;
+#[cfg(feature = "std")]
+pub use flunk::on_flunk;
+#[cfg(feature = "std")]
+#[doc(hidden)]
+pub use flunk::pick as flunk_pick;
+#[cfg(feature = "std")]
 #[doc(hidden)]
-pub use fail::FailScenario as KaosFailScenario;
+pub use flunk::roll as flunk_roll;
+#[cfg(feature = "std")]
+#[doc(hidden)]
+pub use flunk::sleep as flunk_sleep;
+#[cfg(feature = "std")]
+#[doc(hidden)]
+pub use flunk::yield_now as flunk_yield;
 
+#[cfg(not(feature = "std"))]
+#[doc(hidden)]
+pub use bare::eval as flunker;
+#[cfg(not(feature = "std"))]
+#[doc(hidden)]
+pub use bare::eval_in as flunker_in;
+#[cfg(not(feature = "std"))]
+#[doc(hidden)]
+pub use bare::eval_in_groups as flunker_in_groups;
+#[cfg(not(feature = "std"))]
+#[doc(hidden)]
+pub use bare::eval_if as flunker_if;
+#[cfg(not(feature = "std"))]
+#[doc(hidden)]
+pub use bare::eval_in_if as flunker_in_if;
+#[cfg(not(feature = "std"))]
+#[doc(hidden)]
+pub use bare::eval_in_groups_if as flunker_in_groups_if;
+#[cfg(not(feature = "std"))]
+#[doc(hidden)]
+pub use bare::pick as flunk_pick;
+#[cfg(not(feature = "std"))]
+#[doc(hidden)]
+pub use bare::roll as flunk_roll;
+#[cfg(feature = "std")]
+pub use normalize::{Diagnostics, Variations};
 
-pub use macros::*;
+pub use kaos_macros::flunkable;
+#[doc(hidden)]
+pub use macros::split as flunk_arg;
+#[cfg(feature = "std")]
+pub use chaos_io::{ChaosReader, ChaosWriter};
+#[cfg(feature = "std")]
+pub use report::{ElapsedStats, Mtbf, RunReport, RunResult, TestResult};
+#[cfg(not(feature = "std"))]
+pub use bare::{activate as flunk_activate, reset as flunk_reset, seed as flunk_seed};
 
 ///
 /// Chaotic runs test setup
+#[cfg(feature = "std")]
 #[derive(Debug)]
 pub struct Runs {
     runner: RefCell<Runner>,
 }
 
-#[derive(Debug)]
+#[cfg(feature = "std")]
+#[derive(Debug, Default)]
 struct Runner {
     tests: Vec<Test>,
+    seed: Option<u64>,
+    report: Option<PathBuf>,
+    junit: Option<PathBuf>,
+    jobs: Option<usize>,
+    fail_fast: bool,
+    exit_codes: bool,
+    timeout: Option<Duration>,
+    settle: Duration,
+    warn_margin: Option<f64>,
+    partition: Option<Partition>,
+    require_flunk_coverage: bool,
+    target_crate: Option<String>,
+    extra_crates: Vec<(String, PathBuf)>,
+    cargo_bin: Option<PathBuf>,
+    cargo_args: Vec<String>,
+    retries: usize,
+    edition: Option<String>,
+    without_history: bool,
+    discard_generated: bool,
+    log_dir: Option<PathBuf>,
+    run_dir: Option<PathBuf>,
+    setup_bin: Option<PathBuf>,
+    teardown_bin: Option<PathBuf>,
+    isolated_tmp: bool,
+    path_rules: Vec<PathRule>,
+    target: Option<String>,
+    profile: Option<String>,
+    env: BTreeMap<String, String>,
+    explore: bool,
+    exclude_features: Vec<String>,
+    expected_outputs: Vec<(PathBuf, PathBuf)>,
+    hooks: Mutex<Hooks>,
+    diagnostic_checks: Vec<DiagnosticCheck>,
+    // Ran by `Runs::run_collect`, dropping the runs doesn't run them again.
+    collected: bool,
 }
 
+#[cfg(feature = "std")]
+// Called with the path of each test.
+type Hook = Box<dyn FnMut(&Path) + Send>;
+
+#[cfg(feature = "std")]
+// Checks the compiler output of a test that built, see [Runs::assert_diagnostics].
+type Check = dyn Fn(&Variations) -> Result<(), String> + Send + Sync;
+
+#[cfg(feature = "std")]
+#[derive(Clone)]
+struct DiagnosticCheck(Arc<Check>);
+
+#[cfg(feature = "std")]
+impl Debug for DiagnosticCheck {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("DiagnosticCheck")
+    }
+}
+
+#[cfg(feature = "std")]
+#[derive(Default)]
+struct Hooks {
+    before_each: Option<Hook>,
+    after_each: Option<Hook>,
+}
+
+#[cfg(feature = "std")]
+impl Debug for Hooks {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Hooks")
+            .field("before_each", &self.before_each.is_some())
+            .field("after_each", &self.after_each.is_some())
+            .finish()
+    }
+}
+
+#[cfg(feature = "std")]
 #[derive(Clone, Debug)]
 struct Test {
     path: PathBuf,
     duration: Option<Duration>,
-    max_surge: isize,
+    max_duration: Option<Duration>,
+    min_surge: usize,
+    max_surge: usize,
     expected: Expected,
+    distribution: Distribution,
+    tags: Vec<String>,
+    features: Vec<String>,
+    env: BTreeMap<String, String>,
+    expected_output: Option<PathBuf>,
+    supervision: Option<Supervision>,
+    slo: Option<Slo>,
+    convergence: Option<Convergence>,
+    // How long a soaked test keeps running, see [Runs::soak].
+    soak: Option<Duration>,
+    // Options left to `kaos.toml`, read once the tests are about to run.
+    from_settings: Option<FromSettings>,
+    description: Option<String>,
+    platforms: Vec<Os>,
+    // Which call registered the test, shared by the copies of a chaotic test.
+    registration: usize,
 }
 
+#[cfg(feature = "std")]
+// How a supervised test restarts its service, see [Runs::supervised].
 #[derive(Copy, Clone, Debug)]
-enum Expected {
+struct Supervision {
+    restarts: usize,
+    budget: Duration,
+}
+
+#[cfg(feature = "std")]
+// The availability a chaotic test holds its sweep to, see [Runs::chaotic_slo].
+#[derive(Copy, Clone, Debug)]
+struct Slo {
+    min_duration: Duration,
+    percentile: f64,
+}
+
+#[cfg(feature = "std")]
+// What a chaotic test takes from `kaos.toml`. Its surges go up to the file's
+// `max_surge`, sweeps registered with [Runs::chaotic_default] also run the
+// file's `run_count` times rather than once.
+#[derive(Copy, Clone, Debug)]
+enum FromSettings {
+    Sweep,
+    MaxSurge,
+}
+
+#[cfg(feature = "std")]
+// When a chaotic test stops sampling, see [Runs::chaotic_until_converged].
+#[derive(Copy, Clone, Debug)]
+struct Convergence {
+    max_runs: usize,
+    tolerance: f64,
+}
+
+///
+/// How a chaotic test spreads the surges it samples over its range, see
+/// [Runs::chaotic_weighted]
+#[cfg(feature = "std")]
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub enum Distribution {
+    /// Every surge in the range is equally likely.
+    #[default]
+    Uniform,
+    /// Density falls off exponentially from the start of the range, about
+    /// two thirds of the samples land in its lowest fifth.
+    Exponential,
+    /// Milder than [Distribution::Exponential], half of the samples land in the
+    /// lowest quarter of the range.
+    FrontLoaded,
+}
+
+///
+/// An operating system tests can be limited to, see [Runs::only_on]
+#[cfg(feature = "std")]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Os {
+    Linux,
+    MacOs,
+    Windows,
+    FreeBsd,
+    Android,
+    Ios,
+}
+
+#[cfg(feature = "std")]
+impl Os {
+    /// The name of the system in `cfg(target_os)`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Os::Linux => "linux",
+            Os::MacOs => "macos",
+            Os::Windows => "windows",
+            Os::FreeBsd => "freebsd",
+            Os::Android => "android",
+            Os::Ios => "ios",
+        }
+    }
+
+    /// Whether kaos runs on this system.
+    pub fn is_current(self) -> bool {
+        self.name() == std::env::consts::OS
+    }
+}
+
+///
+/// What a test expects of its service, see [TestResult::expected]
+#[cfg(feature = "std")]
+#[derive(Copy, Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Expected {
+    /// The service stays up, registered with [Runs::available].
     Available,
+    /// The service survives injected failures, registered with [Runs::chaotic].
     Chaotic
 }
 
+#[cfg(feature = "std")]
 impl Runs {
     #[allow(clippy::new_without_default)]
     pub fn new() -> Self {
         Runs {
-            runner: RefCell::new(Runner { tests: Vec::new() }),
+            runner: RefCell::new(Runner::default()),
+        }
+    }
+
+    ///
+    /// Configure global options of the chaotic runs.
+    ///
+    /// ```no_run
+    /// let k = kaos::Runs::builder()
+    ///     .seed(42)
+    ///     .jobs(4)
+    ///     .junit("target/kaos-junit.xml")
+    ///     .history(false)
+    ///     .build();
+    /// ```
+    pub fn builder() -> RunsBuilder {
+        RunsBuilder {
+            runner: Runner::default(),
         }
     }
 
+    ///
+    /// Chaotic runs test setup with a pinned seed for surge sampling.
+    ///
+    /// A failing chaotic run prints the seed it used; passing it here, or through
+    /// the `KAOS_SEED` environment variable, replays the same surges.
+    /// The environment variable takes precedence.
+    pub fn with_seed(seed: u64) -> Self {
+        let runs = Runs::new();
+        runs.runner.borrow_mut().seed = Some(seed);
+        runs
+    }
+
     pub fn available<P: AsRef<Path>>(&self, path: P, duration: Duration) {
+        self.push_available(path.as_ref(), duration, None);
+    }
+
+    ///
+    /// Availability test with the duration written like `"2s 500ms"`.
+    ///
+    /// Accepts what [humantime::parse_duration] does, the format kaos prints
+    /// durations in, for availabilities read from config files or the
+    /// environment. Nothing is registered when the duration is malformed.
+    ///
+    /// ```no_run
+    /// let k = kaos::Runs::new();
+    /// k.available_str("kaos-tests/startup.rs", "2s 500ms").unwrap();
+    /// ```
+    pub fn available_str<P: AsRef<Path>>(
+        &self,
+        path: P,
+        duration: &str,
+    ) -> Result<(), humantime::DurationError> {
+        let duration = humantime::parse_duration(duration)?;
+        self.available(path, duration);
+        Ok(())
+    }
+
+    ///
+    /// Availability test that also fails if the service runs for longer than
+    /// `max`.
+    ///
+    /// Catches services that should finish quickly but started hanging or
+    /// slowing down. Elapsed time is measured as for [Runs::available], so it
+    /// includes building the test. Panics unless `min <= max`.
+    pub fn available_within<P: AsRef<Path>>(&self, path: P, min: Duration, max: Duration) {
+        assert!(
+            min <= max,
+            "kaos: availability window of {} is empty, expected min <= max but got {:?}..{:?}",
+            path.as_ref().display(),
+            min,
+            max,
+        );
+        self.push_available(path.as_ref(), min, Some(max));
+    }
+
+    ///
+    /// Availability test that restarts the service when it crashes, like a
+    /// supervisor such as systemd or Kubernetes would.
+    ///
+    /// A service exiting with a non-zero status is restarted up to `restarts`
+    /// times. The test passes if an incarnation exits cleanly and the downtime,
+    /// the time spent in the ones that crashed, stays under `budget`. Every
+    /// incarnation gets the number of restarts before it in `KAOS_RESTART`,
+    /// and probabilistic flunks draw differently after each restart.
+    ///
+    /// ```no_run
+    /// # use std::time::Duration;
+    /// let k = kaos::Runs::new();
+    /// k.supervised("kaos-tests/flaky_start.rs", 3, Duration::from_secs(2));
+    /// ```
+    pub fn supervised<P: AsRef<Path>>(&self, path: P, restarts: usize, budget: Duration) {
+        self.push_available(path.as_ref(), Duration::from_secs(0), None);
+        if let Some(test) = self.runner.borrow_mut().tests.last_mut() {
+            test.supervision = Some(Supervision { restarts, budget });
+        }
+    }
+
+    fn push_available(&self, path: &Path, duration: Duration, max_duration: Option<Duration>) {
+        let registration = self.runner.borrow().tests.len();
         self.runner.borrow_mut().tests.push(Test {
-            path: path.as_ref().to_owned(),
+            path: path.to_owned(),
             duration: Some(duration),
-            max_surge: !0,
+            max_duration,
+            min_surge: 0,
+            max_surge: 0,
             expected: Expected::Available,
+            distribution: Distribution::Uniform,
+            tags: Vec::new(),
+            features: Vec::new(),
+            env: BTreeMap::new(),
+            expected_output: None,
+            supervision: None,
+            slo: None,
+            convergence: None,
+            soak: None,
+            from_settings: None,
+            description: None,
+            platforms: Vec::new(),
+            registration,
         });
     }
 
     pub fn chaotic<P: AsRef<Path>>(&self, path: P, run_count: usize, max_surge: usize) {
-        (0..run_count).into_iter().for_each(|_| {
+        self.chaotic_range(path, run_count, 0, max_surge);
+    }
+
+    ///
+    /// Chaotic test with the `run_count` and `max_surge` of `kaos.toml`.
+    ///
+    /// Without the file, or the options in it, the test runs 10 times with
+    /// surges up to 1000.
+    pub fn chaotic_default<P: AsRef<Path>>(&self, path: P) {
+        self.push_from_settings(path.as_ref(), 1, FromSettings::Sweep);
+    }
+
+    // Registers `run_count` copies of a chaotic test with the built-in
+    // `max_surge`, the file's options replace it once it is read.
+    fn push_from_settings(&self, path: &Path, run_count: usize, from_settings: FromSettings) -> usize {
+        let registration = self.runner.borrow().tests.len();
+        self.push_chaotic(path, run_count, 0, settings::MAX_SURGE, Distribution::Uniform);
+        for test in &mut self.runner.borrow_mut().tests[registration..] {
+            test.from_settings = Some(from_settings);
+        }
+        registration
+    }
+
+    ///
+    /// Chaotic test sampling surges from `min_surge..max_surge` only.
+    ///
+    /// Useful to focus on a band of surges away from zero where the
+    /// interesting failures happen. Panics unless `min_surge < max_surge`.
+    pub fn chaotic_range<P: AsRef<Path>>(
+        &self,
+        path: P,
+        run_count: usize,
+        min_surge: usize,
+        max_surge: usize,
+    ) {
+        self.push_chaotic(path.as_ref(), run_count, min_surge, max_surge, Distribution::Uniform);
+    }
+
+    ///
+    /// Chaotic test sampling surges from `0..max_surge` according to
+    /// `distribution` instead of uniformly.
+    ///
+    /// Concentrating samples near zero finds the smallest surge the service
+    /// copes with in fewer runs.
+    ///
+    /// ```no_run
+    /// use kaos::Distribution;
+    ///
+    /// let k = kaos::Runs::new();
+    /// k.chaotic_weighted("kaos-tests/*.rs", 2, 1000, Distribution::Exponential);
+    /// ```
+    pub fn chaotic_weighted<P: AsRef<Path>>(
+        &self,
+        path: P,
+        run_count: usize,
+        max_surge: usize,
+        distribution: Distribution,
+    ) {
+        self.push_chaotic(path.as_ref(), run_count, 0, max_surge, distribution);
+    }
+
+    ///
+    /// Chaotic test held to an availability objective over its runs, rather
+    /// than to every run surviving its surge.
+    ///
+    /// The test fails only when fewer than `percentile` percent of the runs
+    /// of a sweep stay up for at least `min_duration`, like an SLO of "95% of
+    /// requests within 2s". Runs that crash count with the time they stayed
+    /// up. Surges are sampled up to the `max_surge` of `kaos.toml`, 1000 by
+    /// default. Panics unless `percentile` is within `(0, 100]`.
+    ///
+    /// ```no_run
+    /// # use std::time::Duration;
+    /// let k = kaos::Runs::new();
+    /// k.chaotic_slo("kaos-tests/replica.rs", 2, Duration::from_secs(2), 95.0);
+    /// ```
+    pub fn chaotic_slo<P: AsRef<Path>>(
+        &self,
+        path: P,
+        run_count: usize,
+        min_duration: Duration,
+        percentile: f64,
+    ) {
+        assert!(
+            percentile > 0.0 && percentile <= 100.0,
+            "kaos: SLO percentile of {} must be within (0, 100], got {}",
+            path.as_ref().display(),
+            percentile,
+        );
+        let registration = self.push_from_settings(path.as_ref(), run_count, FromSettings::MaxSurge);
+        for test in &mut self.runner.borrow_mut().tests[registration..] {
+            test.slo = Some(Slo {
+                min_duration,
+                percentile,
+            });
+        }
+    }
+
+    ///
+    /// Chaotic test that samples surges until its MTBF estimate settles,
+    /// rather than for a fixed number of cases.
+    ///
+    /// After every run the mean time between failures is estimated again, the
+    /// sweep stops once it moved by no more than `tolerance`, relative to the
+    /// estimate before, and after `max_runs` runs otherwise. A service that
+    /// never fails has no estimate and runs all of them. Like in explore mode
+    /// the test fails if any run did, without shrinking. Surges are sampled up
+    /// to the `max_surge` of `kaos.toml`, 1000 by default. Panics unless
+    /// `tolerance` is positive.
+    ///
+    /// ```no_run
+    /// let k = kaos::Runs::new();
+    /// // Stops once the estimate moves by 5% or less, after 200 runs at most.
+    /// k.chaotic_until_converged("kaos-tests/replica.rs", 200, 0.05);
+    /// ```
+    pub fn chaotic_until_converged<P: AsRef<Path>>(&self, path: P, max_runs: usize, tolerance: f64) {
+        assert!(
+            tolerance > 0.0,
+            "kaos: convergence tolerance of {} must be positive, got {}",
+            path.as_ref().display(),
+            tolerance,
+        );
+        let registration = self.push_from_settings(path.as_ref(), 1, FromSettings::MaxSurge);
+        for test in &mut self.runner.borrow_mut().tests[registration..] {
+            test.convergence = Some(Convergence {
+                max_runs,
+                tolerance,
+            });
+        }
+    }
+
+    ///
+    /// Chaotic test that keeps running the service for `total`, rather than
+    /// for a number of cases.
+    ///
+    /// For long-running stability checks: runs with surges sampled from
+    /// `surge_range` follow each other until the wall-clock budget is spent,
+    /// the run in progress at the deadline finishes. Like in explore mode the
+    /// test fails if any run did, without shrinking, and reports how many
+    /// runs completed. Panics if `surge_range` is empty.
+    ///
+    /// ```no_run
+    /// # use std::time::Duration;
+    /// let k = kaos::Runs::new();
+    /// // Ten minutes of surges between 100ms and 2s.
+    /// k.soak("kaos-tests/replica.rs", Duration::from_secs(600), 100..2000);
+    /// ```
+    pub fn soak<P: AsRef<Path>>(&self, path: P, total: Duration, surge_range: Range<usize>) {
+        let registration = self.runner.borrow().tests.len();
+        self.push_chaotic(path.as_ref(), 1, surge_range.start, surge_range.end, Distribution::Uniform);
+        for test in &mut self.runner.borrow_mut().tests[registration..] {
+            test.soak = Some(total);
+        }
+    }
+
+    fn push_chaotic(
+        &self,
+        path: &Path,
+        run_count: usize,
+        min_surge: usize,
+        max_surge: usize,
+        distribution: Distribution,
+    ) {
+        assert!(
+            min_surge < max_surge,
+            "kaos: chaotic surge range of {} is empty, expected min_surge < max_surge but got {}..{}",
+            path.display(),
+            min_surge,
+            max_surge,
+        );
+        let registration = self.runner.borrow().tests.len();
+        (0..run_count).for_each(|_| {
             self.runner.borrow_mut().tests.push(Test {
-                path: path.as_ref().to_owned(),
+                path: path.to_owned(),
                 duration: None,
-                max_surge: max_surge as isize,
+                max_duration: None,
+                min_surge,
+                max_surge,
                 expected: Expected::Chaotic,
+                distribution,
+                tags: Vec::new(),
+                features: Vec::new(),
+                env: BTreeMap::new(),
+                expected_output: None,
+                supervision: None,
+                slo: None,
+                convergence: None,
+                soak: None,
+                from_settings: None,
+                description: None,
+                platforms: Vec::new(),
+                registration,
             });
         });
     }
+
+    ///
+    /// Register tests carrying the given tags.
+    ///
+    /// Passing `kaostag=<tag>` to the test binary runs only the tests with
+    /// that tag, e.g. `cargo test -- chaos_tests kaostag=network`. Combined with `kaos=`
+    /// a test has to match both.
+    ///
+    /// ```no_run
+    /// # use std::time::Duration;
+    /// let k = kaos::Runs::new();
+    /// k.tagged(&["network"]).chaotic("kaos-tests/partition.rs", 2, 1000);
+    /// k.tagged(&["disk", "slow"]).available("kaos-tests/fsync.rs", Duration::from_secs(1));
+    /// ```
+    pub fn tagged<S: AsRef<str>>(&self, tags: &[S]) -> Group<'_> {
+        self.group().tagged(tags)
+    }
+
+    ///
+    /// Register tests built with the given features of the tested crate
+    /// enabled, on top of the ones the tests were run with.
+    ///
+    /// Every test binary is built on its own, so tests needing conflicting
+    /// features don't affect each other.
+    ///
+    /// ```no_run
+    /// # use std::time::Duration;
+    /// let k = kaos::Runs::new();
+    /// k.with_features(&["tls"]).available("kaos-tests/handshake.rs", Duration::from_secs(1));
+    /// ```
+    pub fn with_features<S: AsRef<str>>(&self, features: &[S]) -> Group<'_> {
+        self.group().with_features(features)
+    }
+
+    ///
+    /// Register tests whose services run with the given environment variable,
+    /// overriding the value set by [Runs::env].
+    ///
+    /// ```no_run
+    /// let k = kaos::Runs::new();
+    /// k.with_env("LOG_LEVEL", "trace").chaotic("kaos-tests/replication.rs", 2, 1000);
+    /// ```
+    pub fn with_env<K: Into<String>, V: Into<String>>(&self, key: K, value: V) -> Group<'_> {
+        self.group().with_env(key, value)
+    }
+
+    ///
+    /// Register tests with a description of the scenario they exercise.
+    ///
+    /// The description is shown next to the test's name when it runs and when
+    /// it fails, and is included in the JSON and JUnit reports, for files
+    /// whose names say little about what they test.
+    ///
+    /// ```no_run
+    /// let k = kaos::Runs::new();
+    /// k.described("simulate disk full during flush").chaotic("kaos-tests/launcher07.rs", 2, 1000);
+    /// ```
+    pub fn described<S: Into<String>>(&self, description: S) -> Group<'_> {
+        self.group().described(description)
+    }
+
+    ///
+    /// Register tests that only run on the given operating systems.
+    ///
+    /// On other systems the tests are reported as skipped rather than run,
+    /// for chaos that needs cgroups, namespaces or other platform specifics.
+    ///
+    /// ```no_run
+    /// use kaos::Os;
+    /// let k = kaos::Runs::new();
+    /// k.only_on(&[Os::Linux]).chaotic("kaos-tests/cgroup-oom.rs", 2, 1000);
+    /// ```
+    pub fn only_on(&self, platforms: &[Os]) -> Group<'_> {
+        self.group().only_on(platforms)
+    }
+
+    ///
+    /// Availability test that only runs on the given operating systems, see
+    /// [Runs::only_on].
+    pub fn available_on<P: AsRef<Path>>(&self, path: P, duration: Duration, platforms: &[Os]) {
+        self.only_on(platforms).available(path, duration);
+    }
+
+    fn group(&self) -> Group<'_> {
+        Group {
+            runs: self,
+            tags: Vec::new(),
+            features: Vec::new(),
+            env: BTreeMap::new(),
+            description: None,
+            platforms: Vec::new(),
+        }
+    }
+
+    ///
+    /// Run up to `jobs` test files at the same time.
+    ///
+    /// Tests run one after another by default. The `KAOS_JOBS` environment
+    /// variable takes precedence over this setting.
+    pub fn parallelism(&self, jobs: usize) {
+        self.runner.borrow_mut().jobs = Some(jobs);
+    }
+
+    ///
+    /// Stop running tests as soon as one of them fails.
+    ///
+    /// All tests run by default. The `KAOS_FAIL_FAST` environment variable,
+    /// set to `1` or `0`, takes precedence over this setting.
+    pub fn fail_fast(&self, enabled: bool) {
+        self.runner.borrow_mut().fail_fast = enabled;
+    }
+
+    ///
+    /// Exit the test binary with a code telling failures apart, instead of
+    /// failing the launcher test.
+    ///
+    /// The code is `2` when an availability test failed, `3` when a chaotic
+    /// test crashed the service and `4` when the harness itself failed, like a
+    /// test that didn't build. The most severe one wins. When every test
+    /// passes the launcher returns as usual. Other tests of the launcher's
+    /// binary don't run after it exits. The `KAOS_EXIT_CODES` environment
+    /// variable, set to `1` or `0`, takes precedence over this setting.
+    pub fn exit_codes(&self, enabled: bool) {
+        self.runner.borrow_mut().exit_codes = enabled;
+    }
+
+    ///
+    /// Kill a service that runs longer than `limit` and fail its test.
+    ///
+    /// Deadlocks are a common outcome of chaos; without a limit a hung
+    /// service blocks the whole run. Output printed before the kill is shown.
+    pub fn timeout(&self, limit: Duration) {
+        self.runner.borrow_mut().timeout = Some(limit);
+    }
+
+    ///
+    /// Pause for `pause` between the runs of each chaotic test.
+    ///
+    /// Services leaving sockets, files or child processes behind can make the
+    /// next run fail for reasons that have nothing to do with the surge; a
+    /// pause lets the system settle first. Nothing is paused by default, the
+    /// total time spent waiting is shown in the timing breakdown.
+    ///
+    /// ```no_run
+    /// # use std::time::Duration;
+    /// let k = kaos::Runs::new();
+    /// k.settle(Duration::from_millis(200));
+    /// k.chaotic("kaos-tests/chaos.rs", 10, 1000);
+    /// ```
+    pub fn settle(&self, pause: Duration) {
+        self.runner.borrow_mut().settle = pause;
+    }
+
+    ///
+    /// Warn about availability tests that pass with little to spare.
+    ///
+    /// A service surviving 2.1s against a 2s bar passes, but the next slower
+    /// machine fails it. With a margin of `fraction` a passing run that lasted
+    /// less than the bar plus that fraction of it prints a warning, the test
+    /// still passes. Nothing is warned about by default.
+    ///
+    /// ```no_run
+    /// # use std::time::Duration;
+    /// let k = kaos::Runs::new();
+    /// // Warns when the service lasts less than 2.2s.
+    /// k.warn_margin(0.1);
+    /// k.available("kaos-tests/replica.rs", Duration::from_secs(2));
+    /// ```
+    pub fn warn_margin(&self, fraction: f64) {
+        assert!(
+            fraction > 0.0,
+            "kaos: availability margin must be positive, got {}",
+            fraction,
+        );
+        self.runner.borrow_mut().warn_margin = Some(fraction);
+    }
+
+    ///
+    /// Cut the services off from the network on `ports` at the start of every run.
+    ///
+    /// Flunk points fail the code they are put in, a partition fails the
+    /// network under code that has none: traffic to the ports, incoming and
+    /// outgoing, TCP and UDP, is dropped for `during` and let through again
+    /// afterwards, or when the run ends first. The rules are installed with
+    /// `iptables` on Linux and `pfctl` on macOS, which takes root; tests fail
+    /// when they can't be, and on other platforms. Rules are lifted even when
+    /// the harness panics, but not when it is killed, look for the ones
+    /// commented `kaos-partition` or the `com.apple/kaos` anchor then.
+    ///
+    /// ```no_run
+    /// # use std::time::Duration;
+    /// let k = kaos::Runs::new();
+    /// // Nothing reaches the database for the first 2 seconds.
+    /// k.partition(&[5432], Duration::from_secs(2));
+    /// k.available("kaos-tests/replica.rs", Duration::from_secs(5));
+    /// ```
+    pub fn partition(&self, ports: &[u16], during: Duration) {
+        self.runner.borrow_mut().partition = Some(Partition {
+            ports: ports.to_vec(),
+            during,
+        });
+    }
+
+    ///
+    /// Fail tests whose activated flunk points were never reached.
+    ///
+    /// A flunk that is never evaluated tests nothing, usually because a
+    /// refactor moved the code around it. Without this kaos only warns.
+    pub fn require_flunk_coverage(&self, enabled: bool) {
+        self.runner.borrow_mut().require_flunk_coverage = enabled;
+    }
+
+    ///
+    /// Test a sibling crate of the workspace instead of the crate hosting the
+    /// launcher.
+    ///
+    /// The named workspace member becomes the dependency the tests are built
+    /// against, so a dedicated `chaos-tests` crate can test the service crate.
+    pub fn target_crate<S: AsRef<str>>(&self, name: S) {
+        self.runner.borrow_mut().target_crate = Some(name.as_ref().to_owned());
+    }
+
+    ///
+    /// Build the tests against the crate at `path` too, as the dependency `name`.
+    ///
+    /// For services split across crates, so tests can reach the internal
+    /// libraries without them being published. A relative path is relative to
+    /// the crate hosting the launcher. It keeps its default features, and the
+    /// features named like the ones the launcher is built with are enabled too.
+    ///
+    /// ```no_run
+    /// let k = kaos::Runs::new();
+    /// k.extra_crate("storage", "../storage");
+    /// k.chaotic("kaos-tests/replica.rs", 2, 1000);
+    /// ```
+    pub fn extra_crate<S: AsRef<str>, P: AsRef<Path>>(&self, name: S, path: P) {
+        self.runner
+            .borrow_mut()
+            .extra_crates
+            .push((name.as_ref().to_owned(), path.as_ref().to_owned()));
+    }
+
+    ///
+    /// Run `bin` instead of the cargo running the tests.
+    ///
+    /// For wrappers like `cross`, or a cargo of another toolchain. A bare name
+    /// is looked up in `PATH`, a relative path is relative to the crate. The
+    /// suite fails to start if the binary doesn't exist.
+    ///
+    /// ```no_run
+    /// let k = kaos::Runs::new();
+    /// k.cargo_bin("cross");
+    /// k.chaotic("kaos-tests/chaos.rs", 10, 1000);
+    /// ```
+    pub fn cargo_bin<P: AsRef<Path>>(&self, bin: P) {
+        self.runner.borrow_mut().cargo_bin = Some(bin.as_ref().to_owned());
+    }
+
+    ///
+    /// Pass `arg` to every cargo invocation, before the subcommand.
+    ///
+    /// Arguments are passed in the order they are added, so
+    /// `k.cargo_arg("+nightly")` builds and runs the tests with the nightly
+    /// toolchain, and `k.cargo_arg("-Zbuild-std")` turns on unstable cargo
+    /// features.
+    pub fn cargo_arg<S: AsRef<str>>(&self, arg: S) {
+        self.runner.borrow_mut().cargo_args.push(arg.as_ref().to_owned());
+    }
+
+    ///
+    /// Run a failing test up to `retries` more times before counting it as
+    /// failed.
+    ///
+    /// Absorbs failures caused by host noise such as CPU contention in busy
+    /// CI machines. Retries reuse the binary built for the first attempt.
+    pub fn retries(&self, retries: usize) {
+        self.runner.borrow_mut().retries = retries;
+    }
+
+    ///
+    /// Build the tests with the given Rust edition, one of `"2015"`,
+    /// `"2018"`, `"2021"` or `"2024"`.
+    ///
+    /// Tests use the edition of the crate hosting the launcher by default.
+    pub fn edition<S: AsRef<str>>(&self, edition: S) {
+        self.runner.borrow_mut().edition = Some(edition.as_ref().to_owned());
+    }
+
+    ///
+    /// Call `hook` with the path of each test before it runs.
+    ///
+    /// Useful to bring up external dependencies of the service, like starting a
+    /// database container. With parallel jobs hooks are called from the worker
+    /// threads, one at a time.
+    ///
+    /// ```no_run
+    /// let k = kaos::Runs::new();
+    /// k.before_each(|path| println!("starting the database for {}", path.display()));
+    /// k.after_each(|_| println!("stopping the database"));
+    /// k.chaotic("kaos-tests/*.rs", 2, 1000);
+    /// ```
+    pub fn before_each<F: FnMut(&Path) + Send + 'static>(&self, hook: F) {
+        with_hooks(&self.runner.borrow(), |hooks| hooks.before_each = Some(Box::new(hook)));
+    }
+
+    ///
+    /// Call `hook` with the path of each test after it ran, whether it passed
+    /// or failed. See [Runs::before_each].
+    pub fn after_each<F: FnMut(&Path) + Send + 'static>(&self, hook: F) {
+        with_hooks(&self.runner.borrow(), |hooks| hooks.after_each = Some(Box::new(hook)));
+    }
+
+    ///
+    /// Check the compiler output of every test that built with `check`.
+    ///
+    /// The check gets the normalized diagnostics of the build and fails the
+    /// test with the message it returns, before the service runs. Checks run
+    /// in the order they were added, from the worker threads with parallel
+    /// jobs.
+    ///
+    /// ```no_run
+    /// let k = kaos::Runs::new();
+    /// k.assert_diagnostics(|diagnostics| {
+    ///     match diagnostics.classified().warnings.lines().find(|line| line.contains("deprecated")) {
+    ///         Some(line) => Err(format!("the service uses deprecated items: {}", line)),
+    ///         None => Ok(()),
+    ///     }
+    /// });
+    /// ```
+    pub fn assert_diagnostics<F>(&self, check: F)
+    where
+        F: Fn(&Variations) -> Result<(), String> + Send + Sync + 'static,
+    {
+        self.runner
+            .borrow_mut()
+            .diagnostic_checks
+            .push(DiagnosticCheck(Arc::new(check)));
+    }
+
+    ///
+    /// Never forward the given features of the tested crate to the tests.
+    ///
+    /// Keeps heavyweight or incompatible features, like a `bench` feature, out
+    /// of the chaos binaries even when the tests run with them enabled.
+    pub fn exclude_features<S: AsRef<str>>(&self, features: &[S]) {
+        self.runner
+            .borrow_mut()
+            .exclude_features
+            .extend(features.iter().map(|feature| feature.as_ref().to_owned()));
+    }
+
+    ///
+    /// Run every sampled surge of chaotic tests instead of stopping and
+    /// shrinking at the first failure.
+    ///
+    /// Maps the whole region of failing surges, e.g. "fails below 420ms
+    /// availability", at the cost of running all cases. A test still fails if
+    /// any of its runs did.
+    pub fn explore_mode(&self) {
+        self.runner.borrow_mut().explore = true;
+    }
+
+    ///
+    /// Don't use or record the history of previous invocations.
+    ///
+    /// By default chaotic runs remember the surges they tried in
+    /// `target/kaos-history.json` and focus later sampling near the smallest
+    /// surge seen failing. Disable this for deterministic baselines.
+    pub fn without_history(&self) {
+        self.runner.borrow_mut().without_history = true;
+    }
+
+    ///
+    /// Whether to keep the crate generated under `target/tests` after the
+    /// tests pass. Kept by default.
+    ///
+    /// The generated manifest and cargo config are what the tests were built
+    /// from. They are always kept when a test fails, or when
+    /// `KAOS_DUMP_PROJECT=1` prints where they are.
+    pub fn keep_generated(&self, enabled: bool) {
+        self.runner.borrow_mut().discard_generated = !enabled;
+    }
+
+    ///
+    /// Compare what the tests at `path` print on stdout against the contents
+    /// of `expected_file`, failing with a diff on mismatch.
+    ///
+    /// Meant for flunks that fire deterministically. Running with
+    /// `KAOS_BLESS=1` writes the actual output to `expected_file` instead.
+    ///
+    /// ```no_run
+    /// # use std::time::Duration;
+    /// let k = kaos::Runs::new();
+    /// k.available("kaos-tests/failover.rs", Duration::from_secs(1));
+    /// k.expect_output("kaos-tests/failover.rs", "kaos-tests/failover.stdout");
+    /// ```
+    pub fn expect_output<P: AsRef<Path>, E: AsRef<Path>>(&self, path: P, expected_file: E) {
+        self.runner.borrow_mut().expected_outputs.push((
+            path.as_ref().to_owned(),
+            expected_file.as_ref().to_owned(),
+        ));
+    }
+
+    ///
+    /// Save what the service printed on every run under the given directory.
+    ///
+    /// Files are named after the test binary, `kaos000.stdout` and
+    /// `kaos000.stderr`, with the surge appended for chaotic runs, e.g.
+    /// `kaos000-420.stderr`. Later runs overwrite earlier ones.
+    pub fn log_dir<P: AsRef<Path>>(&self, path: P) {
+        self.runner.borrow_mut().log_dir = Some(path.as_ref().to_owned());
+    }
+
+    ///
+    /// Run the services from the given directory instead of the generated
+    /// test crate.
+    ///
+    /// Relative paths are resolved against the root of the crate under test,
+    /// so `run_dir(".")` runs them from where their config files and fixtures
+    /// usually are.
+    pub fn run_dir<P: AsRef<Path>>(&self, path: P) {
+        self.runner.borrow_mut().run_dir = Some(path.as_ref().to_owned());
+    }
+
+    ///
+    /// Build and run the binary at `path` once before any test.
+    ///
+    /// For shared state that is expensive to create, like a seeded database,
+    /// which [Runs::before_each] would create again for every test. The binary
+    /// is built against the crate like the tests and runs with the variables
+    /// of [Runs::env]. The suite stops before running any test if it fails.
+    ///
+    /// ```no_run
+    /// let k = kaos::Runs::new();
+    /// k.setup_bin("kaos-tests/fixtures/seed-db.rs");
+    /// k.teardown_bin("kaos-tests/fixtures/drop-db.rs");
+    /// k.chaotic("kaos-tests/replica.rs", 2, 1000);
+    /// ```
+    pub fn setup_bin<P: AsRef<Path>>(&self, path: P) {
+        self.runner.borrow_mut().setup_bin = Some(path.as_ref().to_owned());
+    }
+
+    ///
+    /// Build and run the binary at `path` once after the tests.
+    ///
+    /// Runs whether or not the tests passed, but not when [Runs::setup_bin]
+    /// failed. A failing teardown fails the suite.
+    pub fn teardown_bin<P: AsRef<Path>>(&self, path: P) {
+        self.runner.borrow_mut().teardown_bin = Some(path.as_ref().to_owned());
+    }
+
+    ///
+    /// Run every service in a fresh temporary directory of its own.
+    ///
+    /// The directory is the service's working directory and its path is in the
+    /// `KAOS_TMPDIR` environment variable, so files it writes don't carry over
+    /// to the next chaotic run. It is removed after the run, unless the service
+    /// failed, to be inspected. Takes precedence over [Runs::run_dir].
+    pub fn isolated_tmp(&self, enabled: bool) {
+        self.runner.borrow_mut().isolated_tmp = enabled;
+    }
+
+    ///
+    /// Rewrite paths starting with `from` to `to` in diagnostics and in the
+    /// output compared against expected files.
+    ///
+    /// Rules apply in the order they are added, before kaos replaces the crate
+    /// directory with `$DIR`, so a symlinked or virtualized checkout can be
+    /// mapped back onto the real one. A `*` in `from` matches any part of one
+    /// path component.
+    ///
+    /// ```no_run
+    /// let k = kaos::Runs::new();
+    /// k.normalize_path("/mnt/ci/checkout", "/home/dev/service");
+    /// k.normalize_path("/home/*/.cargo/registry", "$CARGO_REGISTRY");
+    /// ```
+    pub fn normalize_path<F: AsRef<str>, T: AsRef<str>>(&self, from: F, to: T) {
+        self.runner.borrow_mut().path_rules.push(PathRule {
+            from: from.as_ref().to_owned(),
+            to: to.as_ref().to_owned(),
+        });
+    }
+
+    ///
+    /// Build the tests for the given target triple instead of the host.
+    ///
+    /// Services built for another architecture run through the runner cargo
+    /// is configured with for the target, like an emulator set in
+    /// `CARGO_TARGET_AARCH64_UNKNOWN_LINUX_GNU_RUNNER`.
+    ///
+    /// ```no_run
+    /// let k = kaos::Runs::new();
+    /// k.target("aarch64-unknown-linux-gnu");
+    /// ```
+    pub fn target<S: AsRef<str>>(&self, triple: S) {
+        self.runner.borrow_mut().target = Some(triple.as_ref().to_owned());
+    }
+
+    ///
+    /// Build the tests with the given cargo profile instead of `dev`.
+    ///
+    /// Optimizations change how long things take, and with that the
+    /// availability and MTBF of a service. Build with `release` to measure
+    /// what runs in production. The `[profile]` sections of the workspace's
+    /// `Cargo.toml` apply, custom profiles included.
+    ///
+    /// ```no_run
+    /// let k = kaos::Runs::new();
+    /// k.profile("release");
+    /// ```
+    pub fn profile<S: AsRef<str>>(&self, name: S) {
+        self.runner.borrow_mut().profile = Some(name.as_ref().to_owned());
+    }
+
+    ///
+    /// Set an environment variable for the services of every test, on top of
+    /// the environment kaos runs in.
+    ///
+    /// ```no_run
+    /// let k = kaos::Runs::new();
+    /// k.env("DATABASE_URL", "postgres://localhost/chaos");
+    /// ```
+    pub fn env<K: Into<String>, V: Into<String>>(&self, key: K, value: V) {
+        self.runner.borrow_mut().env.insert(key.into(), value.into());
+    }
+
+    ///
+    /// Write a JSON report of every test and run to the given path once the
+    /// tests finish, whether or not they pass.
+    pub fn report_to<P: AsRef<Path>>(&self, path: P) {
+        self.runner.borrow_mut().report = Some(path.as_ref().to_owned());
+    }
+
+    ///
+    /// Run the tests right away and return how they went, instead of
+    /// failing when the runs are dropped.
+    ///
+    /// The tests print their progress and write their reports as usual, but
+    /// failing ones neither panic nor exit the process, whatever
+    /// [Runs::exit_codes] says. It's up to the caller to act on the report.
+    ///
+    /// ```no_run
+    /// let k = kaos::Runs::new();
+    /// k.available("kaos-tests/steady.rs", std::time::Duration::from_secs(1));
+    /// let report = k.run_collect();
+    /// for test in report.tests.iter().filter(|test| !test.passed) {
+    ///     eprintln!("{} failed: {:?}", test.path.display(), test.failure);
+    /// }
+    /// std::process::exit(report.exit_code());
+    /// ```
+    pub fn run_collect(self) -> RunReport {
+        let mut runner = self.runner.borrow_mut();
+        runner.collected = true;
+        runner.collect()
+    }
+}
+
+///
+/// Registers tests sharing tags or features, created by [Runs::tagged] and
+/// [Runs::with_features]
+#[cfg(feature = "std")]
+#[derive(Debug)]
+pub struct Group<'a> {
+    runs: &'a Runs,
+    tags: Vec<String>,
+    features: Vec<String>,
+    env: BTreeMap<String, String>,
+    description: Option<String>,
+    platforms: Vec<Os>,
+}
+
+#[cfg(feature = "std")]
+impl<'a> Group<'a> {
+    pub fn tagged<S: AsRef<str>>(mut self, tags: &[S]) -> Self {
+        self.tags.extend(tags.iter().map(|tag| tag.as_ref().to_owned()));
+        self
+    }
+
+    pub fn with_features<S: AsRef<str>>(mut self, features: &[S]) -> Self {
+        self.features
+            .extend(features.iter().map(|feature| feature.as_ref().to_owned()));
+        self
+    }
+
+    pub fn with_env<K: Into<String>, V: Into<String>>(mut self, key: K, value: V) -> Self {
+        self.env.insert(key.into(), value.into());
+        self
+    }
+
+    pub fn described<S: Into<String>>(mut self, description: S) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+
+    pub fn only_on(mut self, platforms: &[Os]) -> Self {
+        self.platforms.extend_from_slice(platforms);
+        self
+    }
+
+    pub fn available<P: AsRef<Path>>(&self, path: P, duration: Duration) {
+        self.add(|runs| runs.available(path, duration));
+    }
+
+    pub fn available_str<P: AsRef<Path>>(
+        &self,
+        path: P,
+        duration: &str,
+    ) -> Result<(), humantime::DurationError> {
+        let duration = humantime::parse_duration(duration)?;
+        self.available(path, duration);
+        Ok(())
+    }
+
+    pub fn available_within<P: AsRef<Path>>(&self, path: P, min: Duration, max: Duration) {
+        self.add(|runs| runs.available_within(path, min, max));
+    }
+
+    pub fn supervised<P: AsRef<Path>>(&self, path: P, restarts: usize, budget: Duration) {
+        self.add(|runs| runs.supervised(path, restarts, budget));
+    }
+
+    pub fn chaotic<P: AsRef<Path>>(&self, path: P, run_count: usize, max_surge: usize) {
+        self.add(|runs| runs.chaotic(path, run_count, max_surge));
+    }
+
+    pub fn chaotic_default<P: AsRef<Path>>(&self, path: P) {
+        self.add(|runs| runs.chaotic_default(path));
+    }
+
+    pub fn chaotic_range<P: AsRef<Path>>(
+        &self,
+        path: P,
+        run_count: usize,
+        min_surge: usize,
+        max_surge: usize,
+    ) {
+        self.add(|runs| runs.chaotic_range(path, run_count, min_surge, max_surge));
+    }
+
+    pub fn chaotic_weighted<P: AsRef<Path>>(
+        &self,
+        path: P,
+        run_count: usize,
+        max_surge: usize,
+        distribution: Distribution,
+    ) {
+        self.add(|runs| runs.chaotic_weighted(path, run_count, max_surge, distribution));
+    }
+
+    pub fn chaotic_slo<P: AsRef<Path>>(
+        &self,
+        path: P,
+        run_count: usize,
+        min_duration: Duration,
+        percentile: f64,
+    ) {
+        self.add(|runs| runs.chaotic_slo(path, run_count, min_duration, percentile));
+    }
+
+    pub fn chaotic_until_converged<P: AsRef<Path>>(&self, path: P, max_runs: usize, tolerance: f64) {
+        self.add(|runs| runs.chaotic_until_converged(path, max_runs, tolerance));
+    }
+
+    pub fn soak<P: AsRef<Path>>(&self, path: P, total: Duration, surge_range: Range<usize>) {
+        self.add(|runs| runs.soak(path, total, surge_range));
+    }
+
+    // Applies the group to whatever tests `add` registers.
+    fn add<F: FnOnce(&Runs)>(&self, add: F) {
+        let start = self.runs.runner.borrow().tests.len();
+        add(self.runs);
+        for test in &mut self.runs.runner.borrow_mut().tests[start..] {
+            test.tags.extend(self.tags.iter().cloned());
+            test.features.extend(self.features.iter().cloned());
+            test.env.extend(self.env.clone());
+            if self.description.is_some() {
+                test.description = self.description.clone();
+            }
+            test.platforms.extend_from_slice(&self.platforms);
+        }
+    }
+}
+
+///
+/// Builder for [Runs] with global options, created by [Runs::builder]
+#[cfg(feature = "std")]
+#[derive(Debug)]
+pub struct RunsBuilder {
+    runner: Runner,
+}
+
+#[cfg(feature = "std")]
+impl RunsBuilder {
+    ///
+    /// Pin the seed for surge sampling, see [Runs::with_seed].
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.runner.seed = Some(seed);
+        self
+    }
+
+    ///
+    /// Run up to `jobs` test files at the same time, see [Runs::parallelism].
+    pub fn jobs(mut self, jobs: usize) -> Self {
+        self.runner.jobs = Some(jobs);
+        self
+    }
+
+    ///
+    /// Stop running tests as soon as one of them fails, see [Runs::fail_fast].
+    pub fn fail_fast(mut self, enabled: bool) -> Self {
+        self.runner.fail_fast = enabled;
+        self
+    }
+
+    ///
+    /// Exit with a code telling failures apart, see [Runs::exit_codes].
+    pub fn exit_codes(mut self, enabled: bool) -> Self {
+        self.runner.exit_codes = enabled;
+        self
+    }
+
+    ///
+    /// Kill a service that runs longer than `limit`, see [Runs::timeout].
+    pub fn timeout(mut self, limit: Duration) -> Self {
+        self.runner.timeout = Some(limit);
+        self
+    }
+
+    ///
+    /// Pause between the runs of each chaotic test, see [Runs::settle].
+    pub fn settle(mut self, pause: Duration) -> Self {
+        self.runner.settle = pause;
+        self
+    }
+
+    ///
+    /// Warn about runs passing with little to spare, see [Runs::warn_margin].
+    pub fn warn_margin(mut self, fraction: f64) -> Self {
+        assert!(
+            fraction > 0.0,
+            "kaos: availability margin must be positive, got {}",
+            fraction,
+        );
+        self.runner.warn_margin = Some(fraction);
+        self
+    }
+
+    ///
+    /// Cut the services off from the network for a while, see [Runs::partition].
+    pub fn partition(mut self, ports: &[u16], during: Duration) -> Self {
+        self.runner.partition = Some(Partition {
+            ports: ports.to_vec(),
+            during,
+        });
+        self
+    }
+
+    ///
+    /// Fail tests whose activated flunk points were never reached, see
+    /// [Runs::require_flunk_coverage].
+    pub fn require_flunk_coverage(mut self, enabled: bool) -> Self {
+        self.runner.require_flunk_coverage = enabled;
+        self
+    }
+
+    ///
+    /// Test a sibling crate of the workspace, see [Runs::target_crate].
+    pub fn target_crate<S: AsRef<str>>(mut self, name: S) -> Self {
+        self.runner.target_crate = Some(name.as_ref().to_owned());
+        self
+    }
+
+    ///
+    /// Build the tests against another crate too, see [Runs::extra_crate].
+    pub fn extra_crate<S: AsRef<str>, P: AsRef<Path>>(mut self, name: S, path: P) -> Self {
+        self.runner
+            .extra_crates
+            .push((name.as_ref().to_owned(), path.as_ref().to_owned()));
+        self
+    }
+
+    ///
+    /// Run another cargo binary, see [Runs::cargo_bin].
+    pub fn cargo_bin<P: AsRef<Path>>(mut self, bin: P) -> Self {
+        self.runner.cargo_bin = Some(bin.as_ref().to_owned());
+        self
+    }
+
+    ///
+    /// Pass an argument to every cargo invocation, see [Runs::cargo_arg].
+    pub fn cargo_arg<S: AsRef<str>>(mut self, arg: S) -> Self {
+        self.runner.cargo_args.push(arg.as_ref().to_owned());
+        self
+    }
+
+    ///
+    /// Run a failing test more times before counting it as failed, see
+    /// [Runs::retries].
+    pub fn retries(mut self, retries: usize) -> Self {
+        self.runner.retries = retries;
+        self
+    }
+
+    ///
+    /// Build the tests with the given Rust edition, see [Runs::edition].
+    pub fn edition<S: AsRef<str>>(mut self, edition: S) -> Self {
+        self.runner.edition = Some(edition.as_ref().to_owned());
+        self
+    }
+
+    ///
+    /// Write a JSON report to the given path, see [Runs::report_to].
+    pub fn report<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.runner.report = Some(path.as_ref().to_owned());
+        self
+    }
+
+    ///
+    /// Save the service output of every run, see [Runs::log_dir].
+    pub fn log_dir<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.runner.log_dir = Some(path.as_ref().to_owned());
+        self
+    }
+
+    ///
+    /// Run the services from the given directory, see [Runs::run_dir].
+    pub fn run_dir<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.runner.run_dir = Some(path.as_ref().to_owned());
+        self
+    }
+
+    ///
+    /// Run a binary once before the tests, see [Runs::setup_bin].
+    pub fn setup_bin<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.runner.setup_bin = Some(path.as_ref().to_owned());
+        self
+    }
+
+    ///
+    /// Run a binary once after the tests, see [Runs::teardown_bin].
+    pub fn teardown_bin<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.runner.teardown_bin = Some(path.as_ref().to_owned());
+        self
+    }
+
+    ///
+    /// Run every service in a fresh temporary directory, see
+    /// [Runs::isolated_tmp].
+    pub fn isolated_tmp(mut self, enabled: bool) -> Self {
+        self.runner.isolated_tmp = enabled;
+        self
+    }
+
+    ///
+    /// Rewrite paths in diagnostics and output, see [Runs::normalize_path].
+    pub fn normalize_path<F: AsRef<str>, T: AsRef<str>>(mut self, from: F, to: T) -> Self {
+        self.runner.path_rules.push(PathRule {
+            from: from.as_ref().to_owned(),
+            to: to.as_ref().to_owned(),
+        });
+        self
+    }
+
+    ///
+    /// Build the tests for the given target triple, see [Runs::target].
+    pub fn target<S: AsRef<str>>(mut self, triple: S) -> Self {
+        self.runner.target = Some(triple.as_ref().to_owned());
+        self
+    }
+
+    ///
+    /// Build the tests with the given cargo profile, see [Runs::profile].
+    pub fn profile<S: AsRef<str>>(mut self, name: S) -> Self {
+        self.runner.profile = Some(name.as_ref().to_owned());
+        self
+    }
+
+    ///
+    /// Set an environment variable for every service, see [Runs::env].
+    pub fn env<K: Into<String>, V: Into<String>>(mut self, key: K, value: V) -> Self {
+        self.runner.env.insert(key.into(), value.into());
+        self
+    }
+
+    ///
+    /// Write a JUnit XML report to the given path.
+    ///
+    /// The `KAOS_JUNIT_PATH` environment variable takes precedence.
+    pub fn junit<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.runner.junit = Some(path.as_ref().to_owned());
+        self
+    }
+
+    ///
+    /// Call `hook` before each test runs, see [Runs::before_each].
+    pub fn before_each<F: FnMut(&Path) + Send + 'static>(self, hook: F) -> Self {
+        with_hooks(&self.runner, |hooks| hooks.before_each = Some(Box::new(hook)));
+        self
+    }
+
+    ///
+    /// Call `hook` after each test ran, see [Runs::after_each].
+    pub fn after_each<F: FnMut(&Path) + Send + 'static>(self, hook: F) -> Self {
+        with_hooks(&self.runner, |hooks| hooks.after_each = Some(Box::new(hook)));
+        self
+    }
+
+    ///
+    /// Check the compiler output of every test that built, see
+    /// [Runs::assert_diagnostics].
+    pub fn assert_diagnostics<F>(mut self, check: F) -> Self
+    where
+        F: Fn(&Variations) -> Result<(), String> + Send + Sync + 'static,
+    {
+        self.runner.diagnostic_checks.push(DiagnosticCheck(Arc::new(check)));
+        self
+    }
+
+    ///
+    /// Never forward the given features to the tests, see
+    /// [Runs::exclude_features].
+    pub fn exclude_features<S: AsRef<str>>(mut self, features: &[S]) -> Self {
+        self.runner
+            .exclude_features
+            .extend(features.iter().map(|feature| feature.as_ref().to_owned()));
+        self
+    }
+
+    ///
+    /// Run every sampled surge of chaotic tests, see [Runs::explore_mode].
+    pub fn explore_mode(mut self) -> Self {
+        self.runner.explore = true;
+        self
+    }
+
+    ///
+    /// Whether to use and record the history of previous invocations, see
+    /// [Runs::without_history]. Enabled by default.
+    pub fn history(mut self, enabled: bool) -> Self {
+        self.runner.without_history = !enabled;
+        self
+    }
+
+    ///
+    /// Whether to keep the generated crate after the tests pass, see
+    /// [Runs::keep_generated]. Kept by default.
+    pub fn keep_generated(mut self, enabled: bool) -> Self {
+        self.runner.discard_generated = !enabled;
+        self
+    }
+
+    pub fn build(self) -> Runs {
+        Runs {
+            runner: RefCell::new(self.runner),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+fn with_hooks<F: FnOnce(&mut Hooks)>(runner: &Runner, f: F) {
+    f(&mut runner.hooks.lock().unwrap_or_else(PoisonError::into_inner))
 }
 
+#[cfg(feature = "std")]
 #[doc(hidden)]
 impl Drop for Runs {
     fn drop(&mut self) {
-        if !thread::panicking() {
-            self.runner.borrow_mut().run();
+        let mut runner = self.runner.borrow_mut();
+        if !thread::panicking() && !runner.collected {
+            runner.run();
         }
     }
 }
@@ -1,21 +1,310 @@
 ///
 /// Macro to define a point to flunk
+///
+/// An optional second argument gives the probability within `[0.0, 1.0]`
+/// that the point fails each time it is reached. It defaults to `1.0`.
+///
+/// A message, or a format string with arguments, can follow the name instead,
+/// to carry context about the failure in the panic message:
+/// `flunk!("evict", "cache is full")` or
+/// `flunk!("evict", "cache holds {} entries", cache.len())`. A message without
+/// arguments is used as it is.
+///
+/// The point is namespaced by the module it is declared in, see the
+/// [crate docs](crate#definining-flunks).
+///
+/// Points can be put in groups, to activate all of them at once with
+/// `group:<name>`: `flunk!("write", group = "storage")`, or with a probability
+/// and in several groups, `flunk!("write", 0.5, groups = ["storage", "io"])`.
 #[macro_export]
 macro_rules! flunk {
     ($name:expr) => {{
-        $crate::flunker($name, |_| {
+        $crate::flunk!($name, 1.0);
+    }};
+    ($name:expr, group = $group:expr) => {{
+        $crate::flunk!($name, 1.0, groups = [$group]);
+    }};
+    ($name:expr, groups = [$($group:expr),+ $(,)?]) => {{
+        $crate::flunk!($name, 1.0, groups = [$($group),+]);
+    }};
+    ($name:expr, $probability:expr, group = $group:expr) => {{
+        $crate::flunk!($name, $probability, groups = [$group]);
+    }};
+    ($name:expr, $probability:expr, groups = [$($group:expr),+ $(,)?]) => {{
+        if let ::core::option::Option::Some(name) =
+            $crate::flunker_in_groups_if(module_path!(), $name, &[$($group),+], |_| {
+                $crate::flunk_roll($probability)
+            })
+        {
+            panic!("KAOS: Flunking at \"{}\"", name);
+        }
+    }};
+    ($name:expr, $fmt:literal, $($arg:tt)+) => {{
+        $crate::flunker_in(module_path!(), $name, |name| {
+            panic!("KAOS: Flunking at \"{}\": {}", name, format_args!($fmt, $($arg)+));
+        });
+    }};
+    ($name:expr, $arg:literal) => {{
+        let (probability, message) = $crate::flunk_arg($arg);
+        if let ::core::option::Option::Some(name) =
+            $crate::flunker_in_if(module_path!(), $name, |_| $crate::flunk_roll(probability))
+        {
+            match message {
+                ::core::option::Option::Some(message) => {
+                    panic!("KAOS: Flunking at \"{}\": {}", name, message)
+                }
+                ::core::option::Option::None => panic!("KAOS: Flunking at \"{}\"", name),
+            }
+        }
+    }};
+    ($name:expr, $probability:expr) => {{
+        if let ::core::option::Option::Some(name) =
+            $crate::flunker_in_if(module_path!(), $name, |_| $crate::flunk_roll($probability))
+        {
+            panic!("KAOS: Flunking at \"{}\"", name);
+        }
+    }};
+}
+
+///
+/// Macro to define a point to flunk under its bare name
+///
+/// Like [flunk], without the module namespace. Only activating `name` itself
+/// turns it on.
+#[macro_export]
+macro_rules! flunk_global {
+    ($name:expr) => {{
+        $crate::flunk_global!($name, 1.0);
+    }};
+    ($name:expr, $probability:expr) => {{
+        if $crate::flunker_if($name, || $crate::flunk_roll($probability)) {
             panic!("KAOS: Flunking at \"{}\"", $name);
+        }
+    }};
+}
+
+///
+/// Macro to define a point that only flunks while a condition holds
+///
+/// Like [flunk], guarded by `condition`, a `bool` expression that is evaluated
+/// every time the point is reached, whether or not it is active. Keep it cheap
+/// and free of side effects. While it is false the point is not engaged at
+/// all, and isn't counted as reached: `flunk_if!("append", is_writer())`, or
+/// with a probability, `flunk_if!("append", len > 1024, 0.5)`.
+#[macro_export]
+macro_rules! flunk_if {
+    ($name:expr, $condition:expr) => {{
+        $crate::flunk_if!($name, $condition, 1.0);
+    }};
+    ($name:expr, $condition:expr, $probability:expr) => {{
+        if $condition {
+            $crate::flunk!($name, $probability);
+        }
+    }};
+}
+
+///
+/// Macro to define a point that injects latency
+///
+/// Sleeps on the calling thread for the given duration, which can be anything
+/// convertible `Into<Duration>`, instead of panicking.
+#[macro_export]
+macro_rules! flunk_delay {
+    ($name:expr, $duration:expr) => {{
+        $crate::flunker_in(module_path!(), $name, |name| {
+            $crate::flunk_sleep(name, $duration);
+        });
+    }};
+}
+
+///
+/// Macro to define a point that flunks after being reached a number of times
+///
+/// Does nothing for the first `count` evaluations and panics on the next one,
+/// only once. Every point of a module using the same name shares a single
+/// count, which is reset when the kaos test scenario is set up and torn down.
+#[macro_export]
+macro_rules! flunk_after {
+    ($name:expr, $count:expr) => {{
+        if let ::core::option::Option::Some(name) =
+            $crate::flunker_in_if(module_path!(), $name, |name| {
+                $crate::flunk_hits(name) == $count + 1
+            })
+        {
+            panic!("KAOS: Flunking at \"{}\"", name);
+        }
+    }};
+}
+
+///
+/// Macro to define a point that flunks on every few evaluations
+///
+/// Panics on every `interval`-th evaluation and does nothing in between, so
+/// `flunk_every!("request", 10)` fails one request in ten, deterministically.
+/// Unlike [flunk_after] it keeps firing. The count is shared the same way,
+/// across threads and by every point of a module using the same name.
+/// `interval` must be at least 1, the point panics on 0 whether it is enabled
+/// or not.
+#[macro_export]
+macro_rules! flunk_every {
+    ($name:expr, $interval:expr) => {{
+        let interval = $interval;
+        assert!(
+            interval >= 1,
+            "KAOS: flunk interval must be at least 1, got {}",
+            interval
+        );
+        if let ::core::option::Option::Some(name) =
+            $crate::flunker_in_if(module_path!(), $name, |name| {
+                $crate::flunk_hits(name) % interval == 0
+            })
+        {
+            panic!("KAOS: Flunking at \"{}\"", name);
+        }
+    }};
+}
+
+///
+/// Macro to define a point that runs a callback instead of panicking
+///
+/// Calls `callback` when the point fires, to inject faults that corrupt state,
+/// like flipping a bit or truncating a buffer, rather than crash. The callback
+/// may capture and mutate its environment. Its result is discarded.
+#[macro_export]
+macro_rules! flunk_with {
+    ($name:expr, $callback:expr) => {{
+        $crate::flunker_in(module_path!(), $name, |_| {
+            let _ = ($callback)();
         });
     }};
 }
 
+///
+/// Macro to define a point that returns an error instead of panicking
+///
+/// When the point fires, returns `Err(error)` from the enclosing function,
+/// converting the error with `From` like `?` does. Otherwise it falls through
+/// and `error` is not evaluated. Only compiles inside functions returning a
+/// `Result`.
+#[macro_export]
+macro_rules! flunk_err {
+    ($name:expr, $error:expr) => {{
+        if let ::core::option::Option::Some(error) = $crate::flunker_in(module_path!(), $name, |_| $error) {
+            return ::core::result::Result::Err(::core::convert::From::from(error));
+        }
+    }};
+}
+
+///
+/// Macro to define a point that returns an I/O error
+///
+/// Like [flunk_err] with a synthetic `std::io::Error` of the given
+/// `io::ErrorKind`, `ErrorKind::Other` if omitted. The error reads like the
+/// panic of [flunk], with the namespaced name. Only compiles inside
+/// functions returning `io::Result`, or a `Result` whose error converts from
+/// `io::Error`.
+#[macro_export]
+macro_rules! flunk_io {
+    ($name:expr) => {{
+        $crate::flunk_io!($name, ::std::io::ErrorKind::Other);
+    }};
+    ($name:expr, $kind:expr) => {{
+        if let ::core::option::Option::Some(error) = $crate::flunker_in(module_path!(), $name, |name| {
+            ::std::io::Error::new($kind, format!("KAOS: Flunking at \"{}\"", name))
+        }) {
+            return ::core::result::Result::Err(::core::convert::From::from(error));
+        }
+    }};
+}
+
+///
+/// Macro to define a point that takes one of several actions
+///
+/// When the point fires it picks one of the listed actions at random:
+/// `panic` like [flunk], `delay(duration)` like [flunk_delay] or `err(error)`
+/// like [flunk_err], e.g.
+/// `flunk_any!("send", [panic, delay(Duration::from_millis(500)), err(ErrorKind::TimedOut)])`.
+/// The pick comes from the run's seeded generator, so a replayed surge takes
+/// the same actions. A list with `err` only compiles inside functions
+/// returning a `Result`.
+#[macro_export]
+macro_rules! flunk_any {
+    ($name:expr, [$($action:ident $(($arg:expr))?),+ $(,)?]) => {{
+        if let ::std::option::Option::Some(name) =
+            $crate::flunker_in(module_path!(), $name, |name| name.to_owned())
+        {
+            let mut pick = $crate::flunk_pick([$(stringify!($action)),+].len());
+            $(
+                if pick == 0 {
+                    $crate::flunk_any!(@$action name $(, $arg)?);
+                }
+                pick = pick.wrapping_sub(1);
+            )+
+            let _ = pick;
+        }
+    }};
+    (@panic $name:ident) => {
+        panic!("KAOS: Flunking at \"{}\"", $name)
+    };
+    (@delay $name:ident, $duration:expr) => {
+        $crate::flunk_sleep(&$name, $duration)
+    };
+    (@err $name:ident, $error:expr) => {
+        return ::std::result::Result::Err(::std::convert::From::from($error))
+    };
+}
+
+///
+/// Macro to define a flunk point inside async code
+///
+/// When the point fires it yields to the executor once and then panics, or
+/// with a second argument returns `Err(error)` from the enclosing `async fn`
+/// or block like [flunk_err] does. Only usable where `.await` is, and the
+/// runtime driving the future must be running. Inside `tokio::select!` wrap
+/// it in a block: `_ = async { flunk_async!("name") } => {}`.
+#[macro_export]
+macro_rules! flunk_async {
+    ($name:expr) => {{
+        if let ::std::option::Option::Some(name) =
+            $crate::flunker_in(module_path!(), $name, |name| name.to_owned())
+        {
+            $crate::flunk_yield().await;
+            panic!("KAOS: Flunking at \"{}\"", name);
+        }
+    }};
+    ($name:expr, $error:expr) => {{
+        if $crate::flunker_in(module_path!(), $name, |_| ()).is_some() {
+            $crate::flunk_yield().await;
+            return ::std::result::Result::Err(::std::convert::From::from($error));
+        }
+    }};
+}
+
 ///
 /// Define kaos tests
+///
+/// Activates the flunk points called `name` while `body` runs, either a bare
+/// name or one namespaced by module like `my_service::net::timeout`. A list of
+/// names, `kaostest!(["disk", "net"], { ... })`, activates all of them. Setting
+/// the `KAOS_FLUNKS` environment variable to a comma separated list of flunk
+/// names activates those instead, leaving every other point a no-op. A name
+/// like `group:storage` activates every point of the group.
+///
+/// Points reached while inactive are reported as a warning, to catch names
+/// that don't match the ones given here.
 #[macro_export]
 macro_rules! kaostest {
+    ([$($name:expr),+ $(,)?], $body:block) => {{
+        let scenario = $crate::KaosFailScenario::setup();
+        $crate::flunk_activate(&[$($name),+]);
+
+        $body
+
+        scenario.teardown();
+    }};
     ($name:expr, $body:block) => {{
         let scenario = $crate::KaosFailScenario::setup();
-        $crate::flunker_cfg($name, "panic").unwrap();
+        $crate::flunk_activate(&[$name]);
 
         $body
 
@@ -23,8 +312,32 @@ macro_rules! kaostest {
     }};
 }
 
+// A literal following the name of a `flunk!` point is either the probability
+// it fails with or the message it always fails with, which the macro can't
+// tell apart.
+#[doc(hidden)]
+pub trait FlunkArg {
+    fn split(self) -> (f64, Option<&'static str>);
+}
+
+impl FlunkArg for f64 {
+    fn split(self) -> (f64, Option<&'static str>) {
+        (self, None)
+    }
+}
+
+impl FlunkArg for &'static str {
+    fn split(self) -> (f64, Option<&'static str>) {
+        (1.0, Some(self))
+    }
+}
+
+#[doc(hidden)]
+pub fn split<A: FlunkArg>(arg: A) -> (f64, Option<&'static str>) {
+    arg.split()
+}
 
-#[cfg(test)]
+#[cfg(all(test, feature = "std"))]
 mod macro_tests {
     #[test]
     fn kaostest() {
@@ -32,4 +345,352 @@ mod macro_tests {
             println!("potato");
         });
     }
+
+    #[test]
+    fn kaostest_activates_a_list_of_flunks() {
+        use std::panic;
+
+        kaostest!(["disk", "net"], {
+            assert!(panic::catch_unwind(|| flunk!("disk")).is_err());
+            assert!(panic::catch_unwind(|| flunk!("net")).is_err());
+            assert!(panic::catch_unwind(|| flunk!("cache")).is_ok());
+        });
+    }
+
+    #[test]
+    fn kaostest_activates_a_group_of_flunks() {
+        use std::panic;
+
+        kaostest!("group:storage", {
+            assert!(panic::catch_unwind(|| flunk!("write", group = "storage")).is_err());
+            assert!(panic::catch_unwind(|| flunk!("read", groups = ["io", "storage"])).is_err());
+            assert!(panic::catch_unwind(|| flunk!("send", 1.0, group = "rpc")).is_ok());
+            assert!(panic::catch_unwind(|| flunk!("write")).is_ok());
+            assert_eq!(crate::flunk_hits("group:storage"), 2);
+        });
+    }
+
+    #[test]
+    fn flunk_never_fires_with_zero_probability() {
+        kaostest!("never", {
+            for _ in 0..100 {
+                flunk!("never", 0.0);
+            }
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "KAOS: Flunking at \"kaos::macros::macro_tests::always\"")]
+    fn flunk_always_fires_with_full_probability() {
+        kaostest!("always", {
+            flunk!("always", 1.0);
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "macro_tests::crowded\": cache holds 3 entries")]
+    fn flunk_formats_a_custom_message() {
+        let entries = 3;
+        kaostest!("crowded", {
+            flunk!("crowded", "cache holds {} entries", entries);
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "macro_tests::full\": cache is full")]
+    fn flunk_formats_a_message_without_arguments() {
+        kaostest!("full", {
+            flunk!("full", "cache is full");
+        });
+    }
+
+    #[test]
+    fn flunk_if_fires_only_while_the_condition_holds() {
+        use std::panic;
+
+        kaostest!("guarded", {
+            let fired = (0..4)
+                .map(|i| panic::catch_unwind(|| flunk_if!("guarded", i % 2 == 1)).is_err())
+                .collect::<Vec<_>>();
+            assert_eq!(fired, [false, true, false, true]);
+            assert!(panic::catch_unwind(|| flunk_if!("guarded", true, 0.0)).is_ok());
+            assert_eq!(crate::flunk_hits("guarded"), 3);
+        });
+    }
+
+    #[test]
+    fn flunk_delay_sleeps_instead_of_panicking() {
+        use std::time::{Duration, Instant};
+
+        kaostest!("slow", {
+            let now = Instant::now();
+            flunk_delay!("slow", Duration::from_millis(20));
+            assert!(now.elapsed() >= Duration::from_millis(20));
+        });
+    }
+
+    #[test]
+    fn flunk_after_fires_once_after_count() {
+        use std::panic;
+
+        kaostest!("worn", {
+            let fired = (0..6)
+                .map(|_| panic::catch_unwind(|| flunk_after!("worn", 3)).is_err())
+                .collect::<Vec<_>>();
+            assert_eq!(fired, [false, false, false, true, false, false]);
+        });
+
+        kaostest!("worn", {
+            let fired = (0..4)
+                .map(|_| panic::catch_unwind(|| flunk_after!("worn", 3)).is_err())
+                .collect::<Vec<_>>();
+            assert_eq!(fired, [false, false, false, true]);
+        });
+    }
+
+    #[test]
+    fn flunk_every_fires_at_the_interval() {
+        use std::panic;
+
+        kaostest!("intermittent", {
+            let fired = (0..7)
+                .map(|_| panic::catch_unwind(|| flunk_every!("intermittent", 3)).is_err())
+                .collect::<Vec<_>>();
+            assert_eq!(fired, [false, false, true, false, false, true, false]);
+        });
+
+        kaostest!("intermittent", {
+            let fired = (0..3)
+                .map(|_| panic::catch_unwind(|| flunk_every!("intermittent", 3)).is_err())
+                .collect::<Vec<_>>();
+            assert_eq!(fired, [false, false, true]);
+        });
+    }
+
+    #[test]
+    fn on_flunk_observes_every_evaluation() {
+        use std::panic;
+        use std::sync::{Arc, Mutex};
+
+        let observed = Arc::new(Mutex::new(Vec::new()));
+        let record = Arc::clone(&observed);
+        crate::on_flunk(move |name, fired| {
+            if name.ends_with("-observed") {
+                record.lock().unwrap().push((name.to_owned(), fired));
+            }
+        });
+
+        kaostest!(["write-observed", "sync-observed"], {
+            assert!(panic::catch_unwind(|| flunk!("write-observed")).is_err());
+            flunk!("read-observed");
+            flunk!("sync-observed", 0.0);
+        });
+        assert_eq!(
+            *observed.lock().unwrap(),
+            [
+                ("kaos::macros::macro_tests::write-observed".to_owned(), true),
+                ("kaos::macros::macro_tests::read-observed".to_owned(), false),
+                ("kaos::macros::macro_tests::sync-observed".to_owned(), false),
+            ]
+        );
+    }
+
+    #[test]
+    fn flunkable_functions_flunk_when_entered() {
+        use std::panic;
+
+        #[crate::flunkable]
+        fn replicate() -> u32 {
+            7
+        }
+        #[crate::flunkable(name = "commit-log", rate = 1.0)]
+        fn commit() {}
+
+        assert_eq!(replicate(), 7);
+        kaostest!("replicate", {
+            assert!(panic::catch_unwind(replicate).is_err());
+            assert!(panic::catch_unwind(commit).is_ok());
+        });
+        kaostest!("commit-log", {
+            assert!(panic::catch_unwind(commit).is_err());
+        });
+    }
+
+    #[test]
+    fn flunk_evaluations_are_counted_while_active() {
+        flunk!("counted", 0.0);
+        kaostest!("counted", {
+            assert_eq!(crate::flunk_hits("counted"), 0);
+            flunk!("counted", 0.0);
+            flunk_delay!("counted", std::time::Duration::from_millis(0));
+            assert_eq!(crate::flunk_hits("counted"), 2);
+        });
+        assert_eq!(crate::flunk_hits("counted"), 0);
+    }
+
+    #[test]
+    fn flunk_names_are_namespaced_by_module() {
+        use std::panic;
+
+        mod net {
+            pub fn timeout() {
+                flunk!("timeout");
+            }
+        }
+        mod disk {
+            pub fn timeout() {
+                flunk!("timeout");
+            }
+        }
+        fn global_timeout() {
+            flunk_global!("timeout");
+        }
+
+        kaostest!("kaos::macros::macro_tests::net::timeout", {
+            assert!(panic::catch_unwind(net::timeout).is_err());
+            assert!(panic::catch_unwind(disk::timeout).is_ok());
+            assert!(panic::catch_unwind(global_timeout).is_ok());
+        });
+        kaostest!("timeout", {
+            assert!(panic::catch_unwind(net::timeout).is_err());
+            assert!(panic::catch_unwind(disk::timeout).is_err());
+            assert!(panic::catch_unwind(global_timeout).is_err());
+        });
+    }
+
+    #[test]
+    fn flunk_with_runs_the_callback() {
+        let mut buffer = vec![1, 2, 3];
+        flunk_with!("truncate", || buffer.truncate(1));
+        assert_eq!(buffer, [1, 2, 3]);
+
+        kaostest!("truncate", {
+            flunk_with!("truncate", || buffer.truncate(1));
+        });
+        assert_eq!(buffer, [1]);
+    }
+
+    #[test]
+    fn flunk_err_returns_the_error() {
+        #[derive(Debug, PartialEq)]
+        enum StoreError {
+            Timeout,
+        }
+
+        fn store(name: &str) -> Result<usize, StoreError> {
+            flunk_err!(name, StoreError::Timeout);
+            Ok(42)
+        }
+
+        assert_eq!(store("unreliable-store"), Ok(42));
+        kaostest!("unreliable-store", {
+            assert_eq!(store("unreliable-store"), Err(StoreError::Timeout));
+            assert_eq!(store("other-store"), Ok(42));
+        });
+    }
+
+    #[test]
+    fn flunk_io_returns_an_io_error() {
+        use std::io::{self, ErrorKind};
+
+        fn send(kind: Option<ErrorKind>) -> io::Result<usize> {
+            match kind {
+                Some(kind) => flunk_io!("peer", kind),
+                None => flunk_io!("peer"),
+            }
+            Ok(4)
+        }
+
+        assert_eq!(send(None).unwrap(), 4);
+        kaostest!("peer", {
+            let err = send(Some(ErrorKind::ConnectionReset)).unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::ConnectionReset);
+            assert_eq!(
+                err.to_string(),
+                concat!("KAOS: Flunking at \"", module_path!(), "::peer\""),
+            );
+            assert_eq!(send(None).unwrap_err().kind(), ErrorKind::Other);
+        });
+    }
+
+    #[test]
+    fn flunk_any_picks_each_action() {
+        use std::io::{self, ErrorKind};
+        use std::panic;
+        use std::time::Duration;
+
+        fn send() -> io::Result<()> {
+            flunk_any!("send", [panic, delay(Duration::from_millis(0)), err(ErrorKind::TimedOut)]);
+            Ok(())
+        }
+
+        kaostest!("send", {
+            let outcomes = (0..30)
+                .map(|_| match panic::catch_unwind(send) {
+                    Err(_) => "panic",
+                    Ok(Ok(())) => "delay",
+                    Ok(Err(err)) => {
+                        assert_eq!(err.kind(), ErrorKind::TimedOut);
+                        "err"
+                    }
+                })
+                .collect::<Vec<_>>();
+            for action in &["panic", "delay", "err"] {
+                assert!(outcomes.contains(action), "{} never picked", action);
+            }
+        });
+    }
+
+    #[test]
+    fn flunk_async_yields_before_failing() {
+        use std::future::Future;
+        use std::pin::Pin;
+        use std::sync::Arc;
+        use std::task::{Context, Poll, Wake};
+
+        struct Noop;
+        impl Wake for Noop {
+            fn wake(self: Arc<Self>) {}
+        }
+
+        async fn fetch() -> Result<usize, &'static str> {
+            flunk_async!("async-fetch", "unavailable");
+            Ok(7)
+        }
+
+        // Polls to completion, counting how often the future yielded.
+        fn block_on<F: Future>(future: F) -> (F::Output, usize) {
+            let waker = Arc::new(Noop).into();
+            let mut cx = Context::from_waker(&waker);
+            let mut future = Box::pin(future);
+            let mut pending = 0;
+            loop {
+                match Pin::as_mut(&mut future).poll(&mut cx) {
+                    Poll::Ready(output) => return (output, pending),
+                    Poll::Pending => pending += 1,
+                }
+            }
+        }
+
+        kaostest!("async-fetch", {
+            assert_eq!(block_on(fetch()), (Err("unavailable"), 1));
+        });
+        assert_eq!(block_on(fetch()), (Ok(7), 0));
+    }
+
+    #[test]
+    #[should_panic(expected = "flunk probability must be within [0.0, 1.0]")]
+    fn flunk_rejects_out_of_range_probability() {
+        kaostest!("out-of-range", {
+            flunk!("out-of-range", 1.5);
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "flunk interval must be at least 1, got 0")]
+    fn flunk_every_rejects_a_zero_interval() {
+        kaostest!("never", {
+            flunk_every!("never", 0);
+        });
+    }
 }
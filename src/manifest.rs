@@ -3,6 +3,7 @@ use serde::{Deserialize, Serialize};
 use std::collections::BTreeMap as Map;
 use std::ffi::OsStr;
 use std::path::PathBuf;
+use toml::Value;
 
 #[derive(Serialize, Debug)]
 pub struct Manifest {
@@ -18,6 +19,8 @@ pub struct Manifest {
     pub patch: Map<String, RegistryPatch>,
     #[serde(skip_serializing_if = "Map::is_empty")]
     pub replace: Map<String, Patch>,
+    #[serde(skip_serializing_if = "Map::is_empty")]
+    pub profile: Map<String, Value>,
 }
 
 #[derive(Serialize, Debug)]
@@ -28,12 +31,29 @@ pub struct Package {
     pub publish: bool,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Default)]
 pub enum Edition {
     #[serde(rename = "2015")]
     E2015,
+    #[default]
     #[serde(rename = "2018")]
     E2018,
+    #[serde(rename = "2021")]
+    E2021,
+    #[serde(rename = "2024")]
+    E2024,
+}
+
+impl Edition {
+    pub fn parse(edition: &str) -> Option<Edition> {
+        match edition {
+            "2015" => Some(Edition::E2015),
+            "2018" => Some(Edition::E2018),
+            "2021" => Some(Edition::E2021),
+            "2024" => Some(Edition::E2024),
+            _ => None,
+        }
+    }
 }
 
 #[derive(Serialize, Debug)]
@@ -52,18 +72,12 @@ pub struct Config {
 
 #[derive(Serialize, Debug)]
 pub struct Build {
-    pub rustflags: Vec<&'static str>,
+    pub rustflags: Vec<String>,
 }
 
 #[derive(Serialize, Debug)]
 pub struct Workspace {}
 
-impl Default for Edition {
-    fn default() -> Self {
-        Edition::E2018
-    }
-}
-
 impl AsRef<OsStr> for Name {
     fn as_ref(&self) -> &OsStr {
         self.0.as_ref()
@@ -1,14 +1,18 @@
 use termcolor::Color::{self, *};
 
-use super::{Expected, Test};
+use super::{Distribution, Expected, Test};
 use crate::diff::{Diff, Render};
 use crate::error::Error;
-use crate::normalize;
+use crate::manifest::Name;
+use crate::normalize::{self, Diagnostics};
+use crate::report::{Bucket, ElapsedStats, Mtbf, Phase, Region, Timings};
 use crate::term;
 
 use std::env;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::process::Output;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::time::Duration;
 use humantime::format_duration;
 
 pub(crate) enum Level {
@@ -18,8 +22,26 @@ pub(crate) enum Level {
 
 pub(crate) use self::Level::*;
 
+// Verbosity levels, each shows what the ones below it do. The summary and the
+// errors that stop the suite are always shown, at 0 they are all there is.
+const TEST: u8 = 1;
+const RUN: u8 = 2;
+const OUTPUT: u8 = 3;
+
+static VERBOSITY: AtomicU8 = AtomicU8::new(TEST);
+
+pub(crate) fn set_verbosity(level: u8) {
+    VERBOSITY.store(level, Ordering::Relaxed);
+}
+
+fn shown(level: u8) -> bool {
+    VERBOSITY.load(Ordering::Relaxed) >= level
+}
+
 pub(crate) fn prepare_fail(err: Error) {
-    if err.already_printed() {
+    // Cargo's output scrolls by as the dependencies build, the error points
+    // out what went wrong in it.
+    if err.already_printed() && !matches!(err, Error::CargoFail { .. }) {
         return;
     }
 
@@ -27,11 +49,34 @@ pub(crate) fn prepare_fail(err: Error) {
     print!("ERROR");
     term::reset();
     println!(": {}", err);
+    match &err {
+        Error::NoCargoProject(_) => println!(
+            "help: kaos builds the tests against the crate it is launched from, \
+             run the launcher with `cargo test` inside a crate that has a Cargo.toml"
+        ),
+        Error::CargoFail { stderr, .. } if unresolved(stderr) => println!(
+            "help: cargo could not resolve the dependencies of the generated crate, \
+             `KAOS_DUMP_PROJECT=1` prints where its manifest is"
+        ),
+        _ => {}
+    }
     println!();
 }
 
+// Whether cargo failed on the dependencies of the manifest before building
+// anything.
+fn unresolved(stderr: &str) -> bool {
+    const UNRESOLVED: &[&str] = &[
+        "failed to select a version",
+        "no matching package named",
+        "failed to load manifest",
+        "failed to get `",
+    ];
+    UNRESOLVED.iter().any(|pattern| stderr.contains(pattern))
+}
+
 pub(crate) fn test_fail(err: Error) {
-    if err.already_printed() {
+    if !shown(TEST) || err.already_printed() {
         return;
     }
 
@@ -43,19 +88,615 @@ pub(crate) fn test_fail(err: Error) {
     println!();
 }
 
+// Reminds of what a failed test was meant to exercise.
+pub(crate) fn failed_scenario(path: &Path, description: &str) {
+    if !shown(TEST) {
+        return;
+    }
+
+    println!("note: {} exercises: {}", path.to_string_lossy(), description);
+    println!();
+}
+
+pub(crate) fn report_fail(err: Error) {
+    term::bold_color(Yellow);
+    print!("WARNING");
+    term::reset();
+    println!(": {}", err);
+    println!();
+}
+
 pub(crate) fn no_tests_enabled() {
     term::color(Yellow);
     println!("There are no kaos tests enabled yet.");
     term::reset();
 }
 
+pub(crate) fn watching(roots: &[PathBuf]) {
+    let roots: Vec<_> = roots.iter().map(|root| root.to_string_lossy()).collect();
+    term::bold_color(Cyan);
+    println!(
+        "watching {} for changes, press Ctrl-C to stop...",
+        roots.join(", "),
+    );
+    term::reset();
+    println!();
+}
+
+pub(crate) fn rerunning(changed: &[PathBuf], tests: usize) {
+    term::color(Cyan);
+    for path in changed {
+        println!("changed {}", path.to_string_lossy());
+    }
+    println!("rerunning {} affected test{}", tests, if tests == 1 { "" } else { "s" });
+    term::reset();
+    println!();
+}
+
+pub(crate) fn unsupported_platform(test: &Test) {
+    if !shown(TEST) {
+        return;
+    }
+
+    let platforms = test
+        .platforms
+        .iter()
+        .map(|os| os.name())
+        .collect::<Vec<_>>();
+    print!("test ");
+    term::bold();
+    print!("{}", test.path.to_string_lossy());
+    term::reset();
+    print!(" ... ");
+    term::color(Yellow);
+    print!("skipped");
+    term::reset();
+    println!(", only runs on {}", platforms.join(", "));
+}
+
+pub(crate) fn planned(name: &Name, test: &Test) {
+    print!("{} ", name.0);
+    term::bold();
+    print!("{}", test.path.to_string_lossy());
+    term::reset();
+    if let Some(description) = &test.description {
+        print!(" ({})", description);
+    }
+    for tag in &test.tags {
+        print!(" #{}", tag);
+    }
+    if !test.features.is_empty() {
+        print!(" --features {}", test.features.join(","));
+    }
+    match test.expected {
+        Expected::Available => match (test.supervision, test.max_duration) {
+            (Some(supervision), _) => println!(
+                " [supervised, up to {} restarts, down at most {}]",
+                supervision.restarts,
+                format_duration(supervision.budget),
+            ),
+            (None, None) => println!(
+                " [available, should survive at least {}]",
+                format_duration(test.duration.unwrap()),
+            ),
+            (None, Some(max)) => println!(
+                " [available, should survive at least {} and at most {}]",
+                format_duration(test.duration.unwrap()),
+                format_duration(max),
+            ),
+        },
+        Expected::Chaotic if test.slo.is_some() => {
+            let slo = test.slo.unwrap();
+            println!(
+                " [chaotic, {}% of runs should survive at least {}, surges {}..{}ms]",
+                slo.percentile,
+                format_duration(slo.min_duration),
+                test.min_surge,
+                test.max_surge,
+            );
+        }
+        Expected::Chaotic if test.soak.is_some() => println!(
+            " [chaotic, soaking for {}, surges {}..{}ms]",
+            format_duration(test.soak.unwrap()),
+            test.min_surge,
+            test.max_surge,
+        ),
+        Expected::Chaotic if test.convergence.is_some() => {
+            let convergence = test.convergence.unwrap();
+            println!(
+                " [chaotic, until the mtbf moves by at most {}%, up to {} runs, surges {}..{}ms]",
+                convergence.tolerance * 100.0,
+                convergence.max_runs,
+                test.min_surge,
+                test.max_surge,
+            );
+        }
+        Expected::Chaotic => match test.distribution {
+            Distribution::Uniform => println!(
+                " [chaotic, surges {}..{}ms]",
+                test.min_surge, test.max_surge,
+            ),
+            distribution => println!(
+                " [chaotic, surges {}..{}ms, {:?}]",
+                test.min_surge, test.max_surge, distribution,
+            ),
+        },
+    }
+}
+
+pub(crate) fn dry_run(tests: usize, cases: u32) {
+    println!();
+    term::color(Yellow);
+    println!(
+        "Dry run: {} test{} planned, chaotic tests sample {} surges each. Nothing was built.",
+        tests,
+        if tests == 1 { "" } else { "s" },
+        cases,
+    );
+    term::reset();
+}
+
+pub(crate) fn summary(passed: usize, failed: usize, skipped: usize) {
+    print!("kaos result: ");
+    if failed == 0 {
+        term::bold_color(Green);
+        print!("ok");
+    } else {
+        term::bold_color(Red);
+        print!("FAILED");
+    }
+    term::reset();
+    print!(". ");
+
+    term::color(Green);
+    print!("{} passed", passed);
+    term::reset();
+    print!(", ");
+    if failed > 0 {
+        term::color(Red);
+    }
+    print!("{} failed", failed);
+    term::reset();
+    if skipped > 0 {
+        print!(", ");
+        term::color(Yellow);
+        print!("{} skipped", skipped);
+        term::reset();
+    }
+    println!();
+    println!();
+}
+
+pub(crate) fn timings(total: Duration, timings: &Timings, jobs: usize) {
+    print!("kaos took {}: ", format_duration(to_millis(total)));
+    print!(
+        "{} building dependencies, {} building tests, {} running services",
+        format_duration(to_millis(timings.spent(Phase::Dependencies))),
+        format_duration(to_millis(timings.spent(Phase::Build))),
+        format_duration(to_millis(timings.spent(Phase::Run))),
+    );
+    let settled = timings.spent(Phase::Settle);
+    if settled > Duration::from_secs(0) {
+        print!(", {} settling between runs", format_duration(to_millis(settled)));
+    }
+    if jobs > 1 {
+        print!(" (summed over {} jobs)", jobs);
+    }
+    println!();
+    println!();
+}
+
+pub(crate) fn retrying(err: Error, attempt: usize, retries: usize) {
+    if !shown(TEST) {
+        return;
+    }
+
+    if !err.already_printed() {
+        term::bold_color(Red);
+        print!("error");
+        term::reset();
+        println!(": {}", err);
+        println!();
+    }
+    term::color(Yellow);
+    println!("Attempt {} of {} failed, retrying...", attempt, retries + 1);
+    term::reset();
+    println!();
+}
+
+pub(crate) fn passed_after_retries(attempts: usize) {
+    if !shown(TEST) {
+        return;
+    }
+
+    term::color(Yellow);
+    println!("note: passed after {} attempts", attempts);
+    term::reset();
+    println!();
+}
+
+pub(crate) fn skipped_after_failure(count: usize) {
+    if !shown(TEST) {
+        return;
+    }
+
+    term::color(Yellow);
+    println!(
+        "Skipped {} remaining test{} after the first failure.",
+        count,
+        if count == 1 { "" } else { "s" },
+    );
+    term::reset();
+}
+
+pub(crate) fn elapsed_stats(path: &Path, stats: &ElapsedStats) {
+    if !shown(TEST) {
+        return;
+    }
+
+    print!("elapsed ");
+    term::bold();
+    print!("{}", path.to_string_lossy());
+    term::reset();
+    println!(
+        " ... min {}, median {}, mean {}, max {}",
+        format_duration(to_millis(stats.min)),
+        format_duration(to_millis(stats.median)),
+        format_duration(to_millis(stats.mean)),
+        format_duration(to_millis(stats.max)),
+    );
+    println!();
+}
+
+// Columns of a histogram bar at most, and when the width of the terminal is
+// unknown.
+const HISTOGRAM_BAR: usize = 40;
+
+// One line per bucket, `#` for passing runs and `x` for failing ones, so the
+// bars read without colors too.
+pub(crate) fn histogram(path: &Path, buckets: &[Bucket]) {
+    if !shown(TEST) {
+        return;
+    }
+
+    print!("histogram ");
+    term::bold();
+    print!("{}", path.to_string_lossy());
+    term::reset();
+    println!(" ... runs by surge");
+
+    let labels = buckets
+        .iter()
+        .map(|bucket| format!("{}ms..{}ms", bucket.start, bucket.end))
+        .collect::<Vec<_>>();
+    let label_width = labels.iter().map(String::len).max().unwrap_or(0);
+    let most = buckets
+        .iter()
+        .map(|bucket| bucket.passed + bucket.failed)
+        .max()
+        .unwrap_or(0)
+        .max(1);
+    // Leaves room for the label and the counts after the bar.
+    let bar = term::width()
+        .map_or(HISTOGRAM_BAR, |width| width.saturating_sub(label_width + 30))
+        .clamp(10, HISTOGRAM_BAR);
+    // Rounded up, so a bucket with any runs shows them.
+    let scale = |runs: usize| (runs * bar).div_ceil(most);
+
+    for (bucket, label) in buckets.iter().zip(&labels) {
+        let passed = scale(bucket.passed);
+        let failed = scale(bucket.failed);
+        print!("  {:>width$}  ", label, width = label_width);
+        term::color(Green);
+        print!("{}", "#".repeat(passed));
+        term::color(Red);
+        print!("{}", "x".repeat(failed));
+        term::reset();
+        println!(
+            "{:pad$}  {} passed, {} failed",
+            "",
+            bucket.passed,
+            bucket.failed,
+            pad = (bar + 1).saturating_sub(passed + failed),
+        );
+    }
+    println!();
+}
+
+pub(crate) fn soaked(path: &Path, runs: usize, failures: usize, elapsed: Duration) {
+    if !shown(TEST) {
+        return;
+    }
+
+    print!("soaked ");
+    term::bold();
+    print!("{}", path.to_string_lossy());
+    term::reset();
+    print!(" ... ");
+    if failures == 0 {
+        term::color(Green);
+        print!("no failures");
+    } else {
+        term::color(Red);
+        print!("{} failed", failures);
+    }
+    term::reset();
+    println!(" in {} runs over {}", runs, format_duration(to_millis(elapsed)));
+    println!();
+}
+
+pub(crate) fn convergence(path: &Path, estimate: Option<Duration>, converged: bool, runs: usize) {
+    if !shown(TEST) {
+        return;
+    }
+
+    print!("convergence ");
+    term::bold();
+    print!("{}", path.to_string_lossy());
+    term::reset();
+    print!(" ... ");
+    match (estimate, converged) {
+        (Some(estimate), true) => {
+            term::color(Green);
+            print!("mtbf converged to {}", format_duration(to_millis(estimate)));
+        }
+        (Some(estimate), false) => {
+            term::color(Yellow);
+            print!("mtbf did not converge, last estimate {}", format_duration(to_millis(estimate)));
+        }
+        (None, _) => {
+            term::color(Green);
+            print!("no failures to estimate an mtbf from");
+        }
+    }
+    term::reset();
+    println!(" after {} runs", runs);
+    println!();
+}
+
+pub(crate) fn slo(path: &Path, percentile: f64, uptime: Duration, min_duration: Duration) {
+    if !shown(TEST) {
+        return;
+    }
+
+    print!("slo ");
+    term::bold();
+    print!("{}", path.to_string_lossy());
+    term::reset();
+    print!(" ... {}% of runs stayed up at least {}, ", percentile, format_duration(to_millis(uptime)));
+    if uptime >= min_duration {
+        term::color(Green);
+        println!("objective {} met", format_duration(min_duration));
+    } else {
+        term::bold_color(Red);
+        println!("objective {} missed", format_duration(min_duration));
+    }
+    term::reset();
+    println!();
+}
+
+pub(crate) fn surge_below_runtime(path: &Path, max_surge: isize, shortest: Duration) {
+    if !shown(TEST) {
+        return;
+    }
+
+    term::color(Yellow);
+    print!("warning: ");
+    term::bold();
+    print!("{}", path.to_string_lossy());
+    term::reset();
+    term::color(Yellow);
+    println!(
+        " has a max_surge of {}ms but its shortest run took {}, every surge is met by startup alone",
+        max_surge,
+        format_duration(to_millis(shortest)),
+    );
+    println!("help: the surge budget is likely misconfigured, raise max_surge above the service's startup time");
+    term::reset();
+    println!();
+}
+
+pub(crate) fn generated_project(dir: &Path) {
+    println!("note: the generated project is at {}", dir.display());
+}
+
+pub(crate) fn kept_tmp(dirs: &[PathBuf]) {
+    println!("note: kept the temporary directories of the failed runs:");
+    for dir in dirs {
+        println!("      {}", dir.display());
+    }
+    println!();
+}
+
+pub(crate) fn leftover_processes(pgids: &[u32]) {
+    let pgids: Vec<String> = pgids.iter().map(u32::to_string).collect();
+    term::color(Yellow);
+    println!(
+        "warning: services started by an earlier kaos run are still alive, process groups {}",
+        pgids.join(", "),
+    );
+    println!(
+        "help: they may hold ports or files the services need, stop them with `kill -- -{}`",
+        pgids[0],
+    );
+    term::reset();
+    println!();
+}
+
+pub(crate) fn minimal_failing_surge(path: &Path, surge: isize) {
+    if !shown(TEST) {
+        return;
+    }
+
+    print!("shrunk ");
+    term::bold();
+    print!("{}", path.to_string_lossy());
+    term::reset();
+    print!(" ... ");
+    term::color(Red);
+    print!("minimum availability at which the service fails: {} ms", surge);
+    term::reset();
+    println!();
+    println!();
+}
+
+pub(crate) fn explored(path: &Path, region: Region, runs: usize) {
+    if !shown(TEST) {
+        return;
+    }
+
+    print!("explored ");
+    term::bold();
+    print!("{}", path.to_string_lossy());
+    term::reset();
+    print!(" ... ");
+
+    match region {
+        Region::None => {
+            term::color(Green);
+            print!("no failures");
+        }
+        Region::All => {
+            term::color(Red);
+            print!("fails at every surge");
+        }
+        Region::Below(surge) => {
+            term::color(Yellow);
+            print!("fails below {}ms availability", surge);
+        }
+        Region::From(surge) => {
+            term::color(Yellow);
+            print!("fails from {}ms availability on", surge);
+        }
+        Region::Mixed(min, max) => {
+            term::color(Yellow);
+            print!("fails between {}ms and {}ms, not at every surge", min, max);
+        }
+    }
+    term::reset();
+    println!(" over {} runs", runs);
+    println!();
+}
+
+pub(crate) fn mtbf(mtbf: &Mtbf) {
+    if !shown(TEST) {
+        return;
+    }
+
+    print!("mtbf ");
+    term::bold();
+    print!("{}", mtbf.path.to_string_lossy());
+    term::reset();
+    print!(" ... ");
+
+    match mtbf.estimate {
+        Some(estimate) => {
+            term::color(Yellow);
+            print!("{}", format_duration(to_millis(estimate)));
+            term::reset();
+            let surges = mtbf
+                .failing_surges
+                .iter()
+                .map(|surge| format!("{}ms", surge))
+                .collect::<Vec<_>>()
+                .join(", ");
+            println!(
+                " ({} of {} runs failed, at surges {})",
+                mtbf.failures, mtbf.runs, surges
+            );
+        }
+        None => {
+            term::color(Green);
+            print!("no failures");
+            term::reset();
+            println!(
+                " in {} runs over {}",
+                mtbf.runs,
+                format_duration(to_millis(mtbf.uptime))
+            );
+        }
+    }
+}
+
+pub(crate) fn reproduce_with_seed(seed: u64) {
+    if !shown(TEST) {
+        return;
+    }
+
+    print!("note: chaotic runs sampled their surges with seed ");
+    term::bold();
+    print!("{}", seed);
+    term::reset();
+    println!(".");
+    println!("      Rerun with the environment variable KAOS_SEED={} to reproduce them.", seed);
+    println!();
+}
+
+pub(crate) fn replay(command: &str) {
+    if !shown(TEST) {
+        return;
+    }
+
+    println!("note: to replay the failing run, rerun it with");
+    print!("      ");
+    term::bold();
+    println!("{}", command);
+    term::reset();
+    println!();
+}
+
 pub(crate) fn ok() {
+    if !shown(TEST) {
+        return;
+    }
+
     term::color(Green);
     println!("ok");
     term::reset();
 }
 
+// Runs past the planned cases are proptest shrinking a failure.
+pub(crate) fn progress(run: u32, cases: u32, surge: isize) {
+    if !shown(RUN) {
+        return;
+    }
+
+    term::color(Cyan);
+    if run <= cases {
+        println!("run {}/{} (surge {}ms)", run, cases, surge);
+    } else {
+        println!("shrinking, run {} (surge {}ms)", run, surge);
+    }
+    term::reset();
+}
+
+pub(crate) fn soak_progress(run: u32, surge: isize) {
+    if !shown(RUN) {
+        return;
+    }
+
+    term::color(Cyan);
+    println!("soak run {} (surge {}ms)", run, surge);
+    term::reset();
+}
+
+pub(crate) fn begin_fixture(kind: &str, path: &Path) {
+    if !shown(TEST) {
+        return;
+    }
+
+    print!("{} ", kind);
+    term::bold();
+    print!("{}", path.display());
+    term::reset();
+    print!(" ... ");
+}
+
 pub(crate) fn begin_test(test: &Test, show_expected: bool) {
+    if !shown(TEST) {
+        return;
+    }
+
     let display_name = if show_expected {
         test.path
             .file_name()
@@ -69,10 +710,16 @@ pub(crate) fn begin_test(test: &Test, show_expected: bool) {
     term::bold();
     print!("{}", display_name);
     term::reset();
+    if let Some(description) = &test.description {
+        print!(" ({})", description);
+    }
 
     if show_expected {
         match test.expected {
-            Expected::Available => print!(" [should survive at least {}]", format_duration(test.duration.unwrap()).to_string()),
+            Expected::Available => match test.supervision {
+                Some(supervision) => print!(" [should recover within {}]", format_duration(supervision.budget)),
+                None => print!(" [should survive at least {}]", format_duration(test.duration.unwrap()).to_string()),
+            },
             Expected::Chaotic => print!(" [should survive from chaos]"),
         }
     }
@@ -80,59 +727,60 @@ pub(crate) fn begin_test(test: &Test, show_expected: bool) {
     print!(" ... ");
 }
 
-pub(crate) fn failed_to_build(stderr: &str) {
-    term::bold_color(Red);
-    println!("error");
-    snippet(Red, stderr);
-    println!();
-}
+pub(crate) fn failed_to_build(diagnostics: &Diagnostics) {
+    if !shown(TEST) {
+        return;
+    }
 
-pub(crate) fn should_not_have_compiled() {
     term::bold_color(Red);
     println!("error");
-    term::color(Red);
-    println!("Expected test case to fail to compile, but it succeeded.");
-    term::reset();
-    println!();
+    self::diagnostics(Fail, &diagnostics.errors);
+    self::diagnostics(Warn, &diagnostics.warnings);
 }
 
-pub(crate) fn write_stderr_wip(wip_path: &Path, stderr_path: &Path, stderr: &str) {
-    let wip_path = wip_path.to_string_lossy();
-    let stderr_path = stderr_path.to_string_lossy();
+pub(crate) fn not_built(count: usize) {
+    if !shown(TEST) {
+        return;
+    }
 
-    term::bold_color(Yellow);
-    println!("wip");
-    println!();
-    print!("NOTE");
-    term::reset();
-    println!(": writing the following output to `{}`.", wip_path);
+    term::color(Red);
     println!(
-        "Move this file to `{}` to accept it as correct.",
-        stderr_path,
+        "{} test{} failed to build and won't run",
+        count,
+        if count == 1 { "" } else { "s" },
     );
-    snippet(Yellow, stderr);
+    term::reset();
+    println!();
     println!();
 }
 
-pub(crate) fn overwrite_stderr(stderr_path: &Path, stderr: &str) {
-    let stderr_path = stderr_path.to_string_lossy();
+pub(crate) fn overwrite_output(path: &Path, output: &str) {
+    if !shown(TEST) {
+        return;
+    }
+
+    let path = path.to_string_lossy();
 
     term::bold_color(Yellow);
-    println!("wip");
+    println!("blessed");
     println!();
     print!("NOTE");
     term::reset();
-    println!(": writing the following output to `{}`.", stderr_path);
-    snippet(Yellow, stderr);
+    println!(": writing the following output to `{}`.", path);
+    snippet(Yellow, output);
     println!();
 }
 
 pub(crate) fn mismatch(expected: &str, actual: &str) {
+    if !shown(TEST) {
+        return;
+    }
+
     term::bold_color(Red);
     println!("mismatch");
     term::reset();
     println!();
-    let diff = if env::var_os("TERM").map_or(true, |term| term == "dumb") {
+    let diff = if env::var_os("TERM").is_none_or(|term| term == "dumb") {
         // No diff in dumb terminal or when TERM is unset.
         None
     } else {
@@ -150,14 +798,22 @@ pub(crate) fn mismatch(expected: &str, actual: &str) {
     print!("actual output");
     term::reset();
     println!(" is the correct output you can bless it by rerunning");
-    println!("      your test with the environment variable KAOS=overwrite");
+    println!("      your test with the environment variable KAOS_BLESS=1");
     println!();
 }
 
 pub(crate) fn output(warnings: &str, output: &Output) {
+    if !shown(TEST) {
+        return;
+    }
+
     let success = output.status.success();
-    let stdout = normalize::trim(&output.stdout);
-    let stderr = normalize::trim(&output.stderr);
+    // What passing runs printed only shows at the highest verbosity.
+    let (stdout, stderr) = if success && !shown(OUTPUT) {
+        (String::new(), String::new())
+    } else {
+        (normalize::trim(&output.stdout), normalize::trim(&output.stderr))
+    };
     let has_output = !stdout.is_empty() || !stderr.is_empty();
 
     if success {
@@ -169,7 +825,9 @@ pub(crate) fn output(warnings: &str, output: &Output) {
         term::bold_color(Red);
         println!("error");
         term::color(Red);
-        if has_output {
+        if let Some(flunk) = normalize::fatal_flunk(&output.stderr) {
+            println!("Test case failed at runtime after the flunk injected at \"{}\".", flunk);
+        } else if has_output {
             println!("Test case failed at runtime.");
         } else {
             println!("Execution of the test case was unsuccessful but there was no output.");
@@ -181,7 +839,164 @@ pub(crate) fn output(warnings: &str, output: &Output) {
     self::warnings(warnings);
 
     let color = if success { Yellow } else { Red };
+    streams(color, &stdout, &stderr);
+}
 
+pub(crate) fn crashed(warnings: &str, output: &Output, flunk: Option<&str>) {
+    if !shown(TEST) {
+        return;
+    }
+
+    let stdout = normalize::trim(&output.stdout);
+    let (stderr, backtrace) = normalize::backtrace(&output.stderr);
+    let stderr = normalize::trim(stderr);
+
+    term::bold_color(Red);
+    println!("error");
+    term::color(Red);
+    if let Some(flunk) = flunk {
+        println!("Service did not recover from the flunk injected at \"{}\".", flunk);
+    } else {
+        println!("Service crashed during the chaotic run.");
+    }
+    term::reset();
+    println!();
+
+    self::warnings(warnings);
+    streams(Red, &stdout, &stderr);
+    if let Some(backtrace) = backtrace {
+        term::bold_color(Red);
+        println!("BACKTRACE:");
+        snippet(Red, &backtrace);
+        println!();
+    }
+}
+
+pub(crate) fn out_of_memory(warnings: &str, output: &Output) {
+    if !shown(TEST) {
+        return;
+    }
+
+    let stdout = normalize::trim(&output.stdout);
+    let stderr = normalize::trim(&output.stderr);
+
+    term::bold_color(Red);
+    println!("error");
+    term::color(Red);
+    println!("Service was killed with SIGKILL, most likely by the out-of-memory killer.");
+    term::reset();
+    if let Some(kills) = oom_kills() {
+        println!("note: the kernel reports {} out-of-memory kills since boot", kills);
+    }
+    println!();
+
+    self::warnings(warnings);
+    streams(Red, &stdout, &stderr);
+}
+
+// The kernel's count of out-of-memory kills, only kept by Linux.
+fn oom_kills() -> Option<u64> {
+    let vmstat = std::fs::read_to_string("/proc/vmstat").ok()?;
+    vmstat
+        .lines()
+        .find_map(|line| line.strip_prefix("oom_kill "))
+        .and_then(|kills| kills.trim().parse().ok())
+}
+
+pub(crate) fn unreached_flunks(names: &[String]) {
+    if !shown(TEST) {
+        return;
+    }
+
+    let names = names
+        .iter()
+        .map(|name| format!("\"{}\"", name))
+        .collect::<Vec<_>>();
+    term::color(Yellow);
+    println!(
+        "warning: activated flunk point{} never reached: {}",
+        if names.len() == 1 { " was" } else { "s were" },
+        names.join(", "),
+    );
+    term::reset();
+    println!();
+}
+
+pub(crate) fn inactive_flunks(names: &[String]) {
+    if !shown(TEST) {
+        return;
+    }
+
+    let names = names
+        .iter()
+        .map(|name| format!("\"{}\"", name))
+        .collect::<Vec<_>>();
+    term::color(Yellow);
+    println!(
+        "warning: inactive flunk point{} reached: {}",
+        if names.len() == 1 { " was" } else { "s were" },
+        names.join(", "),
+    );
+    println!("help: check that the names given to `kaostest!` match the flunk points meant to fire");
+    term::reset();
+    println!();
+}
+
+pub(crate) fn recovered_from_flunk(flunks: &[String]) {
+    if !shown(TEST) {
+        return;
+    }
+
+    let mut names = Vec::new();
+    for name in flunks {
+        let name = format!("\"{}\"", name);
+        if !names.contains(&name) {
+            names.push(name);
+        }
+    }
+    term::color(Green);
+    println!(
+        "note: service recovered from the flunk{} injected at {}",
+        if names.len() == 1 { "" } else { "s" },
+        names.join(", "),
+    );
+    term::reset();
+    println!();
+}
+
+// The crashes a supervised service was restarted after, with the flunk behind
+// each one if a flunk caused it, and how long the service was down for.
+pub(crate) fn restarted(crashes: &[Option<String>], downtime: Duration) {
+    if !shown(TEST) {
+        return;
+    }
+
+    term::color(Yellow);
+    println!(
+        "note: service was restarted {} time{} after crashing, down for {}",
+        crashes.len(),
+        if crashes.len() == 1 { "" } else { "s" },
+        format_duration(downtime),
+    );
+    let mut flunks = Vec::new();
+    for flunk in crashes.iter().flatten() {
+        let flunk = format!("\"{}\"", flunk);
+        if !flunks.contains(&flunk) {
+            flunks.push(flunk);
+        }
+    }
+    if !flunks.is_empty() {
+        println!(
+            "      crashes followed the flunk{} injected at {}",
+            if flunks.len() == 1 { "" } else { "s" },
+            flunks.join(", "),
+        );
+    }
+    term::reset();
+    println!();
+}
+
+fn streams(color: Color, stdout: &str, stderr: &str) {
     for (name, content) in &[("STDOUT", stdout), ("STDERR", stderr)] {
         if !content.is_empty() {
             term::bold_color(color);
@@ -192,31 +1007,31 @@ pub(crate) fn output(warnings: &str, output: &Output) {
     }
 }
 
-pub(crate) fn fail_output(level: Level, stdout: &[u8]) {
-    let color = match level {
-        Fail => Red,
-        Warn => Yellow,
-    };
-
-    if !stdout.is_empty() {
-        term::bold_color(color);
-        println!("STDOUT:");
-        snippet(color, &normalize::trim(stdout));
-        println!();
-    }
+pub(crate) fn warnings(warnings: &str) {
+    diagnostics(Warn, warnings);
 }
 
-pub(crate) fn warnings(warnings: &str) {
-    if warnings.is_empty() {
+fn diagnostics(level: Level, content: &str) {
+    if content.is_empty() {
         return;
     }
 
-    term::bold_color(Yellow);
-    println!("WARNINGS:");
-    snippet(Yellow, warnings);
+    match level {
+        Fail => snippet(Red, content),
+        Warn => {
+            term::bold_color(Yellow);
+            println!("WARNINGS:");
+            snippet(Yellow, content);
+        }
+    }
     println!();
 }
 
+// Sub-millisecond precision is noise next to process start up times.
+fn to_millis(duration: Duration) -> Duration {
+    Duration::from_millis(duration.as_millis() as u64)
+}
+
 fn snippet(color: Color, content: &str) {
     snippet_diff(color, content, None);
 }
@@ -251,3 +1066,42 @@ fn snippet_diff(color: Color, content: &str, diff: Option<&Diff>) {
     dotted_line();
     term::reset();
 }
+
+pub(crate) fn thin_margin(path: &Path, required: Duration, elapsed: Duration, margin: f64) {
+    if !shown(TEST) {
+        return;
+    }
+
+    term::color(Yellow);
+    print!("warning: ");
+    term::bold();
+    print!("{}", path.to_string_lossy());
+    term::reset();
+    term::color(Yellow);
+    println!(
+        " availability margin thin, survived {} against {}, within {}% of the bar",
+        format_duration(to_millis(elapsed)),
+        format_duration(required),
+        margin * 100.0,
+    );
+    println!("help: a slower machine or a little more chaos will fail it, look into what keeps it down");
+    term::reset();
+    println!();
+}
+
+pub(crate) fn no_flunk_points(path: &Path) {
+    if !shown(TEST) {
+        return;
+    }
+
+    term::color(Yellow);
+    print!("warning: ");
+    term::bold();
+    print!("{}", path.to_string_lossy());
+    term::reset();
+    term::color(Yellow);
+    println!(" never activated a flunk point, it probably isn't injecting any chaos");
+    println!("help: wrap the service in `kaostest!` and put `flunk!` points on its failure paths");
+    term::reset();
+    println!();
+}
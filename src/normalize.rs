@@ -1,10 +1,88 @@
 use std::path::Path;
+use std::time::Duration;
+
+use crate::flunk::{
+    ACTIVATED_MARKER, DELAY_MARKER, FLUNK_MARKER, INACTIVE_MARKER, UNREACHED_MARKER,
+};
 
 #[derive(Copy, Clone)]
 pub struct Context<'a> {
     pub krate: &'a str,
     pub source_dir: &'a Path,
     pub workspace: &'a Path,
+    pub paths: &'a [PathRule],
+}
+
+/// A user supplied rewrite of paths, applied before the built-in ones. A `*`
+/// in `from` matches any run of characters within one path component.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PathRule {
+    pub from: String,
+    pub to: String,
+}
+
+/// Applies every rule to `text`, in the order they were added.
+pub fn rewrite_paths(text: &str, rules: &[PathRule]) -> String {
+    rules.iter().fold(text.to_owned(), |text, rule| {
+        if rule.from.contains('*') {
+            replace_pattern(&text, &rule.from, &rule.to)
+        } else {
+            text.replace(&rule.from, &rule.to)
+        }
+    })
+}
+
+fn replace_pattern(text: &str, pattern: &str, to: &str) -> String {
+    let mut replaced = String::new();
+    let mut rest = text;
+    while let Some((start, end)) = find_pattern(rest, pattern) {
+        replaced += &rest[..start];
+        replaced += to;
+        rest = &rest[end..];
+        if start == end {
+            // An empty match, step over a character to make progress.
+            match rest.chars().next() {
+                Some(c) => {
+                    replaced.push(c);
+                    rest = &rest[c.len_utf8()..];
+                }
+                None => break,
+            }
+        }
+    }
+    replaced + rest
+}
+
+// Where the first match of `pattern` in `text` starts and ends. A `*` matches
+// as little as it can, except at the end of the pattern where it takes the
+// rest of the path component.
+fn find_pattern(text: &str, pattern: &str) -> Option<(usize, usize)> {
+    let in_component = |c: char| c != '/' && c != '\\' && !c.is_whitespace();
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or("");
+    let parts = parts.collect::<Vec<_>>();
+
+    'start: for (start, _) in text.match_indices(first) {
+        let mut end = start + first.len();
+        for part in &parts {
+            if part.is_empty() {
+                continue;
+            }
+            match text[end..].find(part) {
+                Some(skip) if text[end..end + skip].chars().all(in_component) => {
+                    end += skip + part.len();
+                }
+                _ => continue 'start,
+            }
+        }
+        if pattern.ends_with('*') {
+            end += text[end..]
+                .find(|c: char| !in_component(c))
+                .unwrap_or(text.len() - end);
+        }
+        return Some((start, end));
+    }
+    None
 }
 
 pub fn trim<S: AsRef<[u8]>>(output: S) -> String {
@@ -21,6 +99,156 @@ pub fn trim<S: AsRef<[u8]>>(output: S) -> String {
     normalized
 }
 
+/// Names of the flunk points that panicked during a run, in the order they fired.
+pub fn fired_flunks<S: AsRef<[u8]>>(stderr: S) -> Vec<String> {
+    marked_names(stderr.as_ref(), FLUNK_MARKER)
+}
+
+/// Names of the flunk points a kaos test turned on during a run.
+pub fn activated_flunks<S: AsRef<[u8]>>(stderr: S) -> Vec<String> {
+    marked_names(stderr.as_ref(), ACTIVATED_MARKER)
+}
+
+/// Names of the flunk points that were active but never reached during a run.
+pub fn unreached_flunks<S: AsRef<[u8]>>(stderr: S) -> Vec<String> {
+    marked_names(stderr.as_ref(), UNREACHED_MARKER)
+}
+
+/// Names of the inactive flunk points a kaos test reached during a run.
+pub fn inactive_flunks<S: AsRef<[u8]>>(stderr: S) -> Vec<String> {
+    marked_names(stderr.as_ref(), INACTIVE_MARKER)
+}
+
+// Quoted names following `marker` anywhere in a line.
+fn marked_names(stderr: &[u8], marker: &str) -> Vec<String> {
+    String::from_utf8_lossy(stderr)
+        .lines()
+        .filter_map(|line| {
+            let rest = &line[line.find(marker)? + marker.len()..];
+            let name = rest.strip_prefix('"')?;
+            Some(name[..name.find('"')?].to_owned())
+        })
+        .collect()
+}
+
+/// The flunk point that brought the service down. Earlier flunks were caught,
+/// so the one that fired last is the one that actually panicked out.
+pub fn fatal_flunk<S: AsRef<[u8]>>(stderr: S) -> Option<String> {
+    fired_flunks(stderr).pop()
+}
+
+// Frames of the panic machinery and of the flunk points themselves, rather
+// than of the code that crashed.
+const NOISY_FRAMES: &[&str] = &[
+    "std::", "core::", "alloc::", "<std::", "<core::", "<alloc::", "__rustc::",
+    "rust_begin_unwind", "kaos::", "fail::",
+];
+
+/// Splits the backtraces off the stderr of a run. Returns the rest of it and
+/// the backtrace of the panic that fired last, which brought the service
+/// down, without the frames of the standard library, kaos and `fail`.
+pub fn backtrace<S: AsRef<[u8]>>(stderr: S) -> (String, Option<String>) {
+    let stderr = String::from_utf8_lossy(stderr.as_ref());
+    let mut rest = String::new();
+    let mut last = None;
+    let mut lines = stderr.lines().peekable();
+    while let Some(line) = lines.next() {
+        if line != "stack backtrace:" {
+            rest.push_str(line);
+            rest.push('\n');
+            continue;
+        }
+        let mut frames = Vec::new();
+        while let Some(&frame) = lines.peek() {
+            if !frame.starts_with(' ') {
+                break;
+            }
+            frames.push(frame);
+            lines.next();
+        }
+        if lines
+            .peek()
+            .is_some_and(|line| line.starts_with("note: Some details are omitted"))
+        {
+            lines.next();
+        }
+        last = Some(trim_frames(&frames));
+    }
+    (rest, last.filter(|backtrace| !backtrace.is_empty()))
+}
+
+// Keeps the frames outside of `NOISY_FRAMES`, each with the `at` lines giving
+// its location.
+fn trim_frames(lines: &[&str]) -> String {
+    let mut trimmed = String::new();
+    let mut keep = false;
+    for line in lines {
+        let header = line.trim_start();
+        if let Some((index, symbol)) = header.split_once(": ") {
+            if index.chars().all(|c| c.is_ascii_digit()) {
+                // Full backtraces put the address before the symbol.
+                let symbol = symbol.rsplit(" - ").next().unwrap_or(symbol);
+                keep = !NOISY_FRAMES.iter().any(|noisy| symbol.starts_with(noisy));
+            }
+        }
+        if keep {
+            trimmed.push_str(line);
+            trimmed.push('\n');
+        }
+    }
+    trimmed
+}
+
+/// Total latency injected by `flunk_delay!` points during a run.
+pub fn injected_latency<S: AsRef<[u8]>>(stderr: S) -> Duration {
+    String::from_utf8_lossy(stderr.as_ref())
+        .lines()
+        .filter(|line| line.contains(DELAY_MARKER))
+        .filter_map(|line| {
+            let millis = line.rsplit(" for ").next()?.strip_suffix("ms")?;
+            millis.parse().ok().map(Duration::from_millis)
+        })
+        .sum()
+}
+
+/// Build diagnostics split by severity.
+#[derive(Debug, Default, PartialEq)]
+pub struct Diagnostics {
+    /// The errors, each from its `error` header on.
+    pub errors: String,
+    /// The warnings, with whatever came before the first diagnostic.
+    pub warnings: String,
+}
+
+/// Sorts normalized compiler output into errors and warnings. Each diagnostic
+/// runs from its `error`/`warning` header up to the next header; anything
+/// before the first header is kept with the warnings so that it still shows up
+/// when the build succeeds.
+pub fn classify(diagnostics: &str) -> Diagnostics {
+    let mut classified = Diagnostics::default();
+    let mut is_error = false;
+
+    for line in diagnostics.lines() {
+        if line.starts_with("error") {
+            is_error = true;
+        } else if line.starts_with("warning") {
+            is_error = false;
+        }
+
+        let bucket = if is_error {
+            &mut classified.errors
+        } else {
+            &mut classified.warnings
+        };
+        bucket.push_str(line);
+        bucket.push('\n');
+    }
+
+    classified.errors = trim(&classified.errors);
+    classified.warnings = trim(&classified.warnings);
+    classified
+}
+
 /// For a given compiler output, produces the set of saved outputs against which
 /// the compiler's output would be considered correct. If the test's saved
 /// stderr file is identical to any one of these variations, the test will pass.
@@ -50,17 +278,31 @@ pub fn diagnostics(output: Vec<u8>, context: Context) -> Variations {
     Variations { variations }
 }
 
+/// The normalized forms of the compiler output of a test, see
+/// [Runs::assert_diagnostics](crate::Runs::assert_diagnostics).
+///
+/// Paths are shown relative to the crate as `$DIR` and the workspace as
+/// `$WORKSPACE`. Each form strips more of the boilerplate cargo adds, like the
+/// `could not compile` lines.
+#[derive(Debug)]
 pub struct Variations {
     variations: Vec<String>,
 }
 
 impl Variations {
+    /// The most normalized form, the one kaos prints.
     pub fn preferred(&self) -> &str {
         self.variations.last().unwrap()
     }
 
-    pub fn any<F: FnMut(&str) -> bool>(&self, mut f: F) -> bool {
-        self.variations.iter().any(|stderr| f(stderr))
+    /// Every form, from the least normalized to the [preferred](Variations::preferred) one.
+    pub fn all(&self) -> impl Iterator<Item = &str> {
+        self.variations.iter().map(String::as_str)
+    }
+
+    /// The preferred form split into errors and warnings.
+    pub fn classified(&self) -> Diagnostics {
+        classify(self.preferred())
     }
 }
 
@@ -91,6 +333,8 @@ fn apply(original: &str, normalization: Normalization, context: Context) -> Stri
 }
 
 fn filter(line: &str, normalization: Normalization, context: Context) -> Option<String> {
+    let line = &rewrite_paths(line, context.paths);
+
     if line.trim_start().starts_with("--> ") {
         if let Some(cut_end) = line.rfind(&['/', '\\'][..]) {
             let cut_start = line.find('>').unwrap() + 2;
@@ -111,22 +355,18 @@ fn filter(line: &str, normalization: Normalization, context: Context) -> Option<
         return None;
     }
 
-    if normalization >= StripCouldNotCompile {
-        if line.starts_with("error: Could not compile `") {
-            return None;
-        }
+    if normalization >= StripCouldNotCompile && line.starts_with("error: Could not compile `") {
+        return None;
     }
 
-    if normalization >= StripCouldNotCompile2 {
-        if line.starts_with("error: could not compile `") {
-            return None;
-        }
+    if normalization >= StripCouldNotCompile2 && line.starts_with("error: could not compile `") {
+        return None;
     }
 
-    if normalization >= StripForMoreInformation {
-        if line.starts_with("For more information about this error, try `rustc --explain") {
-            return None;
-        }
+    if normalization >= StripForMoreInformation
+        && line.starts_with("For more information about this error, try `rustc --explain")
+    {
+        return None;
     }
 
     if normalization >= StripForMoreInformation2 {
@@ -145,3 +385,156 @@ fn filter(line: &str, normalization: Normalization, context: Context) -> Option<
 
     Some(line)
 }
+
+#[test]
+fn test_classify() {
+    let diagnostics = "\
+warning: unused variable: `x`
+ --> $DIR/service.rs:2:9
+  |
+2 |     let x = 1;
+  |         ^ help: if this is intentional, prefix it with an underscore: `_x`
+
+error[E0308]: mismatched types
+ --> $DIR/service.rs:3:5
+  |
+3 |     \"no\"
+  |     ^^^^ expected `usize`, found `&str`
+
+warning: `$CRATE` (bin \"kaos000\") generated 1 warning
+";
+    let classified = classify(diagnostics);
+    assert!(classified.errors.starts_with("error[E0308]: mismatched types\n"));
+    assert!(!classified.errors.contains("warning"));
+    assert!(classified.warnings.starts_with("warning: unused variable"));
+    assert!(classified.warnings.ends_with("generated 1 warning\n"));
+    assert_eq!(classify(""), Diagnostics::default());
+}
+
+#[test]
+fn test_variations() {
+    let stderr = b"\
+warning: unused variable: `x`
+ --> /home/dev/service/kaos-tests/up.rs:2:9
+
+error: could not compile `service-tests`
+
+For more information about this error, try `rustc --explain E0308`.
+";
+    let context = Context {
+        krate: "service",
+        source_dir: Path::new("/home/dev/service"),
+        workspace: Path::new("/home/dev/service"),
+        paths: &[],
+    };
+    let variations = diagnostics(stderr.to_vec(), context);
+    let all = variations.all().collect::<Vec<_>>();
+    assert_eq!(all.len(), 5);
+    assert!(all[0].contains("could not compile"));
+    assert_eq!(all.last().copied(), Some(variations.preferred()));
+    assert_eq!(
+        variations.preferred(),
+        "warning: unused variable: `x`\n --> $DIR/up.rs:2:9\n"
+    );
+    assert_eq!(variations.classified().warnings, variations.preferred());
+    assert!(variations.classified().errors.is_empty());
+}
+
+#[test]
+fn test_fired_flunks() {
+    let stderr = "\
+thread 'main' panicked at src/lib.rs:4:5:
+KAOS: Flunking at \"cache\"
+note: run with `RUST_BACKTRACE=1` environment variable to display a backtrace
+thread 'main' panicked at 'KAOS: Flunking at \"disk write\"', src/lib.rs:9:5
+";
+    assert_eq!(fired_flunks(stderr), ["cache", "disk write"]);
+    assert_eq!(fatal_flunk(stderr).as_deref(), Some("disk write"));
+    assert_eq!(fatal_flunk("service output"), None);
+    assert!(unreached_flunks(stderr).is_empty());
+    assert_eq!(unreached_flunks("KAOS: Never reached \"cache\"\n"), ["cache"]);
+    assert_eq!(activated_flunks("KAOS: Activated \"cache\"\n"), ["cache"]);
+    assert_eq!(inactive_flunks("KAOS: Reached inactive \"cahce\"\n"), ["cahce"]);
+}
+
+#[test]
+fn test_backtrace() {
+    let stderr = "\
+KAOS: Flunking at \"disk\"
+thread 'main' panicked at src/kaos000.rs:4:9:
+KAOS: Flunking at \"disk\"
+stack backtrace:
+   0: __rustc::rust_begin_unwind
+             at /rustc/library/std/src/panicking.rs:697:5
+   1: core::panicking::panic_fmt
+             at /rustc/library/core/src/panicking.rs:75:14
+   2: kaos::flunk::eval_in
+             at /kaos/src/flunk.rs:120:9
+   3: service::disk::write
+             at ./src/disk.rs:12:5
+   4: kaos000::main
+             at ./src/kaos000.rs:7:5
+   5: core::ops::function::FnOnce::call_once
+             at /rustc/library/core/src/ops/function.rs:250:5
+note: Some details are omitted, run with `RUST_BACKTRACE=full` for a verbose backtrace.
+exiting
+";
+    let (rest, trimmed) = backtrace(stderr);
+    assert_eq!(
+        rest,
+        "\
+KAOS: Flunking at \"disk\"
+thread 'main' panicked at src/kaos000.rs:4:9:
+KAOS: Flunking at \"disk\"
+exiting
+",
+    );
+    assert_eq!(
+        trimmed.unwrap(),
+        "   3: service::disk::write
+             at ./src/disk.rs:12:5
+   4: kaos000::main
+             at ./src/kaos000.rs:7:5
+",
+    );
+    assert_eq!(backtrace("thread 'main' panicked at src/main.rs:1:1:\n").1, None);
+}
+
+#[test]
+fn test_injected_latency() {
+    let stderr = "\
+KAOS: Delaying at \"disk\" for 500ms
+service output
+KAOS: Delaying at \"net for real\" for 250ms
+KAOS: Delaying at \"garbled\" for soon
+";
+    assert_eq!(injected_latency(stderr), Duration::from_millis(750));
+    assert_eq!(injected_latency(""), Duration::from_millis(0));
+}
+
+#[test]
+fn test_rewrite_paths() {
+    let rules = [
+        PathRule {
+            from: "/mnt/build/checkout".to_owned(),
+            to: "/home/dev/service".to_owned(),
+        },
+        PathRule {
+            from: "/home/*/service".to_owned(),
+            to: "$SERVICE".to_owned(),
+        },
+        PathRule {
+            from: "/tmp/kaos-*".to_owned(),
+            to: "$TMP".to_owned(),
+        },
+    ];
+    assert_eq!(
+        rewrite_paths("--> /mnt/build/checkout/src/lib.rs:1:1", &rules),
+        "--> $SERVICE/src/lib.rs:1:1",
+    );
+    assert_eq!(
+        rewrite_paths("/home/a/service and /home/b/c/service", &rules),
+        "$SERVICE and /home/b/c/service",
+    );
+    assert_eq!(rewrite_paths("wrote /tmp/kaos-1234/state", &rules), "wrote $TMP/state");
+}
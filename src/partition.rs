@@ -0,0 +1,169 @@
+use std::io::Write;
+use std::process::{Command, Stdio};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::thread;
+use std::time::Duration;
+
+use crate::error::{Error, Result};
+
+// Anchors under com.apple are evaluated by the default pf.conf of macOS.
+#[cfg(target_os = "macos")]
+const ANCHOR: &str = "com.apple/kaos";
+
+/// Ports cut off at the start of every run of a service, for `during`.
+#[derive(Clone, Debug)]
+pub struct Partition {
+    pub ports: Vec<u16>,
+    pub during: Duration,
+}
+
+// A firewall command, with what to feed it on stdin.
+type Step = (&'static str, Vec<String>, Option<String>);
+
+/// Firewall rules in place for a run. They are lifted once the partition is
+/// over, or when this is dropped, whichever comes first, so a run that ends
+/// early or panics doesn't leave the ports blocked.
+pub struct Rules {
+    lifts: Arc<Mutex<Vec<Step>>>,
+}
+
+impl Partition {
+    pub fn install(&self) -> Result<Rules> {
+        let rules = Rules {
+            lifts: Arc::new(Mutex::new(Vec::new())),
+        };
+        // What was installed before a failure is lifted as the rules drop.
+        block(&self.ports, &mut rules.lifts.lock().unwrap_or_else(PoisonError::into_inner))?;
+
+        let lifts = Arc::clone(&rules.lifts);
+        let during = self.during;
+        thread::spawn(move || {
+            thread::sleep(during);
+            lift(&lifts);
+        });
+        Ok(rules)
+    }
+}
+
+impl Drop for Rules {
+    fn drop(&mut self) {
+        lift(&self.lifts);
+    }
+}
+
+// Lifting is best effort, in the reverse order of installing.
+fn lift(lifts: &Mutex<Vec<Step>>) {
+    let steps = std::mem::take(&mut *lifts.lock().unwrap_or_else(PoisonError::into_inner));
+    for step in steps.into_iter().rev() {
+        let _ = firewall(step);
+    }
+}
+
+// Runs one firewall command, its stderr is the error when it fails.
+fn firewall((program, args, input): Step) -> Result<Vec<u8>> {
+    let command = format!("{} {}", program, args.join(" "));
+    let failed = |reason: String| Error::Partition(format!("`{}` {}", command, reason));
+    let mut child = Command::new(program)
+        .args(&args)
+        .stdin(if input.is_some() { Stdio::piped() } else { Stdio::null() })
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|err| failed(format!("could not run: {}", err)))?;
+    if let (Some(input), Some(mut stdin)) = (input, child.stdin.take()) {
+        stdin
+            .write_all(input.as_bytes())
+            .map_err(|err| failed(format!("could not read the rules: {}", err)))?;
+    }
+    let output = child
+        .wait_with_output()
+        .map_err(|err| failed(format!("could not run: {}", err)))?;
+    if output.status.success() {
+        Ok(output.stderr)
+    } else {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        Err(failed(format!("failed: {}", stderr.trim())))
+    }
+}
+
+// Traffic of a service listening on a port reaches it on INPUT, traffic of a
+// service connecting to one leaves on OUTPUT.
+#[cfg(target_os = "linux")]
+fn iptables_rules(ports: &[u16]) -> Vec<Vec<String>> {
+    let mut rules = Vec::new();
+    for port in ports {
+        let port = port.to_string();
+        for chain in &["INPUT", "OUTPUT"] {
+            for protocol in &["tcp", "udp"] {
+                let rule = vec![*chain, "-p", protocol, "--dport", &port];
+                let target = vec!["-m", "comment", "--comment", "kaos-partition", "-j", "DROP"];
+                rules.push(rule.into_iter().chain(target).map(str::to_owned).collect());
+            }
+        }
+    }
+    rules
+}
+
+#[cfg(target_os = "linux")]
+fn block(ports: &[u16], lifts: &mut Vec<Step>) -> Result<()> {
+    for rule in iptables_rules(ports) {
+        let with = |action: &str| {
+            let args = std::iter::once(action.to_owned()).chain(rule.iter().cloned()).collect();
+            ("iptables", args, None)
+        };
+        firewall(with("-I"))?;
+        lifts.push(with("-D"));
+    }
+    Ok(())
+}
+
+// pf has to be enabled for the anchor to be evaluated. Enabling hands out a
+// token, releasing it disables pf again unless someone else enabled it too.
+// Parallel runs share the anchor, the first to end lifts the rules of all.
+#[cfg(target_os = "macos")]
+fn block(ports: &[u16], lifts: &mut Vec<Step>) -> Result<()> {
+    let args = |args: &[&str]| args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>();
+    let enabled = firewall(("pfctl", args(&["-E"]), None))?;
+    let enabled = String::from_utf8_lossy(&enabled);
+    if let Some(token) = enabled.lines().find_map(|line| line.strip_prefix("Token : ")) {
+        lifts.push(("pfctl", args(&["-X", token.trim()]), None));
+    }
+
+    let ports = ports.iter().map(u16::to_string).collect::<Vec<_>>().join(", ");
+    let rules = format!("block drop quick proto {{ tcp, udp }} from any to any port {{ {} }}\n", ports);
+    firewall(("pfctl", args(&["-a", ANCHOR, "-f", "-"]), Some(rules)))?;
+    lifts.push(("pfctl", args(&["-a", ANCHOR, "-F", "rules"]), None));
+    Ok(())
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+fn block(_ports: &[u16], _lifts: &mut Vec<Step>) -> Result<()> {
+    Err(Error::Partition(
+        "not supported on this platform, partitions take iptables or pfctl".to_owned(),
+    ))
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_iptables_rules() {
+    let rules = iptables_rules(&[5432]);
+    assert_eq!(rules.len(), 4);
+    assert_eq!(
+        rules[0].join(" "),
+        "INPUT -p tcp --dport 5432 -m comment --comment kaos-partition -j DROP",
+    );
+    assert_eq!(
+        rules[3].join(" "),
+        "OUTPUT -p udp --dport 5432 -m comment --comment kaos-partition -j DROP",
+    );
+}
+
+#[test]
+fn test_lift_on_drop() {
+    let rules = Rules {
+        lifts: Arc::new(Mutex::new(vec![("true", Vec::new(), None)])),
+    };
+    let lifts = Arc::clone(&rules.lifts);
+    drop(rules);
+    assert!(lifts.lock().unwrap().is_empty());
+}
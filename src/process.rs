@@ -0,0 +1,147 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitStatus, Output};
+
+// Exit code of a shell whose child was killed with SIGKILL.
+const KILLED_CODE: i32 = 137;
+// How cargo reports a binary it ran being killed with SIGKILL.
+const CARGO_KILLED: &str = "(signal: 9, SIGKILL: kill)";
+
+// Every service runs in a process group of its own, led by the cargo process
+// that starts it, so whatever the service spawns can be reaped along with it.
+#[cfg(unix)]
+pub fn isolate(cmd: &mut Command) {
+    use std::os::unix::process::CommandExt;
+    cmd.process_group(0);
+}
+
+#[cfg(not(unix))]
+pub fn isolate(_cmd: &mut Command) {}
+
+// Kills everything left in the group. Killing an empty group does nothing.
+#[cfg(unix)]
+pub fn kill_group(pgid: u32) {
+    unsafe {
+        libc::kill(-(pgid as libc::pid_t), libc::SIGKILL);
+    }
+}
+
+#[cfg(not(unix))]
+pub fn kill_group(_pgid: u32) {}
+
+#[cfg(unix)]
+fn is_alive(pgid: u32) -> bool {
+    unsafe { libc::kill(-(pgid as libc::pid_t), 0) == 0 }
+}
+
+#[cfg(not(unix))]
+fn is_alive(_pgid: u32) -> bool {
+    false
+}
+
+#[cfg(unix)]
+fn sigkilled(status: &ExitStatus) -> bool {
+    use std::os::unix::process::ExitStatusExt;
+    status.signal() == Some(libc::SIGKILL)
+}
+
+#[cfg(not(unix))]
+fn sigkilled(_status: &ExitStatus) -> bool {
+    false
+}
+
+/// Whether a service was killed with SIGKILL, which kaos itself only sends on
+/// timeouts. Short of someone killing it by hand, that is the out-of-memory
+/// killer. Services run through cargo, so its report of the kill counts too.
+pub fn killed(output: &Output) -> bool {
+    sigkilled(&output.status)
+        || output.status.code() == Some(KILLED_CODE)
+        || String::from_utf8_lossy(&output.stderr).contains(CARGO_KILLED)
+}
+
+/// Groups of running services recorded under `dir`, one file per group, so
+/// that a later run can tell when one outlived the run that started it.
+#[derive(Debug)]
+pub struct Tracked {
+    path: PathBuf,
+}
+
+impl Tracked {
+    // Recording is best effort, the group is reaped whether or not it is tracked.
+    pub fn new(dir: &Path, pgid: u32) -> Tracked {
+        let path = dir.join(pgid.to_string());
+        let _ = fs::create_dir_all(dir).and_then(|_| fs::write(&path, b""));
+        Tracked { path }
+    }
+}
+
+impl Drop for Tracked {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// Groups tracked under `dir` that are still alive. The records of the ones
+/// that are gone are cleared.
+pub fn leftovers(dir: &Path) -> Vec<u32> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return Vec::new(),
+    };
+    let mut alive = Vec::new();
+    for entry in entries.flatten() {
+        let pgid = entry.file_name().to_str().and_then(|name| name.parse().ok());
+        match pgid {
+            Some(pgid) if is_alive(pgid) => alive.push(pgid),
+            _ => {
+                let _ = fs::remove_file(entry.path());
+            }
+        }
+    }
+    alive.sort_unstable();
+    alive
+}
+
+#[cfg(unix)]
+#[test]
+fn test_killed() {
+    use std::os::unix::process::ExitStatusExt;
+
+    let output = |status, stderr: &str| Output {
+        status: ExitStatus::from_raw(status),
+        stdout: Vec::new(),
+        stderr: stderr.as_bytes().to_vec(),
+    };
+    assert!(killed(&output(libc::SIGKILL, "")));
+    assert!(killed(&output(137 << 8, "")));
+    assert!(killed(&output(
+        101 << 8,
+        "error: process didn't exit successfully: `target/debug/kaos001` (signal: 9, SIGKILL: kill)",
+    )));
+    assert!(!killed(&output(101 << 8, "thread 'main' panicked at src/main.rs:3:5:")));
+    assert!(!killed(&output(0, "")));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_kill_group() {
+    use std::os::unix::process::{CommandExt, ExitStatusExt};
+
+    let mut leader = Command::new("sleep");
+    leader.arg("30");
+    isolate(&mut leader);
+    let mut leader = leader.spawn().unwrap();
+    let pgid = leader.id();
+    // Both members are children of the test, which reaps them itself rather
+    // than leaving them to init.
+    let mut member = Command::new("sleep")
+        .arg("30")
+        .process_group(pgid as i32)
+        .spawn()
+        .unwrap();
+    assert!(is_alive(pgid));
+    kill_group(pgid);
+    assert_eq!(leader.wait().unwrap().signal(), Some(libc::SIGKILL));
+    assert_eq!(member.wait().unwrap().signal(), Some(libc::SIGKILL));
+    assert!(!is_alive(pgid));
+}
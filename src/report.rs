@@ -0,0 +1,782 @@
+use serde::{Serialize, Serializer};
+use std::fmt::Write;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use super::Expected;
+use crate::error::{Error, Result};
+
+#[derive(Serialize, Debug)]
+pub struct Report<'a> {
+    pub seed: u64,
+    pub tests: &'a [TestResult],
+    pub mtbf: &'a [Mtbf],
+}
+
+/// What a run of the tests came to, see `Runs::run_collect`.
+#[derive(Debug)]
+pub struct RunReport {
+    /// The seed the chaotic tests ran with.
+    pub seed: u64,
+    /// The tests that ran, in the order they were registered.
+    pub tests: Vec<TestResult>,
+    /// Failures observed across the chaotic runs of each test file.
+    pub mtbf: Vec<Mtbf>,
+    /// How many tests were skipped after a failure, see `Runs::fail_fast`.
+    pub skipped: usize,
+    /// How long the run took, from the setup on.
+    pub elapsed: Duration,
+    /// Why the tests could not be set up, in which case none ran.
+    pub setup_failure: Option<String>,
+    pub(crate) suite: String,
+}
+
+impl RunReport {
+    /// How many tests passed.
+    pub fn passed(&self) -> usize {
+        self.tests.iter().filter(|test| test.passed).count()
+    }
+
+    /// How many tests failed. Skipped tests are not counted.
+    pub fn failed(&self) -> usize {
+        self.tests.len() - self.passed()
+    }
+
+    /// Whether the tests could be set up and every one that ran passed.
+    pub fn success(&self) -> bool {
+        self.setup_failure.is_none() && self.failed() == 0
+    }
+
+    // Which tests failed and the first line of why, without the backtrace of a
+    // crash, for the panic failing the launcher.
+    pub(crate) fn failures(&self) -> String {
+        let mut failures = format!(
+            "{} of {} tests failed:",
+            self.failed(),
+            self.tests.len() + self.skipped
+        );
+        for test in self.tests.iter().filter(|test| !test.passed) {
+            let reason = test
+                .failure
+                .as_deref()
+                .and_then(|failure| failure.lines().next())
+                .map(|line| line.trim_end_matches(", backtrace:"));
+            failures += &format!("\n    {}: {}", test.path.display(), reason.unwrap_or("failed"));
+        }
+        failures
+    }
+
+    /// The exit code summing up the run, see `Runs::exit_codes`.
+    pub fn exit_code(&self) -> i32 {
+        if self.setup_failure.is_some() {
+            EXIT_INFRA
+        } else {
+            exit_code(&self.tests)
+        }
+    }
+}
+
+/// The outcome of one test.
+#[derive(Serialize, Debug)]
+pub struct TestResult {
+    pub name: String,
+    pub path: PathBuf,
+    pub expected: Expected,
+    pub passed: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub failure: Option<String>,
+    #[serde(rename = "elapsed_ms", serialize_with = "as_millis")]
+    pub elapsed: Duration,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub elapsed_stats: Option<ElapsedStats>,
+    pub attempts: usize,
+    pub runs: Vec<RunResult>,
+    // Failed on the harness side, see `Error::is_infra`.
+    #[serde(skip)]
+    pub(crate) infra_failure: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+/// The outcome of one run of a test.
+#[derive(Serialize, Clone, Debug)]
+pub struct RunResult {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub surge: Option<isize>,
+    #[serde(rename = "elapsed_ms", serialize_with = "as_millis")]
+    pub elapsed: Duration,
+    pub passed: bool,
+}
+
+/// How long the chaotic runs of one test took.
+#[derive(Serialize, Clone, Copy, PartialEq, Debug)]
+pub struct ElapsedStats {
+    #[serde(rename = "min_ms", serialize_with = "as_millis")]
+    pub min: Duration,
+    #[serde(rename = "max_ms", serialize_with = "as_millis")]
+    pub max: Duration,
+    #[serde(rename = "mean_ms", serialize_with = "as_millis")]
+    pub mean: Duration,
+    #[serde(rename = "median_ms", serialize_with = "as_millis")]
+    pub median: Duration,
+}
+
+impl ElapsedStats {
+    /// Statistics over the given runs, absent when there are none.
+    pub fn of(runs: &[RunResult]) -> Option<ElapsedStats> {
+        let mut elapsed = runs.iter().map(|run| run.elapsed).collect::<Vec<_>>();
+        elapsed.sort();
+
+        let len = elapsed.len();
+        let median = match len {
+            0 => return None,
+            _ if len % 2 == 0 => (elapsed[len / 2 - 1] + elapsed[len / 2]) / 2,
+            _ => elapsed[len / 2],
+        };
+
+        Some(ElapsedStats {
+            min: elapsed[0],
+            max: elapsed[len - 1],
+            mean: elapsed.iter().sum::<Duration>() / len as u32,
+            median,
+        })
+    }
+}
+
+/// Runs of one surge bucket, in milliseconds from `start` up to `end`.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Bucket {
+    pub start: isize,
+    pub end: isize,
+    pub passed: usize,
+    pub failed: usize,
+}
+
+/// The surged runs split into at most `count` or so buckets of a round width,
+/// empty ones included. Absent, as an empty list, below two surged runs.
+pub fn histogram(runs: &[RunResult], count: usize) -> Vec<Bucket> {
+    let surged = runs
+        .iter()
+        .filter_map(|run| run.surge.map(|surge| (surge, run.passed)))
+        .collect::<Vec<_>>();
+    if surged.len() < 2 {
+        return Vec::new();
+    }
+    let min = surged.iter().map(|&(surge, _)| surge).min().unwrap();
+    let max = surged.iter().map(|&(surge, _)| surge).max().unwrap();
+
+    // The smallest of 1, 2 and 5 times a power of ten covering the range.
+    let raw = (max - min) / count.max(1) as isize + 1;
+    let mut step = 1;
+    let width = loop {
+        if let Some(width) = [step, 2 * step, 5 * step].iter().find(|&&width| width >= raw) {
+            break *width;
+        }
+        step *= 10;
+    };
+
+    let first = min - min.rem_euclid(width);
+    let mut buckets = (0..=(max - first) / width)
+        .map(|i| Bucket {
+            start: first + i * width,
+            end: first + (i + 1) * width,
+            passed: 0,
+            failed: 0,
+        })
+        .collect::<Vec<_>>();
+    for (surge, passed) in surged {
+        let bucket = &mut buckets[((surge - first) / width) as usize];
+        if passed {
+            bucket.passed += 1;
+        } else {
+            bucket.failed += 1;
+        }
+    }
+    buckets
+}
+
+/// The mean time between the failures of `runs`, the time they were up over
+/// the number that failed. Absent when none did.
+pub fn estimate(runs: &[RunResult]) -> Option<Duration> {
+    let failures = runs.iter().filter(|run| !run.passed).count();
+    if failures == 0 {
+        return None;
+    }
+    Some(runs.iter().map(|run| run.elapsed).sum::<Duration>() / failures as u32)
+}
+
+/// Whether the last run moved the estimate of `runs` by no more than
+/// `tolerance` relative to the one before it. Never before there are two
+/// estimates.
+pub fn converged(runs: &[RunResult], tolerance: f64) -> bool {
+    let earlier = match runs.split_last() {
+        Some((_, earlier)) => earlier,
+        None => return false,
+    };
+    match (estimate(earlier), estimate(runs)) {
+        (Some(before), Some(now)) => {
+            let before = before.as_secs_f64();
+            (now.as_secs_f64() - before).abs() <= tolerance * before
+        }
+        _ => false,
+    }
+}
+
+/// The longest time that `percentile` percent of the runs stayed up for, by
+/// nearest rank. Absent when there are no runs.
+pub fn uptime_percentile(runs: &[RunResult], percentile: f64) -> Option<Duration> {
+    let mut elapsed = runs.iter().map(|run| run.elapsed).collect::<Vec<_>>();
+    if elapsed.is_empty() {
+        return None;
+    }
+    elapsed.sort_by(|a, b| b.cmp(a));
+    let rank = (percentile / 100.0 * elapsed.len() as f64).ceil() as usize;
+    Some(elapsed[rank.clamp(1, elapsed.len()) - 1])
+}
+
+/// Where the failing surges of an explored chaotic test lie.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Region {
+    /// No run failed.
+    None,
+    /// Every run failed.
+    All,
+    /// Runs failed below the given surge and passed from it on.
+    Below(isize),
+    /// Runs passed below the given surge and failed from it on.
+    From(isize),
+    /// Failing runs between the two surges, interleaved with passing ones.
+    Mixed(isize, isize),
+}
+
+impl Region {
+    pub fn of(runs: &[RunResult]) -> Region {
+        let surges = |passed: bool| {
+            runs.iter()
+                .filter(move |run| run.passed == passed)
+                .filter_map(|run| run.surge)
+        };
+        let (min_failing, max_failing) = match (surges(false).min(), surges(false).max()) {
+            (Some(min), Some(max)) => (min, max),
+            _ => return Region::None,
+        };
+        let (min_passing, max_passing) = match (surges(true).min(), surges(true).max()) {
+            (Some(min), Some(max)) => (min, max),
+            _ => return Region::All,
+        };
+
+        if max_failing < min_passing {
+            Region::Below(min_passing)
+        } else if max_passing < min_failing {
+            Region::From(min_failing)
+        } else {
+            Region::Mixed(min_failing, max_failing)
+        }
+    }
+}
+
+/// Where the time of a suite goes.
+#[derive(Copy, Clone, Debug)]
+pub enum Phase {
+    /// Building the dependencies of the generated test crate, once.
+    Dependencies,
+    /// Building the test binaries.
+    Build,
+    /// Running the services.
+    Run,
+    /// Waiting between chaotic runs, see `Runs::settle`.
+    Settle,
+}
+
+/// Time spent in each [Phase], summed over the tests. Shared by the workers of
+/// parallel runs.
+#[derive(Debug, Default)]
+pub struct Timings {
+    nanos: [AtomicU64; 4],
+}
+
+impl Timings {
+    pub fn time<T, F: FnOnce() -> T>(&self, phase: Phase, f: F) -> T {
+        let start = Instant::now();
+        let result = f();
+        self.nanos[phase as usize].fetch_add(start.elapsed().as_nanos() as u64, Ordering::Relaxed);
+        result
+    }
+
+    pub fn spent(&self, phase: Phase) -> Duration {
+        Duration::from_nanos(self.nanos[phase as usize].load(Ordering::Relaxed))
+    }
+}
+
+/// Exit code when an availability test failed, see `Runs::exit_codes`.
+pub const EXIT_AVAILABILITY: i32 = 2;
+/// Exit code when a chaotic test crashed the service.
+pub const EXIT_CHAOS: i32 = 3;
+/// Exit code when the harness failed, like a test that didn't build.
+pub const EXIT_INFRA: i32 = 4;
+
+/// The exit code summing up the tests, the most severe failure wins.
+pub fn exit_code(tests: &[TestResult]) -> i32 {
+    let failed = |expected: Expected| {
+        tests
+            .iter()
+            .any(|test| !test.passed && test.expected == expected)
+    };
+    if tests.iter().any(|test| test.infra_failure) {
+        EXIT_INFRA
+    } else if failed(Expected::Chaotic) {
+        EXIT_CHAOS
+    } else if failed(Expected::Available) {
+        EXIT_AVAILABILITY
+    } else {
+        0
+    }
+}
+
+/// Failures observed across every chaotic run of one test file.
+#[derive(Serialize, Debug)]
+pub struct Mtbf {
+    pub path: PathBuf,
+    pub runs: usize,
+    pub failures: usize,
+    pub failing_surges: Vec<isize>,
+    #[serde(rename = "uptime_ms", serialize_with = "as_millis")]
+    pub uptime: Duration,
+    #[serde(rename = "mtbf_ms", serialize_with = "as_opt_millis")]
+    pub estimate: Option<Duration>,
+}
+
+/// Computes the mean time between failures of each chaotic test file, in the
+/// order the files first appear. The estimate is the total time the service
+/// was up divided by the number of failed runs, and is absent when none failed.
+pub fn mtbf(tests: &[TestResult]) -> Vec<Mtbf> {
+    let mut mtbf: Vec<Mtbf> = Vec::new();
+
+    for test in tests {
+        if let Expected::Available = test.expected {
+            continue;
+        }
+
+        let i = match mtbf.iter().position(|m| m.path == test.path) {
+            Some(i) => i,
+            None => {
+                mtbf.push(Mtbf {
+                    path: test.path.clone(),
+                    runs: 0,
+                    failures: 0,
+                    failing_surges: Vec::new(),
+                    uptime: Duration::from_secs(0),
+                    estimate: None,
+                });
+                mtbf.len() - 1
+            }
+        };
+
+        let entry = &mut mtbf[i];
+        for run in &test.runs {
+            entry.runs += 1;
+            entry.uptime += run.elapsed;
+            if !run.passed {
+                entry.failures += 1;
+                entry.failing_surges.extend(run.surge);
+            }
+        }
+    }
+
+    for entry in &mut mtbf {
+        if entry.failures > 0 {
+            entry.estimate = Some(entry.uptime / entry.failures as u32);
+        }
+    }
+
+    mtbf
+}
+
+impl Report<'_> {
+    pub fn write_json(&self, path: &Path) -> Result<()> {
+        let write = || -> io::Result<()> {
+            let json = serde_json::to_vec_pretty(self)?;
+            fs::write(path, json)
+        };
+        write().map_err(|err| Error::WriteReport(path.to_owned(), err))
+    }
+}
+
+impl Report<'_> {
+    pub fn write_junit(&self, path: &Path, suite: &str) -> Result<()> {
+        fs::write(path, self.junit(suite))
+            .map_err(|err| Error::WriteReport(path.to_owned(), err))
+    }
+
+    fn junit(&self, suite: &str) -> String {
+        let failures = self.tests.iter().filter(|test| !test.passed).count();
+        let time: Duration = self.tests.iter().map(|test| test.elapsed).sum();
+
+        let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        let _ = writeln!(
+            xml,
+            "<testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" time=\"{:.3}\">",
+            escape(suite),
+            self.tests.len(),
+            failures,
+            time.as_secs_f64(),
+        );
+        for test in self.tests {
+            let _ = write!(
+                xml,
+                "  <testcase name=\"{}\" classname=\"{}\" time=\"{:.3}\"",
+                escape(&test.path.to_string_lossy()),
+                escape(&test.name),
+                test.elapsed.as_secs_f64(),
+            );
+            if test.failure.is_none() && test.description.is_none() {
+                xml.push_str("/>\n");
+                continue;
+            }
+            xml.push_str(">\n");
+            if let Some(description) = &test.description {
+                let _ = writeln!(
+                    xml,
+                    "    <properties>\n      <property name=\"description\" value=\"{}\"/>\n    </properties>",
+                    escape(description),
+                );
+            }
+            if let Some(failure) = &test.failure {
+                let kind = match test.expected {
+                    Expected::Available => "AvailabilityFailure",
+                    Expected::Chaotic => "ChaosFailure",
+                };
+                let _ = writeln!(
+                    xml,
+                    "    <failure type=\"{}\" message=\"{}\">{}</failure>",
+                    kind,
+                    escape(failure),
+                    escape(failure),
+                );
+            }
+            xml.push_str("  </testcase>\n");
+        }
+        xml.push_str("</testsuite>\n");
+        xml
+    }
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for ch in text.chars() {
+        match ch {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            _ => escaped.push(ch),
+        }
+    }
+    escaped
+}
+
+fn as_millis<S>(duration: &Duration, serializer: S) -> std::result::Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    serializer.serialize_u64(duration.as_millis() as u64)
+}
+
+fn as_opt_millis<S>(
+    duration: &Option<Duration>,
+    serializer: S,
+) -> std::result::Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    match duration {
+        Some(duration) => as_millis(duration, serializer),
+        None => serializer.serialize_none(),
+    }
+}
+
+#[test]
+fn test_junit() {
+    let tests = vec![
+        TestResult {
+            name: "kaos000".to_owned(),
+            path: PathBuf::from("kaos-tests/up.rs"),
+            expected: Expected::Available,
+            passed: true,
+            failure: None,
+            elapsed: Duration::from_millis(1500),
+            elapsed_stats: None,
+            attempts: 1,
+            runs: Vec::new(),
+            infra_failure: false,
+            description: None,
+        },
+        TestResult {
+            name: "kaos001".to_owned(),
+            path: PathBuf::from("kaos-tests/down.rs"),
+            expected: Expected::Chaotic,
+            passed: false,
+            failure: Some("availability is low <1s>".to_owned()),
+            elapsed: Duration::from_millis(250),
+            elapsed_stats: None,
+            attempts: 1,
+            runs: Vec::new(),
+            infra_failure: false,
+            description: Some("partition & heal".to_owned()),
+        },
+    ];
+    let report = Report {
+        seed: 0,
+        tests: &tests,
+        mtbf: &[],
+    };
+
+    let expected = r#"<?xml version="1.0" encoding="UTF-8"?>
+<testsuite name="crate-tests" tests="2" failures="1" time="1.750">
+  <testcase name="kaos-tests/up.rs" classname="kaos000" time="1.500"/>
+  <testcase name="kaos-tests/down.rs" classname="kaos001" time="0.250">
+    <properties>
+      <property name="description" value="partition &amp; heal"/>
+    </properties>
+    <failure type="ChaosFailure" message="availability is low &lt;1s&gt;">availability is low &lt;1s&gt;</failure>
+  </testcase>
+</testsuite>
+"#;
+    assert_eq!(report.junit("crate-tests"), expected);
+}
+
+#[test]
+fn test_elapsed_stats() {
+    let runs = [40, 10, 30, 20]
+        .iter()
+        .map(|&millis| RunResult {
+            surge: Some(millis),
+            elapsed: Duration::from_millis(millis as u64),
+            passed: true,
+        })
+        .collect::<Vec<_>>();
+
+    let stats = ElapsedStats::of(&runs).unwrap();
+    assert_eq!(stats.min, Duration::from_millis(10));
+    assert_eq!(stats.max, Duration::from_millis(40));
+    assert_eq!(stats.mean, Duration::from_millis(25));
+    assert_eq!(stats.median, Duration::from_millis(25));
+
+    let stats = ElapsedStats::of(&runs[..3]).unwrap();
+    assert_eq!(stats.median, Duration::from_millis(30));
+    assert_eq!(ElapsedStats::of(&[]), None);
+}
+
+#[test]
+fn test_uptime_percentile() {
+    let runs = (1..=20)
+        .map(|secs| RunResult {
+            surge: Some(secs),
+            elapsed: Duration::from_secs(secs as u64),
+            passed: true,
+        })
+        .collect::<Vec<_>>();
+
+    assert_eq!(uptime_percentile(&runs, 95.0), Some(Duration::from_secs(2)));
+    assert_eq!(uptime_percentile(&runs, 50.0), Some(Duration::from_secs(11)));
+    assert_eq!(uptime_percentile(&runs, 100.0), Some(Duration::from_secs(1)));
+    assert_eq!(uptime_percentile(&runs, 0.1), Some(Duration::from_secs(20)));
+    assert_eq!(uptime_percentile(&[], 95.0), None);
+}
+
+#[test]
+fn test_mtbf() {
+    fn chaotic(path: &str, runs: &[(isize, u64, bool)]) -> TestResult {
+        TestResult {
+            name: String::new(),
+            path: PathBuf::from(path),
+            expected: Expected::Chaotic,
+            passed: runs.iter().all(|run| run.2),
+            failure: None,
+            elapsed: Duration::from_secs(0),
+            elapsed_stats: None,
+            attempts: 1,
+            runs: runs
+                .iter()
+                .map(|&(surge, elapsed, passed)| RunResult {
+                    surge: Some(surge),
+                    elapsed: Duration::from_millis(elapsed),
+                    passed,
+                })
+                .collect(),
+            infra_failure: false,
+            description: None,
+        }
+    }
+
+    let tests = [
+        chaotic("flaky.rs", &[(10, 100, true), (20, 50, false)]),
+        chaotic("solid.rs", &[(10, 100, true)]),
+        chaotic("flaky.rs", &[(30, 150, false), (40, 300, true)]),
+    ];
+    let mtbf = mtbf(&tests);
+
+    assert_eq!(mtbf.len(), 2);
+    assert_eq!(mtbf[0].path, Path::new("flaky.rs"));
+    assert_eq!(mtbf[0].runs, 4);
+    assert_eq!(mtbf[0].failing_surges, [20, 30]);
+    assert_eq!(mtbf[0].estimate, Some(Duration::from_millis(300)));
+    assert_eq!(mtbf[1].path, Path::new("solid.rs"));
+    assert_eq!(mtbf[1].estimate, None);
+}
+
+#[test]
+fn test_converged() {
+    let runs = |runs: &[(u64, bool)]| {
+        runs.iter()
+            .map(|&(elapsed, passed)| RunResult {
+                surge: Some(0),
+                elapsed: Duration::from_millis(elapsed),
+                passed,
+            })
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(estimate(&runs(&[(100, true)])), None);
+    assert_eq!(
+        estimate(&runs(&[(100, true), (50, false), (150, false)])),
+        Some(Duration::from_millis(150)),
+    );
+    assert!(!converged(&[], 0.5));
+    // No estimate before the first failure.
+    assert!(!converged(&runs(&[(100, true), (50, false)]), 0.5));
+    // 150ms to 160ms is a change of under 10%, to 200ms of a third.
+    assert!(converged(&runs(&[(100, false), (50, false), (10, true)]), 0.1));
+    assert!(!converged(&runs(&[(100, false), (50, false), (50, true)]), 0.1));
+}
+
+#[test]
+fn test_histogram() {
+    let runs = |runs: &[(isize, bool)]| {
+        runs.iter()
+            .map(|&(surge, passed)| RunResult {
+                surge: Some(surge),
+                elapsed: Duration::from_millis(0),
+                passed,
+            })
+            .collect::<Vec<_>>()
+    };
+    let bucket = |start, end, passed, failed| Bucket { start, end, passed, failed };
+
+    assert!(histogram(&runs(&[(10, true)]), 10).is_empty());
+    assert_eq!(
+        histogram(&runs(&[(30, false), (120, false), (180, true), (350, true), (990, true)]), 5),
+        [
+            bucket(0, 200, 1, 2),
+            bucket(200, 400, 1, 0),
+            bucket(400, 600, 0, 0),
+            bucket(600, 800, 0, 0),
+            bucket(800, 1000, 1, 0),
+        ],
+    );
+    assert_eq!(
+        histogram(&runs(&[(42, true), (42, false)]), 10),
+        [bucket(42, 43, 1, 1)],
+    );
+}
+
+#[test]
+fn test_region() {
+    let runs = |runs: &[(isize, bool)]| {
+        runs.iter()
+            .map(|&(surge, passed)| RunResult {
+                surge: Some(surge),
+                elapsed: Duration::from_millis(0),
+                passed,
+            })
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(Region::of(&[]), Region::None);
+    assert_eq!(Region::of(&runs(&[(10, true), (20, true)])), Region::None);
+    assert_eq!(Region::of(&runs(&[(10, false), (20, false)])), Region::All);
+    assert_eq!(
+        Region::of(&runs(&[(500, true), (100, false), (420, true), (300, false)])),
+        Region::Below(420)
+    );
+    assert_eq!(Region::of(&runs(&[(100, true), (700, false)])), Region::From(700));
+    assert_eq!(
+        Region::of(&runs(&[(100, false), (200, true), (300, false)])),
+        Region::Mixed(100, 300)
+    );
+}
+
+#[test]
+fn test_timings() {
+    let timings = Timings::default();
+    assert_eq!(timings.time(Phase::Build, || 7), 7);
+    timings.time(Phase::Run, || std::thread::sleep(Duration::from_millis(5)));
+    assert!(timings.spent(Phase::Run) >= Duration::from_millis(5));
+    assert_eq!(timings.spent(Phase::Dependencies), Duration::from_secs(0));
+    assert_eq!(timings.spent(Phase::Settle), Duration::from_secs(0));
+}
+
+#[test]
+fn test_failures() {
+    let test = |path: &str, failure: Option<&str>| TestResult {
+        name: String::new(),
+        path: PathBuf::from(path),
+        expected: Expected::Chaotic,
+        passed: failure.is_none(),
+        failure: failure.map(str::to_owned),
+        elapsed: Duration::from_secs(0),
+        elapsed_stats: None,
+        attempts: 1,
+        runs: Vec::new(),
+        infra_failure: false,
+        description: None,
+    };
+    let report = RunReport {
+        seed: 0,
+        tests: vec![
+            test("kaos-tests/up.rs", None),
+            test("kaos-tests/crash.rs", Some("service crashed, backtrace:\n   0: crash::main")),
+            test("kaos-tests/slow.rs", Some("chaos test failed: went down after 12ms")),
+        ],
+        mtbf: Vec::new(),
+        skipped: 1,
+        elapsed: Duration::from_secs(0),
+        setup_failure: None,
+        suite: String::new(),
+    };
+    assert_eq!(
+        report.failures(),
+        "2 of 4 tests failed:\n    kaos-tests/crash.rs: service crashed\n    \
+         kaos-tests/slow.rs: chaos test failed: went down after 12ms",
+    );
+}
+
+#[test]
+fn test_exit_code() {
+    let test = |expected, passed, infra_failure| TestResult {
+        name: String::new(),
+        path: PathBuf::new(),
+        expected,
+        passed,
+        failure: None,
+        elapsed: Duration::from_secs(0),
+        elapsed_stats: None,
+        attempts: 1,
+        runs: Vec::new(),
+        infra_failure,
+        description: None,
+    };
+    let up = || test(Expected::Available, true, false);
+    let down = || test(Expected::Available, false, false);
+    let crashed = || test(Expected::Chaotic, false, false);
+    let broken = || test(Expected::Chaotic, false, true);
+
+    assert_eq!(exit_code(&[]), 0);
+    assert_eq!(exit_code(&[up(), test(Expected::Chaotic, true, false)]), 0);
+    assert_eq!(exit_code(&[up(), down()]), EXIT_AVAILABILITY);
+    assert_eq!(exit_code(&[down(), crashed()]), EXIT_CHAOS);
+    assert_eq!(exit_code(&[crashed(), broken(), down()]), EXIT_INFRA);
+}
@@ -1,21 +1,41 @@
+use std::cell::{Cell, RefCell};
 use std::collections::BTreeMap as Map;
 use std::env;
-use std::ffi::{OsStr, OsString};
+use std::ffi::OsString;
 use std::fs::{self, File};
+use std::process::{ExitStatus, Output};
 use std::{time::{Instant, Duration}, path::{Path, PathBuf}};
 
-use super::{Expected, Runner, Test};
-use crate::cargo;
+use super::{Convergence, DiagnosticCheck, Distribution, Expected, FromSettings, Hooks, Runner, Slo, Test};
+use crate::cargo::{self, TargetMetadata};
 use crate::dependencies::{self, Dependency};
 use crate::env::Update;
 use crate::error::{Error, Result};
+use crate::events::{self, Event};
 use crate::features;
-use crate::manifest::{Bin, Build, Config, Manifest, Name, Package, Workspace};
-use crate::message::{self, Fail, Warn};
-use crate::normalize::{self, Context, Variations};
+use crate::manifest::{Bin, Build, Config, Edition, Manifest, Name, Package, Workspace};
+use crate::message;
+use crate::normalize::{self, Context, PathRule, Variations};
+use crate::partition::Partition;
+use crate::process;
+use crate::report::{self, ElapsedStats, Phase, Region, Report, RunReport, RunResult, TestResult, Timings};
 use crate::rustflags;
-use std::convert::TryInto;
-use proptest::test_runner::{TestRunner, TestCaseError};
+use crate::settings::Settings;
+use crate::term;
+use crate::watch::Snapshot;
+use std::cmp;
+use std::ops::Range;
+use std::convert::TryFrom;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Mutex, PoisonError};
+use std::thread;
+use crate::flunk;
+use crate::history::History;
+use crate::ignore::{self, Ignore};
+use proptest::prelude::RngCore;
+use proptest::prop_oneof;
+use proptest::strategy::{BoxedStrategy, Strategy, ValueTree};
+use proptest::test_runner::{Config as ProptestConfig, TestCaseError, TestError, TestRunner};
 use humantime::format_duration;
 
 #[derive(Debug)]
@@ -23,53 +43,310 @@ pub struct Project {
     pub dir: PathBuf,
     source_dir: PathBuf,
     pub target_dir: PathBuf,
+    pub cargo_bin: PathBuf,
+    pub cargo_args: Vec<String>,
     pub name: String,
     update: Update,
     pub has_run_at_least: bool,
-    pub surges: Vec<isize>,
-    test_idx: usize,
-    pub durations: Vec<Option<Duration>>,
     has_compile_fail: bool,
     pub features: Option<Vec<String>>,
     workspace: PathBuf,
+    seed: u64,
+    // Selects the target of the launcher in a cargo command, if it was found.
+    launcher: Option<String>,
+    jobs: usize,
+    fail_fast: bool,
+    quiet: bool,
+    bless: bool,
+    explore: bool,
+    pub timeout: Option<Duration>,
+    settle: Duration,
+    warn_margin: Option<f64>,
+    partition: Option<Partition>,
+    require_flunk_coverage: bool,
+    retries: usize,
+    log_dir: Option<PathBuf>,
+    timings: Timings,
+    run_dir: Option<PathBuf>,
+    isolated_tmp: bool,
+    path_rules: Vec<PathRule>,
+    // Temporary directories of failed runs, left for inspection.
+    kept_tmp: Mutex<Vec<PathBuf>>,
+    pub target: Option<String>,
+    pub profile: Option<String>,
+    diagnostic_checks: Vec<DiagnosticCheck>,
+    history: Option<History>,
 }
 
 impl Runner {
     pub fn run(&mut self) {
-        let mut tests = expand_globs(&self.tests);
-        filter(&mut tests);
+        let exit_codes = crate::env::exit_codes()
+            .unwrap_or_else(|err| prepare_failed(err, false))
+            .unwrap_or(self.exit_codes);
+        let report = self.collect();
 
-        let mut project = self.prepare(&tests).unwrap_or_else(|err| {
-            message::prepare_fail(err);
+        if report.setup_failure.is_some() {
+            if exit_codes {
+                std::process::exit(report::EXIT_INFRA);
+            }
             panic!("tests failed");
-        });
+        }
+        let exit_code = report.exit_code();
+        if exit_codes && exit_code != 0 {
+            std::process::exit(exit_code);
+        }
+        if report.failed() > 0 && report.suite != "kaos-tests" {
+            // Each failure was reported as it happened, the panic hook would
+            // only add a backtrace of the harness.
+            let failures = report.failures();
+            eprintln!("{}", failures);
+            std::panic::resume_unwind(Box::new(failures));
+        }
+    }
+
+    // Runs the tests and reports on them. A report without tests says why they
+    // couldn't be set up, after printing it.
+    pub fn collect(&mut self) -> RunReport {
+        let start = Instant::now();
+        match self.try_collect(start) {
+            Ok(report) => report,
+            Err(err) => {
+                let failure = err.to_string();
+                message::prepare_fail(err);
+                RunReport {
+                    seed: 0,
+                    tests: Vec::new(),
+                    mtbf: Vec::new(),
+                    skipped: 0,
+                    elapsed: start.elapsed(),
+                    setup_failure: Some(failure),
+                    suite: String::new(),
+                }
+            }
+        }
+    }
+
+    fn try_collect(&mut self, start: Instant) -> Result<RunReport> {
+        if let Some(level) = crate::env::verbose()? {
+            message::set_verbosity(level);
+        }
+        // Read first, `run_count` decides how many tests there are.
+        let settings = Settings::load(&source_dir()?)?;
+        let ignore = Ignore::load(Path::new(ignore::TESTS_DIR))?;
+        let mut tests = expand_globs(&with_settings(&self.tests, &settings), &ignore);
+        filter(&mut tests);
+        let unsupported = unsupported(&mut tests);
+        for test in &mut tests {
+            test.test.expected_output = self
+                .expected_outputs
+                .iter()
+                .rev()
+                .find(|(path, _)| *path == test.test.path)
+                .map(|(_, expected_file)| expected_file.clone());
+            // Variables of the test override the ones set for every test.
+            let env = std::mem::take(&mut test.test.env);
+            test.test.env = self.env.clone();
+            test.test.env.extend(env);
+        }
+
+        if crate::env::dry_run()? {
+            print!("\n\n");
+            for test in &unsupported {
+                message::unsupported_platform(&test.test);
+            }
+            for test in &tests {
+                message::planned(&test.name, &test.test);
+            }
+            message::dry_run(tests.len(), ProptestConfig::default().cases);
+            return Ok(RunReport {
+                seed: 0,
+                tests: Vec::new(),
+                mtbf: Vec::new(),
+                skipped: 0,
+                elapsed: start.elapsed(),
+                setup_failure: None,
+                suite: String::new(),
+            });
+        }
+
+        let mut project = self.prepare(&tests, &settings)?;
+        let dump_project = crate::env::dump_project()?;
+        if dump_project {
+            message::generated_project(&project.dir);
+        }
+        let watch = crate::env::watch()?;
+        let watched = if watch {
+            tests.iter().filter_map(ExpandedTest::again).collect()
+        } else {
+            Vec::new()
+        };
 
         print!("\n\n");
 
+        self.run_fixture(&project, Fixture::Setup)?;
+        for test in &unsupported {
+            message::unsupported_platform(&test.test);
+        }
+        preflight(&mut tests, &project);
+
         let len = tests.len();
-        let mut failures = 0;
+        let mut results = Vec::new();
 
         if tests.is_empty() {
             message::no_tests_enabled();
-        } else {
+        } else if project.jobs <= 1 {
             for test in tests {
-                if let Err(err) = test.run(&mut project) {
-                    failures += 1;
-                    message::test_fail(err);
+                let result = run_with_hooks(test, &project, &self.hooks);
+                let failed = !result.passed;
+                results.push(result);
+                if failed && project.fail_fast {
+                    break;
                 }
             }
+        } else {
+            results = run_parallel(tests, &project, &self.hooks);
         }
 
+        let failures = results.iter().filter(|result| !result.passed).count();
+        let passed = results.len() - failures;
+        let skipped = len - results.len();
+
+        if skipped > 0 {
+            message::skipped_after_failure(skipped);
+        }
+        let skipped = skipped + unsupported.len();
+
         print!("\n\n");
 
-        if failures > 0 && project.name != "kaos-tests" {
-            panic!("{} of {} tests failed", failures, len);
+        let teardown = self.run_fixture(&project, Fixture::Teardown).err();
+        if teardown.is_some() {
+            print!("\n\n");
+        }
+
+        if let Some(mut history) = project.history.take() {
+            history.record(&results);
+            if let Err(err) = history.save(&history_path(&project)) {
+                message::report_fail(err);
+            }
+        }
+
+        let mtbf = report::mtbf(&results);
+        for entry in &mtbf {
+            message::mtbf(entry);
+        }
+        if !mtbf.is_empty() {
+            println!();
+        }
+
+        let report_path = self.report.clone().or(settings.report);
+        let junit = crate::env::junit_path()
+            .or_else(|| self.junit.clone())
+            .or(settings.junit);
+        if report_path.is_some() || junit.is_some() {
+            let report = Report {
+                seed: project.seed,
+                tests: &results,
+                mtbf: &mtbf,
+            };
+            if let Some(path) = &report_path {
+                if let Err(err) = report.write_json(path) {
+                    message::report_fail(err);
+                }
+            }
+            if let Some(path) = &junit {
+                if let Err(err) = report.write_junit(path, &project.name) {
+                    message::report_fail(err);
+                }
+            }
+        }
+
+        if failures > 0 && project.has_compile_fail {
+            message::reproduce_with_seed(project.seed);
+        }
+
+        let kept_tmp = project.kept_tmp.get_mut().unwrap_or_else(PoisonError::into_inner);
+        if !kept_tmp.is_empty() {
+            message::kept_tmp(kept_tmp);
+        }
+
+        if len + unsupported.len() > 0 {
+            message::summary(passed, failures, skipped);
+            message::timings(start.elapsed(), &project.timings, project.jobs);
         }
+        events::emit(Event::Finished {
+            passed,
+            failed: failures,
+            skipped,
+        });
+
+        if watch {
+            self.watch(watched, &project);
+        }
+        // Failed runs keep it, to look into what they were built from.
+        if self.discard_generated && !dump_project && failures == 0 {
+            let _ = fs::remove_dir_all(&project.dir);
+        }
+        Ok(RunReport {
+            seed: project.seed,
+            tests: results,
+            mtbf,
+            skipped,
+            elapsed: start.elapsed(),
+            setup_failure: teardown.map(|err| {
+                let failure = err.to_string();
+                message::prepare_fail(err);
+                failure
+            }),
+            suite: project.name.clone(),
+        })
     }
 
-    fn prepare(&self, tests: &[ExpandedTest]) -> Result<Project> {
-        let metadata = cargo::metadata()?;
-        let target_dir = metadata.target_directory;
+    // Reruns the tests affected by every change to the tests, the sources or
+    // the manifest of the crate, until interrupted. A change to a test file
+    // affects its own tests, any other change, like one to a helper module
+    // next to the tests, every test.
+    fn watch(&self, tests: Vec<ExpandedTest>, project: &Project) -> ! {
+        let paths = tests
+            .iter()
+            .map(|test| project.source_dir.join(&test.test.path))
+            .collect::<Vec<_>>();
+        let roots = watch_roots(&project.source_dir, &paths);
+        let mut snapshot = Snapshot::take(&roots);
+        loop {
+            message::watching(&roots);
+            let changed = snapshot.wait(&roots);
+            let others_changed = changed.iter().any(|path| !paths.contains(path));
+            let affected = tests
+                .iter()
+                .zip(&paths)
+                .filter(|(_, path)| others_changed || changed.contains(path))
+                .map(|(test, _)| test)
+                .filter_map(ExpandedTest::again)
+                .collect::<Vec<_>>();
+            if affected.is_empty() {
+                continue;
+            }
+
+            message::rerunning(&changed, affected.len());
+            let results = affected
+                .into_iter()
+                .map(|test| run_with_hooks(test, project, &self.hooks))
+                .collect::<Vec<_>>();
+            let failures = results.iter().filter(|result| !result.passed).count();
+            print!("\n\n");
+            message::summary(results.len() - failures, failures, 0);
+        }
+    }
+
+    fn prepare(&self, tests: &[ExpandedTest], settings: &Settings) -> Result<Project> {
+        crate::env::color()?;
+
+        let cargo_bin = match &self.cargo_bin {
+            Some(bin) => cargo::find_bin(bin)?,
+            None => cargo::default_bin(),
+        };
+        let metadata = cargo::metadata(&cargo_bin, &self.cargo_args)?;
+        let target_dir = crate::env::cargo_target_dir().unwrap_or(metadata.target_directory);
         let workspace = metadata.workspace_root;
 
         let crate_name = env::var("CARGO_PKG_NAME").map_err(Error::PkgName)?;
@@ -83,39 +360,101 @@ impl Runner {
             }
         }
 
-        let surges: Vec<isize> =
-            tests.iter().map(|t| t.test.max_surge).collect();
-
-        let mut static_durations: Vec<Option<Duration>> =
-            vec![None; surges.len()];
-
-        surges
+        let source_dir = source_dir()?;
+        let launcher = metadata
+            .packages
             .iter()
-            .position(|&e| e == !0)
-            .map(|i| static_durations[i] = tests[i].test.duration);
+            .find(|package| package.manifest_path.parent() == Some(source_dir.as_path()))
+            .and_then(|package| launcher_target(&package.targets, &env::current_exe().ok()?));
 
-        let source_dir = env::var_os("CARGO_MANIFEST_DIR")
-            .map(PathBuf::from)
-            .ok_or(Error::ProjectDir)?;
+        let target = match &self.target_crate {
+            Some(name) => metadata
+                .packages
+                .iter()
+                .find(|package| package.name == *name)
+                .and_then(|package| package.manifest_path.parent())
+                .map(|dir| TargetCrate {
+                    name: name.clone(),
+                    dir: dir.to_owned(),
+                })
+                .ok_or_else(|| Error::TargetCrate(name.clone()))?,
+            None => TargetCrate {
+                name: crate_name.clone(),
+                dir: source_dir.clone(),
+            },
+        };
 
         let features = features::find();
 
+        let jobs = crate::env::jobs()?.or(self.jobs).or(settings.jobs).unwrap_or(1);
+        let fail_fast = crate::env::fail_fast()?.unwrap_or(self.fail_fast);
+
+        let seed = match crate::env::seed()? {
+            Some(seed) => seed,
+            None => self
+                .seed
+                .or(settings.seed)
+                .unwrap_or_else(|| TestRunner::default().rng().next_u64()),
+        };
+
+        // Relative to the crate under test rather than wherever cargo runs it.
+        let run_dir = self.run_dir.as_ref().map(|dir| source_dir.join(dir));
+
         let mut project = Project {
             dir: path!(target_dir / "tests" / crate_name),
             source_dir,
             target_dir,
+            cargo_bin,
+            cargo_args: self.cargo_args.clone(),
             name: format!("{}-tests", crate_name),
             update: Update::env()?,
-            surges,
-            test_idx: 0,
-            durations: static_durations,
+            bless: crate::env::bless()?,
+            explore: self.explore,
             has_run_at_least,
             has_compile_fail,
             features,
             workspace,
+            seed,
+            launcher,
+            jobs,
+            fail_fast,
+            quiet: crate::env::quiet()?,
+            timeout: self.timeout,
+            settle: self.settle,
+            warn_margin: self.warn_margin,
+            partition: self.partition.clone(),
+            require_flunk_coverage: self.require_flunk_coverage,
+            retries: self.retries,
+            log_dir: self.log_dir.clone(),
+            run_dir,
+            isolated_tmp: self.isolated_tmp,
+            path_rules: self.path_rules.clone(),
+            kept_tmp: Mutex::new(Vec::new()),
+            target: self.target.clone(),
+            profile: self.profile.clone(),
+            diagnostic_checks: self.diagnostic_checks.clone(),
+            timings: Timings::default(),
+            history: None,
         };
 
-        let manifest = self.make_manifest(crate_name, &project, tests)?;
+        if let Some(path) = crate::env::events_path() {
+            events::open(&path)?;
+        }
+
+        let leftovers = process::leftovers(&cargo::running_dir(&project));
+        if !leftovers.is_empty() {
+            message::leftover_processes(&leftovers);
+        }
+
+        if let Some(log_dir) = &project.log_dir {
+            fs::create_dir_all(log_dir).map_err(|err| Error::WriteLog(log_dir.clone(), err))?;
+        }
+
+        if !self.without_history {
+            project.history = Some(History::load(&history_path(&project)));
+        }
+
+        let manifest = self.make_manifest(&target, &project, tests)?;
         let manifest_toml = toml::to_string(&manifest)?;
 
         let config = self.make_config();
@@ -123,6 +462,7 @@ impl Runner {
 
         match &mut project.features {
             Some(enabled_features) => {
+                // Excluded features aren't in the manifest either.
                 enabled_features.retain(|feature| manifest.features.contains_key(feature));
                 // enabled_features.push("fail/failpoints".into());
             }
@@ -136,60 +476,86 @@ impl Runner {
         fs::write(path!(project.dir / "Cargo.toml"), manifest_toml)?;
         fs::write(path!(project.dir / "main.rs"), b"fn main() {}\n")?;
 
-        cargo::build_dependencies(&project)?;
+        project
+            .timings
+            .time(Phase::Dependencies, || cargo::build_dependencies(&project))?;
 
         Ok(project)
     }
 
     fn make_manifest(
         &self,
-        crate_name: String,
+        target: &TargetCrate,
         project: &Project,
         tests: &[ExpandedTest],
     ) -> Result<Manifest> {
         let source_manifest = dependencies::get_manifest(&project.source_dir);
         let workspace_manifest = dependencies::get_workspace_manifest(&project.workspace);
+        let mut crates = vec![(target.name.clone(), target.dir.clone())];
+        for (name, path) in &self.extra_crates {
+            let dir = project.source_dir.join(path);
+            if !dir.join("Cargo.toml").is_file() {
+                return Err(Error::ExtraCrate(name.clone(), dir));
+            }
+            crates.push((name.clone(), dir));
+        }
 
-        let features = source_manifest
-            .features
-            .keys()
-            .map(|feature| {
-                let enable = format!("{}/{}", crate_name, feature);
-                (feature.clone(), vec![enable])
-            })
-            .collect();
+        let edition = match &self.edition {
+            Some(edition) => {
+                Edition::parse(edition).ok_or_else(|| Error::Edition(edition.clone()))?
+            }
+            None => source_manifest.package.edition,
+        };
+
+        // A feature enables the features of that name of every crate having
+        // one. Extra crates keep their default features, whatever the target
+        // crate's are.
+        let mut features = Map::<String, Vec<String>>::new();
+        for (i, (name, dir)) in crates.iter().enumerate() {
+            let crate_features = dependencies::get_manifest(dir).features;
+            for feature in crate_features.keys() {
+                let extra_default = i > 0 && feature == "default";
+                if !extra_default && !self.exclude_features.contains(feature) {
+                    let enable = format!("{}/{}", name, feature);
+                    features.entry(feature.clone()).or_default().push(enable);
+                }
+            }
+        }
 
         let mut manifest = Manifest {
             package: Package {
                 name: project.name.clone(),
                 version: "0.0.0".to_owned(),
-                edition: source_manifest.package.edition,
+                edition,
                 publish: false,
             },
             features,
             dependencies: Map::new(),
             bins: Vec::new(),
             workspace: Some(Workspace {}),
-            // Within a workspace, only the [patch] and [replace] sections in
-            // the workspace root's Cargo.toml are applied by Cargo.
+            // Within a workspace, only the [patch], [replace] and [profile]
+            // sections in the workspace root's Cargo.toml are applied by Cargo.
             patch: workspace_manifest.patch,
             replace: workspace_manifest.replace,
+            profile: workspace_manifest.profile,
         };
 
         manifest.dependencies.extend(source_manifest.dependencies);
         manifest
             .dependencies
             .extend(source_manifest.dev_dependencies);
-        manifest.dependencies.insert(
-            crate_name,
-            Dependency {
-                version: None,
-                path: Some(project.source_dir.clone()),
-                default_features: false,
-                features: Vec::new(),
-                rest: Map::new(),
-            },
-        );
+        for (i, (name, dir)) in crates.into_iter().enumerate() {
+            manifest.dependencies.insert(
+                name,
+                Dependency {
+                    version: None,
+                    path: Some(dir),
+                    default_features: i > 0,
+                    features: Vec::new(),
+                    rest: Map::new(),
+                },
+            );
+        }
 
         manifest.bins.push(Bin {
             name: Name(project.name.to_owned()),
@@ -205,9 +571,52 @@ impl Runner {
             }
         }
 
+        for (fixture, path) in self.fixtures() {
+            manifest.bins.push(Bin {
+                name: fixture.name(),
+                path: project.source_dir.join(path),
+            });
+        }
+
         Ok(manifest)
     }
 
+    fn fixtures(&self) -> Vec<(Fixture, &Path)> {
+        let setup = self.setup_bin.as_deref().map(|path| (Fixture::Setup, path));
+        let teardown = self.teardown_bin.as_deref().map(|path| (Fixture::Teardown, path));
+        setup.into_iter().chain(teardown).collect()
+    }
+
+    // Builds and runs the setup or teardown binary, if there is one.
+    fn run_fixture(&self, project: &Project, fixture: Fixture) -> Result<()> {
+        let path = match self.fixtures().into_iter().find(|(f, _)| *f == fixture) {
+            Some((_, path)) => path.to_owned(),
+            None => return Ok(()),
+        };
+        let name = fixture.name();
+        message::begin_fixture(fixture.as_str(), &path);
+        check_exists(&path)?;
+
+        let built = project
+            .timings
+            .time(Phase::Build, || cargo::build_bin(project, &name, &[]))?;
+        if !built.status.success() {
+            let variations = diagnostics(project, &name, built.stderr);
+            message::failed_to_build(&normalize::classify(variations.preferred()));
+            return Err(Error::FixtureFailed(fixture.as_str(), path));
+        }
+
+        let output = project.timings.time(Phase::Run, || {
+            cargo::run_test(project, &name, &[], &self.env, project.run_dir.as_deref(), None)
+        })?;
+        message::output("", &output);
+        if output.status.success() {
+            Ok(())
+        } else {
+            Err(Error::FixtureFailed(fixture.as_str(), path))
+        }
+    }
+
     fn make_config(&self) -> Config {
         Config {
             build: Build {
@@ -218,93 +627,251 @@ impl Runner {
 }
 
 impl Test {
-    fn run(&self, project: &mut Project, name: &Name) -> Result<()> {
+    fn run(
+        &self,
+        project: &Project,
+        name: &Name,
+        index: usize,
+        attempt: usize,
+        prebuilt: Option<Output>,
+        runs: &mut Vec<RunResult>,
+    ) -> Result<()> {
         let show_expected = project.has_run_at_least && project.has_compile_fail;
-        let mut runner = TestRunner::default();
+        // Every expanded test samples its own surges, reproducibly from the seed.
+        let rng = flunk::rng_from_seed(project.seed.wrapping_add(index as u64));
+        let mut runner = TestRunner::new_with_rng(ProptestConfig::default(), rng);
 
-        let max_surge = project.surges[project.test_idx];
+        if let Expected::Chaotic = self.expected {
+            let range = self.surge_range()?;
+            let observed = RefCell::new(Vec::new());
+            // Flunk points activated across the runs that got the service to exit.
+            let activated = Cell::new(None);
+            // A surge pinned from the environment replays that single run.
+            let pinned = crate::env::surge()?
+                .map(|surge| match isize::try_from(surge) {
+                    Ok(pinned) if range.contains(&pinned) => Ok(pinned),
+                    _ => Err(Error::SurgeOutOfRange(surge, range.clone())),
+                })
+                .transpose()?;
+            let cases = match self.convergence {
+                _ if pinned.is_some() => 1,
+                // Soaking runs for as long as it takes, not for a count.
+                _ if self.soak.is_some() => 0,
+                // Proptest counts cases in a `u32`, a larger cap runs as many
+                // as it can count.
+                Some(convergence) => u32::try_from(convergence.max_runs).unwrap_or(u32::MAX),
+                None => runner.config().cases,
+            };
+            let run = Cell::new(0);
+            let boundary = project
+                .history
+                .as_ref()
+                .and_then(|history| history.boundary(&self.path));
 
-        if max_surge != !0 {
-            project.test_idx += 1;
+            // Only the surge differs between cases, it reaches the service at
+            // runtime, so the binary is built once for all of them.
+            check_exists(&self.path)?;
+            let built = build(project, name, &self.features, attempt, prebuilt)?;
+            if !built.status.success() {
+                message::begin_test(self, show_expected);
+                let variations = diagnostics(project, name, built.stderr);
+                message::failed_to_build(&normalize::classify(variations.preferred()));
+                return Err(Error::CargoFail {
+                    stderr: variations.preferred().to_owned(),
+                    status: built.status,
+                });
+            }
+            if let Err(err) = check_diagnostics(project, &diagnostics(project, name, built.stderr.clone())) {
+                message::begin_test(self, show_expected);
+                return Err(err);
+            }
 
-            let res = runner.run(&(0..max_surge), |v| {
-                let duration = Duration::from_millis(v.try_into().unwrap());
+            // Proptest samples the range lazily, no surge is drawn up front.
+            let strategy = surges(range.clone(), boundary, self.distribution);
+            let run_case = |v: isize| {
+                // Surges are sampled from a range starting at zero or above.
+                let duration = match self.slo {
+                    Some(slo) => slo.min_duration,
+                    None => Duration::from_millis(v as u64),
+                };
+                // The first run has nothing to settle from.
+                if run.get() > 0 && project.settle > Duration::from_secs(0) {
+                    project
+                        .timings
+                        .time(Phase::Settle, || thread::sleep(project.settle));
+                }
                 let now = Instant::now();
 
+                run.set(run.get() + 1);
+                if !project.quiet {
+                    match self.soak {
+                        Some(_) => message::soak_progress(run.get(), v),
+                        None => message::progress(run.get(), cases, v),
+                    }
+                }
                 message::begin_test(self, show_expected);
                 check_exists(&self.path).unwrap();
 
-                let output = cargo::build_test(project, name).unwrap();
-                let success = output.status.success();
-                let stdout = output.stdout;
-                let stderr = normalize::diagnostics(
-                    output.stderr,
-                    Context {
-                        krate: &name.0,
-                        source_dir: &project.source_dir,
-                        workspace: &project.workspace,
-                    },
-                );
+                let stdout = built.stdout.clone();
+                let stderr = diagnostics(project, name, built.stderr.clone());
 
                 let check = match self.expected {
                     Expected::Available => Test::check_available,
-                    // TODO: separate cases
-                    Expected::Chaotic => Test::check_available,
+                    Expected::Chaotic => Test::check_chaotic,
                 };
 
-                let res = check(self, project, name, success, stdout, stderr);
+                let res = check(self, project, name, Some(v), built.status, stdout, stderr);
                 let elapsed = now.elapsed();
+                let passed = res.is_ok() && elapsed >= duration;
+                observed.borrow_mut().push(RunResult {
+                    surge: Some(v),
+                    elapsed,
+                    passed,
+                });
+                events::emit(Event::RunCompleted {
+                    test: &name.0,
+                    surge: Some(v),
+                    elapsed_ms: elapsed.as_millis() as u64,
+                    passed,
+                });
+                if let Ok(observed) = &res {
+                    activated.set(Some(activated.get().unwrap_or(0) + observed.activated_flunks));
+                }
                 if elapsed < duration {
+                    let latency = res.as_ref().map_or(Duration::from_secs(0), |o| o.injected_latency);
                     Err(TestCaseError::Fail(
                         format!(
-                            "chaos test failed: availability is low. Expected at least: {}, Found: {}",
-                            format_duration(duration).to_string(),
-                            format_duration(elapsed).to_string()
+                            "chaos test failed: {}",
+                            low_availability(duration, elapsed, latency)
                         ).into()
                     ))
                 } else {
-                    res.map_err(|e| TestCaseError::Fail(format!("{}", e).into()))
+                    res.map(drop).map_err(|e| TestCaseError::Fail(format!("{}", e).into()))
                 }
-            })?;
+            };
+            // The smallest failing surge proptest shrank down to, every case
+            // on the way was checked against the service like the first one.
+            let mut minimal = None;
+            let res = if let Some(surge) = pinned {
+                run_case(surge).map_err(|err| {
+                    let reason = match err {
+                        TestCaseError::Fail(reason) | TestCaseError::Reject(reason) => reason,
+                    };
+                    Error::from(TestError::Fail(reason, surge))
+                })
+            } else if project.explore {
+                for _ in 0..cases {
+                    let _ = run_case(strategy.new_tree(&mut runner).unwrap().current());
+                }
+                explored(&self.path, &observed.borrow())
+            } else if let Some(slo) = self.slo {
+                // The objective is over the whole sweep, no single run fails it.
+                for _ in 0..cases {
+                    let _ = run_case(strategy.new_tree(&mut runner).unwrap().current());
+                }
+                check_slo(&self.path, slo, &observed.borrow())
+            } else if let Some(total) = self.soak {
+                let start = Instant::now();
+                while start.elapsed() < total {
+                    let _ = run_case(strategy.new_tree(&mut runner).unwrap().current());
+                }
+                check_soaked(&self.path, start.elapsed(), &observed.borrow())
+            } else if let Some(convergence) = self.convergence {
+                for _ in 0..cases {
+                    let _ = run_case(strategy.new_tree(&mut runner).unwrap().current());
+                    if report::converged(&observed.borrow(), convergence.tolerance) {
+                        break;
+                    }
+                }
+                check_converged(&self.path, convergence, &observed.borrow())
+            } else {
+                let res = runner.run(&strategy, run_case);
+                if let Err(TestError::Fail(_, surge)) = &res {
+                    minimal = Some(*surge);
+                }
+                res.map_err(Error::from)
+            };
+            let observed = observed.into_inner();
+            if activated.get() == Some(0) {
+                message::no_flunk_points(&self.path);
+            }
+            if let Some(stats) = ElapsedStats::of(&observed) {
+                message::elapsed_stats(&self.path, &stats);
+                // Runs only fail when the service exits before its surge.
+                // Below the shortest run that can't happen, so the sweep
+                // says nothing about the service.
+                if self.slo.is_none() && stats.min > Duration::from_millis(range.end as u64) {
+                    message::surge_below_runtime(&self.path, range.end, stats.min);
+                }
+            }
+            // About ten lines show where the service stops coping.
+            let buckets = report::histogram(&observed, 10);
+            if !buckets.is_empty() {
+                message::histogram(&self.path, &buckets);
+            }
+            if let Some(surge) = minimal {
+                message::minimal_failing_surge(&self.path, surge);
+            }
+            if res.is_err() {
+                let failing = minimal.or_else(|| observed.iter().find(|run| !run.passed).and_then(|run| run.surge));
+                if let Some(surge) = failing {
+                    let launcher = project.launcher.as_deref();
+                    message::replay(&replay_command(project.seed, launcher, surge, &self.path));
+                }
+            }
+            runs.extend(observed);
+            res?;
 
-            Ok(res)
+            Ok(())
         } else {
-            let duration = project.durations[project.test_idx].unwrap();
+            let duration = self.duration.unwrap();
             let now = Instant::now();
 
             message::begin_test(self, show_expected);
             check_exists(&self.path).unwrap();
 
-            let output = cargo::build_test(project, name).unwrap();
-            let success = output.status.success();
+            // Availability includes building the test, so it builds again.
+            let output = build(project, name, &self.features, attempt, prebuilt)?;
+            let status = output.status;
             let stdout = output.stdout;
-            let stderr = normalize::diagnostics(
-                output.stderr,
-                Context {
-                    krate: &name.0,
-                    source_dir: &project.source_dir,
-                    workspace: &project.workspace,
-                },
-            );
+            let stderr = diagnostics(project, name, output.stderr);
+            if status.success() {
+                check_diagnostics(project, &stderr)?;
+            }
 
             let check = match self.expected {
+                Expected::Available if self.supervision.is_some() => Test::check_supervised,
                 Expected::Available => Test::check_available,
-                // TODO: separate cases
-                Expected::Chaotic => Test::check_available,
+                Expected::Chaotic => Test::check_chaotic,
             };
 
-            let res = check(self, project, name, success, stdout, stderr);
+            let res = check(self, project, name, None, status, stdout, stderr);
             let elapsed = now.elapsed();
+            let too_slow = self.max_duration.filter(|max| elapsed > *max);
+            let passed = res.is_ok() && elapsed >= duration && too_slow.is_none();
+            runs.push(RunResult {
+                surge: None,
+                elapsed,
+                passed,
+            });
+            events::emit(Event::RunCompleted {
+                test: &name.0,
+                surge: None,
+                elapsed_ms: elapsed.as_millis() as u64,
+                passed,
+            });
+            if passed {
+                if let Some(margin) = project.warn_margin.filter(|margin| thin_margin(duration, elapsed, *margin)) {
+                    message::thin_margin(&self.path, duration, elapsed, margin);
+                }
+            }
             if elapsed < duration {
-                Err(Error::ChaosTestFailed(
-                    format!(
-                        "availability is low. Expected at least: {}, Found: {}",
-                        format_duration(duration).to_string(),
-                        format_duration(elapsed).to_string()
-                    )
-                ))
+                let latency = res.as_ref().map_or(Duration::from_secs(0), |o| o.injected_latency);
+                Err(Error::ChaosTestFailed(low_availability(duration, elapsed, latency)))
+            } else if let Some(max) = too_slow {
+                res.and(Err(Error::TooSlow(max, elapsed)))
             } else {
-                res
+                res.map(drop)
             }
         }
     }
@@ -313,27 +880,569 @@ impl Test {
         &self,
         project: &Project,
         name: &Name,
-        success: bool,
+        surge: Option<isize>,
+        status: ExitStatus,
         build_stdout: Vec<u8>,
         variations: Variations,
-    ) -> Result<()> {
-        let preferred = variations.preferred();
-        if !success {
-            message::failed_to_build(preferred);
-            return Err(Error::CargoFail);
+    ) -> Result<Observed> {
+        let diagnostics = normalize::classify(variations.preferred());
+        if !status.success() {
+            message::failed_to_build(&diagnostics);
+            return Err(Error::CargoFail {
+                stderr: variations.preferred().to_owned(),
+                status,
+            });
         }
+        let warnings = diagnostics.warnings.as_str();
 
-        let mut output = cargo::run_test(project, name)?;
+        let mut output = run_service(project, name, &self.features, &self.env, surge, warnings)?;
+        let service_stdout = output.stdout.clone();
         output.stdout.splice(..0, build_stdout);
-        message::output(preferred, &output);
+        if process::killed(&output) {
+            message::out_of_memory(warnings, &output);
+            return Err(Error::OutOfMemory);
+        }
+        message::output(warnings, &output);
         if output.status.success() {
+            check_flunk_coverage(project, &output)?;
+            self.check_output(project, &service_stdout)?;
+            Ok(Observed::from(&output))
+        } else {
+            Err(Error::RunFailed {
+                stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+                status: output.status,
+            })
+        }
+    }
+
+    // Injected flunks are expected to fire during chaotic runs. A service that
+    // catches the flunk panic and keeps going exits cleanly and passes; only a
+    // crash the service could not recover from is reported as a failure.
+    fn check_chaotic(
+        &self,
+        project: &Project,
+        name: &Name,
+        surge: Option<isize>,
+        status: ExitStatus,
+        build_stdout: Vec<u8>,
+        variations: Variations,
+    ) -> Result<Observed> {
+        let diagnostics = normalize::classify(variations.preferred());
+        if !status.success() {
+            message::failed_to_build(&diagnostics);
+            return Err(Error::CargoFail {
+                stderr: variations.preferred().to_owned(),
+                status,
+            });
+        }
+        let warnings = diagnostics.warnings.as_str();
+
+        let mut output = run_service(project, name, &self.features, &self.env, surge, warnings)?;
+        let service_stdout = output.stdout.clone();
+        output.stdout.splice(..0, build_stdout);
+        if output.status.success() {
+            message::output(warnings, &output);
+            let fired = normalize::fired_flunks(&output.stderr);
+            if !fired.is_empty() {
+                message::recovered_from_flunk(&fired);
+            }
+            check_flunk_coverage(project, &output)?;
+            self.check_output(project, &service_stdout)?;
+            Ok(Observed::from(&output))
+        } else if process::killed(&output) {
+            message::out_of_memory(warnings, &output);
+            Err(Error::OutOfMemory)
+        } else {
+            let flunk = normalize::fatal_flunk(&output.stderr);
+            message::crashed(warnings, &output, flunk.as_deref());
+            Err(Error::Crashed(normalize::backtrace(&output.stderr).1))
+        }
+    }
+
+    // Restarts the service whenever it crashes, as a supervisor would, until an
+    // incarnation exits cleanly or the restarts run out. The downtime is the
+    // time spent in the incarnations that crashed.
+    fn check_supervised(
+        &self,
+        project: &Project,
+        name: &Name,
+        surge: Option<isize>,
+        status: ExitStatus,
+        build_stdout: Vec<u8>,
+        variations: Variations,
+    ) -> Result<Observed> {
+        let supervision = self.supervision.unwrap();
+        let diagnostics = normalize::classify(variations.preferred());
+        if !status.success() {
+            message::failed_to_build(&diagnostics);
+            return Err(Error::CargoFail {
+                stderr: variations.preferred().to_owned(),
+                status,
+            });
+        }
+        let warnings = diagnostics.warnings.as_str();
+
+        let started = Instant::now();
+        let mut crashes = Vec::new();
+        loop {
+            let downtime = started.elapsed();
+            let mut env = self.env.clone();
+            env.insert(crate::env::RESTART.to_owned(), crashes.len().to_string());
+            let mut output = run_service(project, name, &self.features, &env, surge, warnings)?;
+            if output.status.success() {
+                let service_stdout = output.stdout.clone();
+                output.stdout.splice(..0, build_stdout);
+                message::output(warnings, &output);
+                if !crashes.is_empty() {
+                    message::restarted(&crashes, downtime);
+                }
+                check_flunk_coverage(project, &output)?;
+                self.check_output(project, &service_stdout)?;
+                if downtime > supervision.budget {
+                    return Err(Error::Downtime(supervision.budget, downtime));
+                }
+                return Ok(Observed::from(&output));
+            }
+            crashes.push(normalize::fatal_flunk(&output.stderr));
+            if crashes.len() > supervision.restarts {
+                output.stdout.splice(..0, build_stdout);
+                message::output(warnings, &output);
+                if supervision.restarts > 0 {
+                    message::restarted(&crashes[..supervision.restarts], started.elapsed());
+                }
+                return Err(Error::NotRecovered(supervision.restarts));
+            }
+        }
+    }
+
+    // Surges reach the service in milliseconds as an `isize`, a budget past
+    // that can't be sampled on this platform.
+    fn surge_range(&self) -> Result<Range<isize>> {
+        match (isize::try_from(self.min_surge), isize::try_from(self.max_surge)) {
+            (Ok(min_surge), Ok(max_surge)) => Ok(min_surge..max_surge),
+            _ => Err(Error::SurgeTooLarge(self.max_surge)),
+        }
+    }
+
+    // Compares the service's stdout against the expected output file, if the
+    // test has one, or replaces the file when blessing.
+    fn check_output(&self, project: &Project, stdout: &[u8]) -> Result<()> {
+        let expected_file = match &self.expected_output {
+            Some(expected_file) => expected_file,
+            None => return Ok(()),
+        };
+        let actual = normalize::rewrite_paths(&normalize::trim(stdout), &project.path_rules);
+
+        if project.bless || project.update == Update::Overwrite {
+            message::overwrite_output(expected_file, &actual);
+            return fs::write(expected_file, &actual)
+                .map_err(|err| Error::Open(expected_file.clone(), err));
+        }
+
+        let expected = match fs::read_to_string(expected_file) {
+            Ok(expected) => expected.replace("\r\n", "\n"),
+            Err(_) => return Err(Error::MissingOutput(expected_file.clone())),
+        };
+        if normalize::trim(&expected) == actual {
             Ok(())
         } else {
-            Err(Error::RunFailed)
+            message::mismatch(&expected, &actual);
+            Err(Error::Mismatch)
+        }
+    }
+}
+
+// Runs the user's checks of the diagnostics of a test that built.
+fn check_diagnostics(project: &Project, variations: &Variations) -> Result<()> {
+    for check in &project.diagnostic_checks {
+        (check.0)(variations).map_err(Error::DiagnosticsRejected)?;
+    }
+    Ok(())
+}
+
+fn diagnostics(project: &Project, name: &Name, stderr: Vec<u8>) -> Variations {
+    normalize::diagnostics(
+        stderr,
+        Context {
+            krate: &name.0,
+            source_dir: &project.source_dir,
+            workspace: &project.workspace,
+            paths: &project.path_rules,
+        },
+    )
+}
+
+// Builds every test before any of them runs, so that one that doesn't compile
+// is reported as such up front instead of through the runs of its sweep. Tests
+// that fail to build are skipped, the others reuse their build. The tests are
+// cleaned once, so that every build reports its warnings, and never after:
+// cleaning again would throw away the builds of the others, including the
+// ones of parallel jobs.
+fn preflight(tests: &mut [ExpandedTest], project: &Project) {
+    project.timings.time(Phase::Build, || cargo::clean_tests(project));
+    let mut failed = 0;
+    for test in tests.iter_mut().filter(|test| test.error.is_none()) {
+        if check_exists(&test.test.path).is_err() {
+            continue;
+        }
+        let built = project.timings.time(Phase::Build, || {
+            cargo::build_bin(project, &test.name, &test.test.features)
+        });
+        match built {
+            Ok(output) if output.status.success() => test.prebuilt = Some(output),
+            Ok(output) => {
+                let show_expected = false;
+                message::begin_test(&test.test, show_expected);
+                let variations = diagnostics(project, &test.name, output.stderr);
+                message::failed_to_build(&normalize::classify(variations.preferred()));
+                test.error = Some(Error::NotBuilt);
+                failed += 1;
+            }
+            Err(err) => test.error = Some(err),
+        }
+    }
+    if failed > 0 {
+        message::not_built(failed);
+    }
+}
+
+// Builds the test binary on the first attempt. Retries reuse it, `cargo run`
+// won't rebuild it as nothing changed. A test built by the preflight only has
+// its build brought up to date, keeping the warnings reported back then.
+fn build(
+    project: &Project,
+    name: &Name,
+    features: &[String],
+    attempt: usize,
+    prebuilt: Option<Output>,
+) -> Result<Output> {
+    if attempt > 1 {
+        return Ok(Output {
+            status: ExitStatus::default(),
+            stdout: Vec::new(),
+            stderr: Vec::new(),
+        });
+    }
+    let built = project
+        .timings
+        .time(Phase::Build, || cargo::build_bin(project, name, features))?;
+    match prebuilt {
+        Some(prebuilt) if built.status.success() => Ok(prebuilt),
+        _ => Ok(built),
+    }
+}
+
+// Runs the service, surfacing what it printed before it was killed if it
+// exceeded the time limit.
+fn run_service(
+    project: &Project,
+    name: &Name,
+    features: &[String],
+    env: &Map<String, String>,
+    surge: Option<isize>,
+    warnings: &str,
+) -> Result<Output> {
+    let tmp = if project.isolated_tmp {
+        Some(make_tmp(name)?)
+    } else {
+        None
+    };
+    let mut env = env.clone();
+    if let Some(tmp) = &tmp {
+        env.insert(crate::env::TMPDIR.to_owned(), tmp.to_string_lossy().into_owned());
+    }
+    let run_dir = tmp.as_deref().or(project.run_dir.as_deref());
+    // Lifted as it drops, once the service is gone.
+    let partitioned = project.partition.as_ref().map(Partition::install).transpose()?;
+
+    let result = project.timings.time(Phase::Run, || {
+        cargo::run_test(project, name, features, &env, run_dir, surge)
+    });
+    drop(partitioned);
+    if let Some(tmp) = &tmp {
+        match &result {
+            Ok(output) if output.status.success() => {
+                let _ = fs::remove_dir_all(tmp);
+            }
+            _ => project
+                .kept_tmp
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .push(tmp.clone()),
+        }
+    }
+    match &result {
+        Ok(output) => save_output(project, name, surge, &env, output),
+        Err(Error::Timeout(_, output)) => {
+            save_output(project, name, surge, &env, output);
+            message::output(warnings, output);
+        }
+        Err(_) => {}
+    }
+    result
+}
+
+// A directory no other run uses, even across parallel jobs and launchers.
+fn make_tmp(name: &Name) -> Result<PathBuf> {
+    static RUNS: AtomicUsize = AtomicUsize::new(0);
+    let run = RUNS.fetch_add(1, Ordering::SeqCst);
+    let dir = env::temp_dir()
+        .join("kaos")
+        .join(format!("{}-{}-{}", name.0, std::process::id(), run));
+    fs::create_dir_all(&dir).map_err(|err| Error::TmpDir(dir.clone(), err))?;
+    Ok(dir)
+}
+
+// Keeps the service output in the log directory. Failing to do so doesn't
+// fail the test.
+fn save_output(
+    project: &Project,
+    name: &Name,
+    surge: Option<isize>,
+    env: &Map<String, String>,
+    output: &Output,
+) {
+    let log_dir = match &project.log_dir {
+        Some(log_dir) => log_dir,
+        None => return,
+    };
+    let mut stem = match surge {
+        Some(surge) => format!("{}-{}", name.0, surge),
+        None => name.0.clone(),
+    };
+    // Every incarnation of a supervised service keeps its own logs.
+    if let Some(restart) = env.get(crate::env::RESTART) {
+        stem = format!("{}-restart{}", stem, restart);
+    }
+    for (extension, contents) in &[("stdout", &output.stdout), ("stderr", &output.stderr)] {
+        let path = log_dir.join(format!("{}.{}", stem, extension));
+        if let Err(err) = fs::write(&path, contents) {
+            message::report_fail(Error::WriteLog(path, err));
         }
     }
 }
 
+// Flags activated flunk points the service never reached, failing the test if
+// coverage is required, and inactive ones it did reach.
+fn check_flunk_coverage(project: &Project, output: &Output) -> Result<()> {
+    let inactive = normalize::inactive_flunks(&output.stderr);
+    if !inactive.is_empty() {
+        message::inactive_flunks(&inactive);
+    }
+
+    let unreached = normalize::unreached_flunks(&output.stderr);
+    if unreached.is_empty() {
+        Ok(())
+    } else if project.require_flunk_coverage {
+        Err(Error::UnreachedFlunks(unreached))
+    } else {
+        message::unreached_flunks(&unreached);
+        Ok(())
+    }
+}
+
+fn history_path(project: &Project) -> PathBuf {
+    path!(project.target_dir / "kaos-history.json")
+}
+
+// Surges to sample for a chaotic run, spread over the range by the test's
+// distribution. If an earlier invocation saw the test failing most samples land
+// near the smallest failing surge instead, to narrow down where the service
+// stops coping.
+fn surges(
+    range: Range<isize>,
+    boundary: Option<isize>,
+    distribution: Distribution,
+) -> BoxedStrategy<isize> {
+    match boundary {
+        Some(boundary) if range.contains(&boundary) => {
+            let spread = cmp::max((range.end - range.start) / 10, 1);
+            let near = cmp::max(boundary.saturating_sub(spread), range.start)
+                ..cmp::min(boundary.saturating_add(spread), range.end);
+            prop_oneof![3 => near, 1 => weighted(range, distribution)].boxed()
+        }
+        _ => weighted(range, distribution),
+    }
+}
+
+// Reports where the runs of an explored test failed, failing it if any did.
+fn explored(path: &Path, runs: &[RunResult]) -> Result<()> {
+    let region = Region::of(runs);
+    message::explored(path, region, runs.len());
+    match runs.iter().filter(|run| !run.passed).count() {
+        0 => Ok(()),
+        failures => Err(Error::ChaosTestFailed(format!(
+            "{} of {} explored runs failed",
+            failures,
+            runs.len()
+        ))),
+    }
+}
+
+// Reports how many runs a soaked test got through, failing it if any failed.
+fn check_soaked(path: &Path, elapsed: Duration, runs: &[RunResult]) -> Result<()> {
+    let failures = runs.iter().filter(|run| !run.passed).count();
+    message::soaked(path, runs.len(), failures, elapsed);
+    match failures {
+        0 => Ok(()),
+        failures => Err(Error::ChaosTestFailed(format!(
+            "{} of {} runs failed while soaking",
+            failures,
+            runs.len()
+        ))),
+    }
+}
+
+// Reports where the estimate of a sweep run until convergence ended up,
+// failing it if any run failed.
+fn check_converged(path: &Path, convergence: Convergence, runs: &[RunResult]) -> Result<()> {
+    let converged = report::converged(runs, convergence.tolerance);
+    message::convergence(path, report::estimate(runs), converged, runs.len());
+    match runs.iter().filter(|run| !run.passed).count() {
+        0 => Ok(()),
+        failures => Err(Error::ChaosTestFailed(format!(
+            "{} of {} runs failed",
+            failures,
+            runs.len()
+        ))),
+    }
+}
+
+// Fails the sweep when less than the objective's percentile of its runs stayed
+// up for its minimum duration.
+fn check_slo(path: &Path, slo: Slo, runs: &[RunResult]) -> Result<()> {
+    let uptime = match report::uptime_percentile(runs, slo.percentile) {
+        Some(uptime) => uptime,
+        None => return Ok(()),
+    };
+    message::slo(path, slo.percentile, uptime, slo.min_duration);
+    if uptime >= slo.min_duration {
+        Ok(())
+    } else {
+        let uptime = Duration::from_millis(uptime.as_millis() as u64);
+        Err(Error::SloMissed(slo.percentile, slo.min_duration, uptime))
+    }
+}
+
+// Maps uniform samples of `0.0..1.0` onto the range through the inverse of the
+// distribution's CDF. Shrinking the sample still shrinks the surge.
+fn weighted(range: Range<isize>, distribution: Distribution) -> BoxedStrategy<isize> {
+    let span = (range.end - range.start) as f64;
+    let start = range.start;
+    let end = range.end;
+    let weight = match distribution {
+        Distribution::Uniform => return range.boxed(),
+        Distribution::Exponential => |x: f64| -(1.0 - x * (1.0 - (-5.0f64).exp())).ln() / 5.0,
+        Distribution::FrontLoaded => |x: f64| x * x,
+    };
+    (0.0..1.0f64)
+        .prop_map(move |x| cmp::min(start + (weight(x) * span) as isize, end - 1))
+        .boxed()
+}
+
+// The crate whose code the tests exercise, by default the one hosting the launcher.
+struct TargetCrate {
+    name: String,
+    dir: PathBuf,
+}
+
+// What a run of the service reported back to the harness through its output.
+struct Observed {
+    injected_latency: Duration,
+    activated_flunks: usize,
+}
+
+impl From<&Output> for Observed {
+    fn from(output: &Output) -> Self {
+        Observed {
+            injected_latency: normalize::injected_latency(&output.stderr),
+            activated_flunks: normalize::activated_flunks(&output.stderr).len(),
+        }
+    }
+}
+
+// A command rerunning the launcher for only the test at `path`, with the seed
+// and the surge of a failing run pinned. The launcher's name is the one of the
+// thread libtest runs it on. There is none for a launcher with its own `main`,
+// which doesn't need one to be found.
+fn replay_command(seed: u64, launcher: Option<&str>, surge: isize, path: &Path) -> String {
+    let mut command = format!("{}={} {}={} cargo test", crate::env::SEED, seed, crate::env::SURGE, surge);
+    if let Some(launcher) = launcher {
+        command += &format!(" {}", launcher);
+    }
+    command += " --";
+    if let Some(name) = thread::current().name().filter(|name| *name != "main") {
+        command += &format!(" {}", name);
+    }
+    command += &format!(" kaos={}", path.display());
+    command
+}
+
+// Selects the target `exe` was built from among the crate's `targets`, by the
+// name of the binary without the hash cargo appends. Unit tests are built into
+// a binary named after the library, an integration test into one named after
+// the test.
+fn launcher_target(targets: &[TargetMetadata], exe: &Path) -> Option<String> {
+    let stem = exe.file_stem()?.to_str()?;
+    let stem = match stem.rsplit_once('-') {
+        Some((stem, hash)) if hash.chars().all(|c| c.is_ascii_hexdigit()) => stem,
+        _ => stem,
+    };
+    let named = |kind: &str| {
+        targets.iter().find(|target| {
+            target.name.replace('-', "_") == stem && target.kind.iter().any(|k| k == kind)
+        })
+    };
+    if let Some(test) = named("test") {
+        Some(format!("--test {}", test.name))
+    } else if let Some(bin) = named("bin") {
+        Some(format!("--bin {}", bin.name))
+    } else {
+        ["lib", "rlib", "dylib", "cdylib", "staticlib", "proc-macro"]
+            .iter()
+            .any(|kind| named(kind).is_some())
+            .then(|| "--lib".to_owned())
+    }
+}
+
+// Whether a run lasting `elapsed` beat the bar of `required` by less than
+// `margin` of it.
+fn thin_margin(required: Duration, elapsed: Duration, margin: f64) -> bool {
+    elapsed >= required && elapsed < required.mul_f64(1.0 + margin)
+}
+
+fn low_availability(expected: Duration, elapsed: Duration, latency: Duration) -> String {
+    let mut msg = format!(
+        "availability is low. Expected at least: {}, Found: {}",
+        format_duration(expected),
+        format_duration(elapsed)
+    );
+    if latency > Duration::from_secs(0) {
+        msg += &format!(", including {} of injected latency", format_duration(latency));
+    }
+    msg
+}
+
+// The binaries that run once around the suite.
+#[derive(Clone, Copy, PartialEq)]
+enum Fixture {
+    Setup,
+    Teardown,
+}
+
+impl Fixture {
+    fn as_str(self) -> &'static str {
+        match self {
+            Fixture::Setup => "setup",
+            Fixture::Teardown => "teardown",
+        }
+    }
+
+    fn name(self) -> Name {
+        Name(format!("kaos-{}", self.as_str()))
+    }
+}
+
 fn check_exists(path: &Path) -> Result<()> {
     if path.exists() {
         return Ok(());
@@ -347,16 +1456,79 @@ fn check_exists(path: &Path) -> Result<()> {
 #[derive(Debug)]
 struct ExpandedTest {
     name: Name,
+    index: usize,
     test: Test,
     error: Option<Error>,
+    // The build from before the tests started, used by the first attempt.
+    prebuilt: Option<Output>,
+}
+
+// The root of the crate under test, where `kaos.toml` is.
+fn source_dir() -> Result<PathBuf> {
+    env::var_os("CARGO_MANIFEST_DIR")
+        .map(PathBuf::from)
+        .ok_or(Error::ProjectDir)
+}
+
+// What watch mode looks at: the tests dir, `src`, the manifest and the
+// directory of every test, or the test alone when it sits at the crate root.
+// Directories inside another one are walked with it.
+fn watch_roots(source_dir: &Path, tests: &[PathBuf]) -> Vec<PathBuf> {
+    let mut roots = vec![
+        source_dir.join(ignore::TESTS_DIR),
+        source_dir.join("src"),
+        source_dir.join("Cargo.toml"),
+    ];
+    for test in tests {
+        match test.parent() {
+            Some(dir) if dir != source_dir => roots.push(dir.to_owned()),
+            _ => roots.push(test.clone()),
+        }
+    }
+    roots.sort();
+    roots.dedup();
+    let mut outermost: Vec<PathBuf> = Vec::new();
+    for root in roots {
+        // Sorted, a root comes after the ones it is inside of.
+        if !outermost.iter().any(|outer| root.starts_with(outer)) {
+            outermost.push(root);
+        }
+    }
+    outermost
+}
+
+// Gives the chaotic tests left to `kaos.toml` the options of the file.
+fn with_settings(tests: &[Test], settings: &Settings) -> Vec<Test> {
+    let mut resolved = Vec::new();
+    for test in tests {
+        let copies = match test.from_settings {
+            Some(FromSettings::Sweep) => settings.run_count(),
+            Some(FromSettings::MaxSurge) => 1,
+            None => {
+                resolved.push(test.clone());
+                continue;
+            }
+        };
+        let test = Test {
+            max_surge: settings.max_surge(),
+            ..test.clone()
+        };
+        resolved.extend(std::iter::repeat_n(test, copies));
+    }
+    resolved
 }
 
-fn expand_globs(tests: &[Test]) -> Vec<ExpandedTest> {
+fn expand_globs(tests: &[Test], ignore: &Ignore) -> Vec<ExpandedTest> {
     fn glob(pattern: &str) -> Result<Vec<PathBuf>> {
-        let mut paths = glob::glob(pattern)?
-            .map(|entry| entry.map_err(Error::from))
-            .collect::<Result<Vec<PathBuf>>>()?;
+        let mut paths = Vec::new();
+        for pattern in expand_braces(pattern) {
+            for entry in glob::glob(&pattern)? {
+                paths.push(entry?);
+            }
+        }
+        // Alternatives may overlap, like `{net,*}`.
         paths.sort();
+        paths.dedup();
         Ok(paths)
     }
 
@@ -365,27 +1537,41 @@ fn expand_globs(tests: &[Test]) -> Vec<ExpandedTest> {
     }
 
     let mut vec = Vec::new();
+    // The registration that first covered each path, by pattern or by name.
+    // Later registrations skip it, the copies of a chaotic test share theirs.
+    let mut covered = Map::new();
+    let mut first = |path: &Path, registration| {
+        *covered.entry(path.to_owned()).or_insert(registration) == registration
+    };
 
     for test in tests {
+        if ignore.is_ignored(&test.path) {
+            continue;
+        }
         let mut expanded = ExpandedTest {
-            name: bin_name(vec.len()),
+            name: bin_name(0),
+            index: 0,
             test: test.clone(),
             error: None,
+            prebuilt: None,
         };
         if let Some(utf8) = test.path.to_str() {
-            if utf8.contains('*') {
+            if utf8.contains('*') || utf8.contains('{') {
                 match glob(utf8) {
                     Ok(paths) => {
                         for path in paths {
+                            if ignore.is_ignored(&path) || !first(&path, test.registration) {
+                                continue;
+                            }
                             vec.push(ExpandedTest {
-                                name: bin_name(vec.len()),
                                 test: Test {
                                     path,
-                                    duration: expanded.test.duration,
-                                    max_surge: expanded.test.max_surge,
-                                    expected: expanded.test.expected,
+                                    ..expanded.test.clone()
                                 },
+                                name: bin_name(0),
+                                index: 0,
                                 error: None,
+                                prebuilt: None,
                             });
                         }
                         continue;
@@ -394,25 +1580,222 @@ fn expand_globs(tests: &[Test]) -> Vec<ExpandedTest> {
                 }
             }
         }
-        vec.push(expanded);
+        if first(&test.path, test.registration) {
+            vec.push(expanded);
+        }
     }
 
+    // Bins are numbered in the order of the paths, whatever order the
+    // registrations came in. Copies of a chaotic test stay next to each other.
+    vec.sort_by(|a, b| a.test.path.cmp(&b.test.path));
+    for (index, expanded) in vec.iter_mut().enumerate() {
+        expanded.name = bin_name(index);
+        expanded.index = index;
+    }
     vec
 }
 
+// Expands `{a,b}` alternatives, which the glob crate doesn't support, into one
+// pattern per alternative. Alternatives nest, braces without a comma are kept
+// as they are.
+fn expand_braces(pattern: &str) -> Vec<String> {
+    let mut depth = 0;
+    let mut open = 0;
+    let mut commas = Vec::new();
+    for (i, c) in pattern.char_indices() {
+        match c {
+            '{' => {
+                if depth == 0 {
+                    open = i;
+                    commas.clear();
+                }
+                depth += 1;
+            }
+            ',' if depth == 1 => commas.push(i),
+            '}' if depth > 0 => {
+                depth -= 1;
+                if depth == 0 && !commas.is_empty() {
+                    let (prefix, suffix) = (&pattern[..open], &pattern[i + 1..]);
+                    let mut bounds = vec![open];
+                    bounds.extend(&commas);
+                    bounds.push(i);
+                    return bounds
+                        .windows(2)
+                        .flat_map(|alternative| {
+                            let alternative = &pattern[alternative[0] + 1..alternative[1]];
+                            expand_braces(&format!("{}{}{}", prefix, alternative, suffix))
+                        })
+                        .collect();
+                }
+            }
+            _ => {}
+        }
+    }
+    vec![pattern.to_owned()]
+}
+
 impl ExpandedTest {
-    fn run(self, project: &mut Project) -> Result<()> {
+    // A copy to run again, unless the test failed to expand. One that failed
+    // to build may build once its file is fixed.
+    fn again(&self) -> Option<ExpandedTest> {
         match self.error {
-            None => self.test.run(project, &self.name),
+            None | Some(Error::NotBuilt) => {}
+            Some(_) => return None,
+        }
+        Some(ExpandedTest {
+            name: self.name.clone(),
+            index: self.index,
+            test: self.test.clone(),
+            error: None,
+            prebuilt: None,
+        })
+    }
+
+    fn run(mut self, project: &Project) -> TestResult {
+        let mut result = TestResult {
+            name: self.name.0.clone(),
+            path: self.test.path.clone(),
+            expected: self.test.expected,
+            passed: true,
+            failure: None,
+            elapsed: Duration::from_secs(0),
+            elapsed_stats: None,
+            attempts: 1,
+            runs: Vec::new(),
+            infra_failure: false,
+            description: self.test.description.clone(),
+        };
+
+        events::emit(Event::TestStarted {
+            test: &self.name.0,
+            path: &self.test.path,
+        });
+        let now = Instant::now();
+        let outcome = match self.error {
+            None => loop {
+                let outcome = self.test.run(
+                    project,
+                    &self.name,
+                    self.index,
+                    result.attempts,
+                    self.prebuilt.take(),
+                    &mut result.runs,
+                );
+                match outcome {
+                    Err(err) if result.attempts <= project.retries => {
+                        message::retrying(err, result.attempts, project.retries);
+                        result.attempts += 1;
+                    }
+                    Ok(()) if result.attempts > 1 => {
+                        message::passed_after_retries(result.attempts);
+                        break Ok(());
+                    }
+                    outcome => break outcome,
+                }
+            },
             Some(error) => {
                 let show_expected = false;
                 message::begin_test(&self.test, show_expected);
                 Err(error)
             }
+        };
+        result.elapsed = now.elapsed();
+        if let Expected::Chaotic = result.expected {
+            result.elapsed_stats = ElapsedStats::of(&result.runs);
+        }
+
+        match outcome {
+            Ok(()) => events::emit(Event::TestPassed { test: &result.name }),
+            Err(err) => {
+                let failure = err.to_string();
+                events::emit(Event::TestFailed {
+                    test: &result.name,
+                    error: &failure,
+                });
+                result.passed = false;
+                result.failure = Some(failure);
+                result.infra_failure = err.is_infra();
+                message::test_fail(err);
+                if let Some(description) = &self.test.description {
+                    message::failed_scenario(&self.test.path, description);
+                }
+            }
         }
+        result
     }
 }
 
+// Nothing ran, so the harness is at fault.
+fn prepare_failed(err: Error, exit_codes: bool) -> ! {
+    message::prepare_fail(err);
+    if exit_codes {
+        std::process::exit(report::EXIT_INFRA);
+    }
+    panic!("tests failed");
+}
+
+// Runs the test between the user's hooks. The lock is only held while a hook
+// runs so parallel tests don't wait on each other.
+fn run_with_hooks(test: ExpandedTest, project: &Project, hooks: &Mutex<Hooks>) -> TestResult {
+    let path = test.test.path.clone();
+    let lock = || hooks.lock().unwrap_or_else(PoisonError::into_inner);
+
+    if let Some(before_each) = &mut lock().before_each {
+        before_each(&path);
+    }
+    let result = test.run(project);
+    if let Some(after_each) = &mut lock().after_each {
+        after_each(&path);
+    }
+    result
+}
+
+// Runs tests on `project.jobs` worker threads. Each test's output is held back
+// and printed in one piece once it finishes. Results keep the order of `tests`.
+// With fail fast no new tests start after a failure, running ones still finish.
+fn run_parallel(
+    tests: Vec<ExpandedTest>,
+    project: &Project,
+    hooks: &Mutex<Hooks>,
+) -> Vec<TestResult> {
+    let len = tests.len();
+    let queue = Mutex::new(tests.into_iter().enumerate());
+    let results = Mutex::new(Vec::with_capacity(len));
+    let failed = AtomicBool::new(false);
+
+    thread::scope(|scope| {
+        for _ in 0..project.jobs.min(len) {
+            scope.spawn(|| loop {
+                if project.fail_fast && failed.load(Ordering::SeqCst) {
+                    break;
+                }
+                let next = queue.lock().unwrap_or_else(PoisonError::into_inner).next();
+                let (i, test) = match next {
+                    Some(next) => next,
+                    None => break,
+                };
+
+                term::capture();
+                let result = run_with_hooks(test, project, hooks);
+                term::release();
+
+                if !result.passed {
+                    failed.store(true, Ordering::SeqCst);
+                }
+
+                results
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .push((i, result));
+            });
+        }
+    });
+
+    let mut results = results.into_inner().unwrap_or_else(PoisonError::into_inner);
+    results.sort_by_key(|(i, _)| *i);
+    results.into_iter().map(|(_, result)| result).collect()
+}
+
 // Filter which test cases are run by kaos.
 //
 //     $ cargo test -- ui kaos=tuple_structs.rs
@@ -422,26 +1805,292 @@ impl ExpandedTest {
 // Cargo to run the test at all. The next argument starting with `kaos=`
 // provides a filename filter. Only test cases whose filename contains the
 // filter string will be run.
+//
+// Tests can also be selected by tag, which intersects with filename filters.
+//
+//     $ cargo test -- ui kaostag=network
 fn filter(tests: &mut Vec<ExpandedTest>) {
-    let filters = env::args_os()
+    let args = env::args_os()
         .flat_map(OsString::into_string)
-        .filter_map(|mut arg| {
-            const PREFIX: &str = "kaos=";
-            if arg.starts_with(PREFIX) && arg != PREFIX {
-                Some(arg.split_off(PREFIX.len()))
-            } else {
-                None
-            }
-        })
         .collect::<Vec<String>>();
+    let filters = arg_values(&args, "kaos=");
+    let files = arg_values(&args, "kaosfile=");
+    let tags = arg_values(&args, "kaostag=");
 
-    if filters.is_empty() {
-        return;
+    if !filters.is_empty() {
+        tests.retain(|t| {
+            filters
+                .iter()
+                .any(|f| t.test.path.to_string_lossy().contains(f))
+        });
     }
 
-    tests.retain(|t| {
-        filters
-            .iter()
-            .any(|f| t.test.path.to_string_lossy().contains(f))
+    if !files.is_empty() {
+        tests.retain(|t| {
+            t.test
+                .path
+                .file_name()
+                .is_some_and(|name| files.iter().any(|f| name == *f))
+        });
+    }
+
+    if !tags.is_empty() {
+        tests.retain(|t| t.test.tags.iter().any(|tag| tags.contains(&tag.as_str())));
+    }
+}
+
+// Takes out the tests limited to other operating systems than this one.
+fn unsupported(tests: &mut Vec<ExpandedTest>) -> Vec<ExpandedTest> {
+    let (supported, unsupported) = std::mem::take(tests).into_iter().partition(|t| {
+        t.test.platforms.is_empty() || t.test.platforms.iter().any(|os| os.is_current())
     });
+    *tests = supported;
+    unsupported
+}
+
+fn arg_values<'a>(args: &'a [String], prefix: &str) -> Vec<&'a str> {
+    args.iter()
+        .filter(|arg| arg.starts_with(prefix) && arg.as_str() != prefix)
+        .map(|arg| &arg[prefix.len()..])
+        .collect()
+}
+
+// A chaotic test of `path` with nothing else set, tests override the fields
+// they exercise.
+#[cfg(test)]
+fn test_of(path: &str) -> Test {
+    Test {
+        path: PathBuf::from(path),
+        duration: None,
+        max_duration: None,
+        min_surge: 0,
+        max_surge: 1,
+        expected: Expected::Chaotic,
+        distribution: Distribution::Uniform,
+        tags: Vec::new(),
+        features: Vec::new(),
+        env: Map::new(),
+        expected_output: None,
+        supervision: None,
+        slo: None,
+        convergence: None,
+        soak: None,
+        from_settings: None,
+        description: None,
+        platforms: Vec::new(),
+        registration: 0,
+    }
+}
+
+#[test]
+fn test_replay_command() {
+    let command = replay_command(12345, Some("--lib"), 420, Path::new("kaos-tests/chaos.rs"));
+    assert_eq!(
+        command,
+        "KAOS_SEED=12345 KAOS_SURGE=420 cargo test --lib -- run::test_replay_command kaos=kaos-tests/chaos.rs"
+    );
+
+    let command = thread::spawn(|| replay_command(1, None, 2, Path::new("chaos.rs")))
+        .join()
+        .unwrap();
+    assert_eq!(command, "KAOS_SEED=1 KAOS_SURGE=2 cargo test -- kaos=chaos.rs");
+}
+
+#[test]
+fn test_watch_roots() {
+    let crate_dir = Path::new("/work/service");
+    let tests = [
+        crate_dir.join("kaos-tests/net/partition.rs"),
+        crate_dir.join("tests/chaos/restart.rs"),
+        crate_dir.join("tests/chaos/crash.rs"),
+        crate_dir.join("smoke.rs"),
+    ];
+    assert_eq!(
+        watch_roots(crate_dir, &tests),
+        ["Cargo.toml", "kaos-tests", "smoke.rs", "src", "tests/chaos"]
+            .iter()
+            .map(|root| crate_dir.join(root))
+            .collect::<Vec<_>>(),
+    );
+}
+
+#[test]
+fn test_launcher_target() {
+    let target = |name: &str, kind: &str| TargetMetadata {
+        name: name.to_owned(),
+        kind: vec![kind.to_owned()],
+    };
+    let exe = Path::new("target/debug/deps/chaos_tests-0123456789abcdef");
+
+    let found = launcher_target(&[target("chaos", "lib"), target("chaos-tests", "test")], exe);
+    assert_eq!(found.as_deref(), Some("--test chaos-tests"));
+    let found = launcher_target(&[target("chaos_tests", "lib"), target("chaos", "test")], exe);
+    assert_eq!(found.as_deref(), Some("--lib"));
+    assert_eq!(launcher_target(&[target("chaos", "test")], exe), None);
+}
+
+#[test]
+fn test_thin_margin() {
+    let bar = Duration::from_secs(2);
+    assert!(thin_margin(bar, Duration::from_millis(2100), 0.1));
+    assert!(!thin_margin(bar, Duration::from_millis(2200), 0.1));
+    assert!(!thin_margin(bar, Duration::from_millis(3000), 0.1));
+    // Runs that didn't last fail rather than warn.
+    assert!(!thin_margin(bar, Duration::from_millis(1900), 0.1));
+}
+
+#[test]
+fn test_weighted_surges() {
+    let mut runner = TestRunner::new_with_rng(ProptestConfig::default(), flunk::rng_from_seed(7));
+    for distribution in &[Distribution::Exponential, Distribution::FrontLoaded] {
+        let strategy = weighted(100..1100, *distribution);
+        let samples = (0..1000)
+            .map(|_| strategy.new_tree(&mut runner).unwrap().current())
+            .collect::<Vec<_>>();
+        assert!(samples.iter().all(|surge| (100..1100).contains(surge)));
+        let low = samples.iter().filter(|surge| **surge < 350).count();
+        assert!(low > 450, "{:?} put {} of 1000 samples in the lowest quarter", distribution, low);
+    }
+}
+
+#[test]
+fn test_expand_braces() {
+    assert_eq!(
+        expand_braces("kaos-tests/{net,disk}/*.rs"),
+        ["kaos-tests/net/*.rs", "kaos-tests/disk/*.rs"]
+    );
+    assert_eq!(
+        expand_braces("{a,b{c,d}}-{1,2}"),
+        ["a-1", "a-2", "bc-1", "bc-2", "bd-1", "bd-2"]
+    );
+    assert_eq!(expand_braces("{net}/{a,b"), ["{net}/{a,b"]);
+    assert_eq!(expand_braces("kaos-tests/*.rs"), ["kaos-tests/*.rs"]);
+}
+
+#[test]
+fn test_with_settings() {
+    let settings = Settings {
+        run_count: Some(3),
+        max_surge: Some(50),
+        ..Settings::default()
+    };
+    let tests = [
+        Test {
+            from_settings: Some(FromSettings::Sweep),
+            ..test_of("kaos-tests/default.rs")
+        },
+        Test {
+            from_settings: Some(FromSettings::MaxSurge),
+            ..test_of("kaos-tests/slo.rs")
+        },
+        test_of("kaos-tests/set.rs"),
+    ];
+    let resolved = with_settings(&tests, &settings)
+        .into_iter()
+        .map(|test| (test.path, test.max_surge))
+        .collect::<Vec<_>>();
+    let expected = [
+        ("kaos-tests/default.rs", 50),
+        ("kaos-tests/default.rs", 50),
+        ("kaos-tests/default.rs", 50),
+        ("kaos-tests/slo.rs", 50),
+        ("kaos-tests/set.rs", 1),
+    ]
+    .iter()
+    .map(|&(path, max_surge)| (PathBuf::from(path), max_surge))
+    .collect::<Vec<_>>();
+    assert_eq!(resolved, expected);
+}
+
+#[test]
+fn test_expand_globs_dedup() {
+    let test = |path, registration, max_surge| Test {
+        max_surge,
+        registration,
+        ..test_of(path)
+    };
+    let tests = [
+        test("src/{run,report}.rs", 0, 10),
+        test("src/{run,report}.rs", 0, 10),
+        test("src/{run,lib}.rs", 2, 20),
+        // Named after a pattern covered it.
+        test("src/lib.rs", 3, 30),
+        // Named twice, then covered by a pattern.
+        test("src/error.rs", 4, 40),
+        test("src/error.rs", 5, 50),
+        test("src/{error,cargo}.rs", 6, 60),
+    ];
+    let expanded = expand_globs(&tests, &Ignore::default())
+        .into_iter()
+        .map(|e| (e.name.0, e.test.path, e.test.max_surge))
+        .collect::<Vec<_>>();
+    let expected = [
+        ("kaos000", "src/cargo.rs", 60),
+        ("kaos001", "src/error.rs", 40),
+        ("kaos002", "src/lib.rs", 20),
+        ("kaos003", "src/report.rs", 10),
+        ("kaos004", "src/report.rs", 10),
+        ("kaos005", "src/run.rs", 10),
+        ("kaos006", "src/run.rs", 10),
+    ]
+    .iter()
+    .map(|&(name, path, max_surge)| (name.to_owned(), PathBuf::from(path), max_surge))
+    .collect::<Vec<_>>();
+    assert_eq!(expanded, expected);
+}
+
+#[test]
+fn test_unsupported() {
+    use super::Os;
+
+    let test = |path: &str, platforms: Vec<Os>| ExpandedTest {
+        name: Name(path.to_owned()),
+        index: 0,
+        test: Test {
+            duration: Some(Duration::from_secs(1)),
+            max_surge: 0,
+            expected: Expected::Available,
+            platforms,
+            ..test_of(path)
+        },
+        error: None,
+        prebuilt: None,
+    };
+    let current = [Os::Linux, Os::MacOs, Os::Windows]
+        .iter()
+        .copied()
+        .find(|os| os.is_current());
+    let other = if current == Some(Os::Linux) { Os::Windows } else { Os::Linux };
+
+    let mut tests = vec![test("anywhere", Vec::new()), test("elsewhere", vec![other])];
+    tests.extend(current.map(|os| test("here", vec![other, os])));
+    let skipped = unsupported(&mut tests);
+    let paths = |tests: &[ExpandedTest]| tests.iter().map(|t| t.name.0.clone()).collect::<Vec<_>>();
+    let mut expected = vec!["anywhere".to_owned()];
+    expected.extend(current.map(|_| "here".to_owned()));
+    assert_eq!(paths(&tests), expected);
+    assert_eq!(paths(&skipped), ["elsewhere"]);
+}
+
+#[test]
+fn test_surge_range() {
+    let test = |min_surge, max_surge| Test {
+        min_surge,
+        max_surge,
+        ..test_of("kaos-tests/huge.rs")
+    };
+    assert_eq!(test(10, 20).surge_range().unwrap(), 10..20);
+    match test(0, usize::MAX).surge_range() {
+        Err(Error::SurgeTooLarge(max_surge)) => assert_eq!(max_surge, usize::MAX),
+        other => panic!("expected SurgeTooLarge, got {:?}", other),
+    }
+
+    // The largest budget samples without materializing the range.
+    let range = test(0, isize::MAX as usize).surge_range().unwrap();
+    let mut runner = TestRunner::new_with_rng(ProptestConfig::default(), flunk::rng_from_seed(7));
+    for boundary in &[None, Some(isize::MAX - 1)] {
+        let strategy = surges(range.clone(), *boundary, Distribution::Exponential);
+        let surge = strategy.new_tree(&mut runner).unwrap().current();
+        assert!(range.contains(&surge));
+    }
 }
@@ -2,29 +2,69 @@ use std::env;
 use std::process::Command;
 
 const RUSTFLAGS: &str = "RUSTFLAGS";
+const ENCODED_RUSTFLAGS: &str = "CARGO_ENCODED_RUSTFLAGS";
 const IGNORED_LINTS: &[&str] = &["dead_code"];
 
-pub fn make_vec() -> Vec<&'static str> {
-    let mut rustflags = vec!["--cfg", "kaos"];
+// The user's flags followed by the ones kaos needs, for the generated
+// `.cargo/config`.
+pub fn make_vec() -> Vec<String> {
+    let mut rustflags = from_env();
+    rustflags.push("--cfg".to_owned());
+    rustflags.push("kaos".to_owned());
 
     for &lint in IGNORED_LINTS {
-        rustflags.push("-A");
-        rustflags.push(lint);
+        rustflags.push("-A".to_owned());
+        rustflags.push(lint.to_owned());
     }
 
     rustflags
 }
 
+// Flags in the environment would replace the ones of the generated config, so
+// they are merged into it instead.
 pub fn set_env(cmd: &mut Command) {
-    let mut rustflags = match env::var_os(RUSTFLAGS) {
-        Some(rustflags) => rustflags,
-        None => return,
-    };
-
-    for flag in make_vec() {
-        rustflags.push(" ");
-        rustflags.push(flag);
+    cmd.env_remove(RUSTFLAGS);
+    cmd.env_remove(ENCODED_RUSTFLAGS);
+}
+
+// For commands run away from the generated project, which don't pick up its
+// `.cargo/config`. Passes the same flags so nothing is rebuilt.
+pub fn set_encoded_env(cmd: &mut Command) {
+    cmd.env(ENCODED_RUSTFLAGS, make_vec().join("\x1f"));
+}
+
+// Like cargo, prefers `CARGO_ENCODED_RUSTFLAGS`, separated by 0x1f, over the
+// space separated `RUSTFLAGS`.
+fn from_env() -> Vec<String> {
+    if let Ok(encoded) = env::var(ENCODED_RUSTFLAGS) {
+        return parse_encoded(&encoded);
     }
+    env::var(RUSTFLAGS)
+        .map(|rustflags| parse(&rustflags))
+        .unwrap_or_default()
+}
+
+fn parse(rustflags: &str) -> Vec<String> {
+    rustflags.split_whitespace().map(str::to_owned).collect()
+}
+
+fn parse_encoded(encoded: &str) -> Vec<String> {
+    encoded
+        .split('\x1f')
+        .filter(|flag| !flag.is_empty())
+        .map(str::to_owned)
+        .collect()
+}
 
-    cmd.env(RUSTFLAGS, rustflags);
+#[test]
+fn test_parse_rustflags() {
+    assert_eq!(
+        parse(" -C target-cpu=native  -Zsanitizer=address "),
+        ["-C", "target-cpu=native", "-Zsanitizer=address"]
+    );
+    assert_eq!(
+        parse_encoded("-C\x1flink-arg=-Wl,--as-needed two"),
+        ["-C", "link-arg=-Wl,--as-needed two"]
+    );
+    assert!(parse_encoded("").is_empty());
 }
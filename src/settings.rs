@@ -0,0 +1,71 @@
+use serde::Deserialize;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::error::{Error, Result};
+
+/// Shared configuration of a crate's chaos tests, relative to the crate root.
+pub const FILE_NAME: &str = "kaos.toml";
+
+const RUN_COUNT: usize = 10;
+pub const MAX_SURGE: usize = 1000;
+
+/// Defaults read from `kaos.toml`. Options set in code take precedence.
+#[derive(Deserialize, Default, Debug, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Settings {
+    pub run_count: Option<usize>,
+    pub max_surge: Option<usize>,
+    pub seed: Option<u64>,
+    pub jobs: Option<usize>,
+    pub report: Option<PathBuf>,
+    pub junit: Option<PathBuf>,
+}
+
+impl Settings {
+    /// Reads `kaos.toml` from `dir`. A missing file sets nothing.
+    pub fn load(dir: &Path) -> Result<Settings> {
+        let path = dir.join(FILE_NAME);
+        match fs::read_to_string(&path) {
+            Ok(contents) => parse(&contents).map_err(|err| Error::Settings(path, err)),
+            Err(ref err) if err.kind() == io::ErrorKind::NotFound => Ok(Settings::default()),
+            Err(err) => Err(Error::Open(path, err)),
+        }
+    }
+
+    pub fn run_count(&self) -> usize {
+        self.run_count.unwrap_or(RUN_COUNT)
+    }
+
+    pub fn max_surge(&self) -> usize {
+        self.max_surge.unwrap_or(MAX_SURGE)
+    }
+}
+
+// Surges are sampled from zero up to `max_surge`, which can't be empty.
+fn parse(contents: &str) -> std::result::Result<Settings, toml::de::Error> {
+    let settings: Settings = toml::from_str(contents)?;
+    if settings.max_surge == Some(0) {
+        return Err(serde::de::Error::custom("max_surge must be positive"));
+    }
+    Ok(settings)
+}
+
+#[test]
+fn test_settings() {
+    let settings: Settings = toml::from_str(
+        r#"
+run_count = 25
+seed = 42
+junit = "target/kaos-junit.xml"
+"#,
+    )
+    .unwrap();
+    assert_eq!(settings.run_count(), 25);
+    assert_eq!(settings.max_surge(), MAX_SURGE);
+    assert_eq!(settings.seed, Some(42));
+    assert_eq!(settings.junit, Some(PathBuf::from("target/kaos-junit.xml")));
+    assert!(toml::from_str::<Settings>("runcount = 25").is_err());
+    assert!(parse("max_surge = 0").is_err());
+}
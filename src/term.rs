@@ -1,37 +1,94 @@
 use lazy_static::lazy_static;
+use std::cell::RefCell;
 use std::io::{Result, Write};
-use std::sync::{Mutex, MutexGuard, PoisonError};
-use termcolor::{Color, ColorChoice, ColorSpec, StandardStream as Stream, WriteColor};
+use std::sync::{Mutex, PoisonError};
+use termcolor::{
+    Buffer, BufferWriter, Color, ColorChoice, ColorSpec, StandardStream as Stream, WriteColor,
+};
 
 lazy_static! {
     static ref TERM: Mutex<Term> = Mutex::new(Term::new());
 }
 
-pub fn lock() -> MutexGuard<'static, Term> {
-    TERM.lock().unwrap_or_else(PoisonError::into_inner)
+thread_local! {
+    // Output of a test running on a worker thread, held back until the test
+    // finishes so that concurrent tests don't interleave.
+    static CAPTURED: RefCell<Option<Term>> = const { RefCell::new(None) };
+}
+
+pub fn with<R>(f: impl FnOnce(&mut Term) -> R) -> R {
+    CAPTURED.with(|captured| match captured.borrow_mut().as_mut() {
+        Some(term) => f(term),
+        None => f(&mut TERM.lock().unwrap_or_else(PoisonError::into_inner)),
+    })
+}
+
+// An invalid KAOS_COLOR is reported when the run is prepared.
+fn color_choice() -> ColorChoice {
+    crate::env::color().unwrap_or(ColorChoice::Auto)
+}
+
+pub fn capture() {
+    let mut term = Term::new();
+    term.buffer = Some(BufferWriter::stderr(color_choice()).buffer());
+    CAPTURED.with(|captured| *captured.borrow_mut() = Some(term));
+}
+
+pub fn release() {
+    let term = CAPTURED.with(|captured| captured.borrow_mut().take());
+    if let Some(Term {
+        buffer: Some(buffer),
+        ..
+    }) = term
+    {
+        let _guard = TERM.lock().unwrap_or_else(PoisonError::into_inner);
+        let _ = BufferWriter::stderr(color_choice()).print(&buffer);
+    }
+}
+
+// Columns of the terminal kaos writes to, absent when it doesn't write to one.
+#[cfg(unix)]
+pub fn width() -> Option<usize> {
+    let mut size: libc::winsize = unsafe { std::mem::zeroed() };
+    let known = unsafe {
+        libc::isatty(libc::STDERR_FILENO) == 1
+            && libc::ioctl(libc::STDERR_FILENO, libc::TIOCGWINSZ, &mut size) == 0
+    };
+    if known && size.ws_col > 0 {
+        Some(size.ws_col as usize)
+    } else {
+        None
+    }
+}
+
+#[cfg(not(unix))]
+pub fn width() -> Option<usize> {
+    None
 }
 
 pub fn bold() {
-    lock().set_color(ColorSpec::new().set_bold(true));
+    with(|term| term.set_color(ColorSpec::new().set_bold(true)));
 }
 
 pub fn color(color: Color) {
-    lock().set_color(ColorSpec::new().set_fg(Some(color)));
+    with(|term| term.set_color(ColorSpec::new().set_fg(Some(color))));
 }
 
 pub fn bold_color(color: Color) {
-    lock().set_color(ColorSpec::new().set_bold(true).set_fg(Some(color)));
+    with(|term| term.set_color(ColorSpec::new().set_bold(true).set_fg(Some(color))));
 }
 
 pub fn reset() {
-    lock().reset();
+    with(|term| term.reset());
 }
 
 #[deny(unused_macros)]
 macro_rules! print {
     ($($args:tt)*) => {{
         use std::io::Write;
-        let _ = std::write!($crate::term::lock(), $($args)*);
+        $crate::term::with(|term| {
+            let _ = std::write!(term, $($args)*);
+        });
     }};
 }
 
@@ -39,13 +96,16 @@ macro_rules! print {
 macro_rules! println {
     ($($args:tt)*) => {{
         use std::io::Write;
-        let _ = std::writeln!($crate::term::lock(), $($args)*);
+        $crate::term::with(|term| {
+            let _ = std::writeln!(term, $($args)*);
+        });
     }};
 }
 
 pub struct Term {
     spec: ColorSpec,
     stream: Stream,
+    buffer: Option<Buffer>,
     start_of_line: bool,
 }
 
@@ -53,11 +113,19 @@ impl Term {
     fn new() -> Self {
         Term {
             spec: ColorSpec::new(),
-            stream: Stream::stderr(ColorChoice::Auto),
+            stream: Stream::stderr(color_choice()),
+            buffer: None,
             start_of_line: true,
         }
     }
 
+    fn out(&mut self) -> &mut dyn WriteColor {
+        match &mut self.buffer {
+            Some(buffer) => buffer,
+            None => &mut self.stream,
+        }
+    }
+
     fn set_color(&mut self, spec: &ColorSpec) {
         if self.spec != *spec {
             self.spec = spec.clone();
@@ -67,7 +135,7 @@ impl Term {
 
     fn reset(&mut self) {
         self.spec = ColorSpec::new();
-        let _ = self.stream.reset();
+        let _ = self.out().reset();
     }
 }
 
@@ -76,22 +144,23 @@ impl Write for Term {
     // across output lines.
     fn write(&mut self, mut buf: &[u8]) -> Result<usize> {
         if self.spec.is_none() {
-            return self.stream.write(buf);
+            return self.out().write(buf);
         }
 
         let len = buf.len();
         while !buf.is_empty() {
             if self.start_of_line {
-                let _ = self.stream.set_color(&self.spec);
+                let spec = self.spec.clone();
+                let _ = self.out().set_color(&spec);
             }
             match buf.iter().position(|byte| *byte == b'\n') {
                 Some(line_len) => {
-                    self.stream.write(&buf[..line_len + 1])?;
+                    self.out().write(&buf[..line_len + 1])?;
                     self.start_of_line = true;
                     buf = &buf[line_len + 1..];
                 }
                 None => {
-                    self.stream.write(buf)?;
+                    self.out().write(buf)?;
                     self.start_of_line = false;
                     break;
                 }
@@ -101,6 +170,6 @@ impl Write for Term {
     }
 
     fn flush(&mut self) -> Result<()> {
-        self.stream.flush()
+        self.out().flush()
     }
 }
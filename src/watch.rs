@@ -0,0 +1,108 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+// How often to look for changes. Polling keeps watch mode free of platform
+// specific notification APIs.
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Modification times of the files under a set of directories, or of files
+/// watched on their own.
+#[derive(Debug, Default)]
+pub struct Snapshot {
+    files: BTreeMap<PathBuf, SystemTime>,
+}
+
+impl Snapshot {
+    pub fn take(roots: &[PathBuf]) -> Snapshot {
+        let mut snapshot = Snapshot::default();
+        for root in roots {
+            match fs::metadata(root) {
+                Ok(metadata) if metadata.is_file() => snapshot.insert(root.clone(), &metadata),
+                _ => snapshot.walk(root),
+            }
+        }
+        snapshot
+    }
+
+    fn insert(&mut self, path: PathBuf, metadata: &fs::Metadata) {
+        if let Ok(modified) = metadata.modified() {
+            self.files.insert(path, modified);
+        }
+    }
+
+    fn walk(&mut self, dir: &Path) {
+        let entries = match fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(_) => return,
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let metadata = match entry.metadata() {
+                Ok(metadata) => metadata,
+                Err(_) => continue,
+            };
+            if metadata.is_dir() {
+                self.walk(&path);
+            } else {
+                self.insert(path, &metadata);
+            }
+        }
+    }
+
+    /// Files added, removed or modified since `earlier`, sorted.
+    pub fn changes(&self, earlier: &Snapshot) -> Vec<PathBuf> {
+        let mut changed = self
+            .files
+            .iter()
+            .filter(|(path, modified)| earlier.files.get(*path) != Some(modified))
+            .map(|(path, _)| path.clone())
+            .collect::<Vec<_>>();
+        changed.extend(
+            earlier
+                .files
+                .keys()
+                .filter(|path| !self.files.contains_key(*path))
+                .cloned(),
+        );
+        changed.sort();
+        changed
+    }
+
+    /// Blocks until something under `roots` changes, returning what did.
+    pub fn wait(&mut self, roots: &[PathBuf]) -> Vec<PathBuf> {
+        loop {
+            thread::sleep(POLL_INTERVAL);
+            let now = Snapshot::take(roots);
+            let changed = now.changes(self);
+            if !changed.is_empty() {
+                *self = now;
+                return changed;
+            }
+        }
+    }
+}
+
+#[test]
+fn test_changes() {
+    let at = |secs| SystemTime::UNIX_EPOCH + Duration::from_secs(secs);
+    let snapshot = |files: &[(&str, u64)]| Snapshot {
+        files: files
+            .iter()
+            .map(|&(path, secs)| (PathBuf::from(path), at(secs)))
+            .collect(),
+    };
+
+    let earlier = snapshot(&[("kaos-tests/a.rs", 1), ("kaos-tests/b.rs", 1), ("src/lib.rs", 1)]);
+    let now = snapshot(&[("kaos-tests/a.rs", 1), ("kaos-tests/c.rs", 2), ("src/lib.rs", 3)]);
+    assert_eq!(
+        now.changes(&earlier),
+        ["kaos-tests/b.rs", "kaos-tests/c.rs", "src/lib.rs"]
+            .iter()
+            .map(PathBuf::from)
+            .collect::<Vec<_>>(),
+    );
+    assert!(now.changes(&now).is_empty());
+}